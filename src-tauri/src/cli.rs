@@ -1,5 +1,16 @@
-use std::path::PathBuf;
-use crate::project::package::Package;
+use log::{error, info, warn};
+use std::path::{Path, PathBuf};
+use crate::{
+  project::{
+    define::Offset,
+    package::Package,
+    validation::{log_issues, Severity},
+  },
+  racekeys,
+  settings::{ExportSettings, ValidationSettings, SETTINGS},
+};
+
+const PROJECT_EXTENSION: &str = ".slsb.json";
 
 pub fn convert(
   args: std::collections::HashMap<String, tauri_plugin_cli::ArgData>,
@@ -12,7 +23,7 @@ pub fn convert(
       return Err("input slal file is invalid".to_string());
   }
 
-  let mut out_path = match &args.get("out").unwrap().value {
+  let out_path = match &args.get("out").unwrap().value {
       serde_json::Value::String(value) => PathBuf::from(value),
       _ => return Err("output dir not provided".to_string()),
   };
@@ -20,17 +31,88 @@ pub fn convert(
       return Err("output dir is invalid".to_string());
   }
 
-  out_path.push(in_path.file_stem().unwrap());
+  convert_file(in_path, &out_path).map(|_| ())
+}
+
+/// Convert a single SLAL file into a project file in the output dir, returns the path written
+fn convert_file(in_path: PathBuf, out_dir: &Path) -> Result<PathBuf, String> {
+  let mut out_path = out_dir.join(in_path.file_stem().unwrap());
   out_path.set_extension("slsb.json");
   println!("Converting {} to {}", in_path.display(), out_path.display());
 
   let mut project = Package::from_slal(in_path)?;
-  project.write(out_path.clone())
+  project.write(out_path.clone())?;
+  Ok(out_path)
+}
+
+pub fn convert_all(
+  args: std::collections::HashMap<String, tauri_plugin_cli::ArgData>,
+) -> Result<(), String> {
+  let in_dir = match &args.get("in").unwrap().value {
+      serde_json::Value::String(value) => PathBuf::from(value),
+      _ => return Err("input dir not provided".to_string()),
+  };
+  if !in_dir.exists() || !in_dir.is_dir() {
+      return Err("input dir is invalid".to_string());
+  }
+  let out_dir = match &args.get("out").unwrap().value {
+      serde_json::Value::String(value) => PathBuf::from(value),
+      _ => return Err("output dir not provided".to_string()),
+  };
+  std::fs::create_dir_all(&out_dir).map_err(|e| e.to_string())?;
+  let format = |name: &str, default: &str| match args.get(name).map(|arg| &arg.value) {
+    Some(serde_json::Value::String(value)) => value.to_lowercase(),
+    _ => default.to_string(),
+  };
+  let (from, to) = (format("from", "slal"), format("to", "slsb"));
+  if from != "slal" || to != "slsb" {
+    return Err(format!(
+      "Cannot convert from {} to {}, only slal to slsb is supported",
+      from, to
+    ));
+  }
+
+  let mut files: Vec<PathBuf> = std::fs::read_dir(&in_dir)
+    .map_err(|e| e.to_string())?
+    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+    .filter(|path| {
+      path.is_file()
+        && path.extension().is_some_and(|ext| ext == "json")
+        && project_stem(path).is_none()
+    })
+    .collect();
+  if files.is_empty() {
+    return Err(format!("No slal files found in {}", in_dir.display()));
+  }
+  files.sort();
+
+  let mut failed = 0;
+  for path in &files {
+    match convert_file(path.clone(), &out_dir) {
+      Ok(out_path) => info!("Converted {} to {}", path.display(), out_path.display()),
+      Err(e) => {
+        error!("Failed to convert {}: {}", path.display(), e);
+        failed += 1;
+      }
+    }
+  }
+
+  info!(
+    "Converted {} files, {} succeeded, {} failed",
+    files.len(),
+    files.len() - failed,
+    failed
+  );
+  if failed > 0 {
+    return Err(format!("{} of {} files failed to convert", failed, files.len()));
+  }
+  Ok(())
 }
 
 pub fn build(
   args: std::collections::HashMap<String, tauri_plugin_cli::ArgData>,
 ) -> Result<(), String> {
+  tee_log_file(&args)?;
   let in_path = match &args.get("in").unwrap().value {
       serde_json::Value::String(value) => PathBuf::from(value),
       _ => return Err("input slal file not provided".to_string()),
@@ -47,7 +129,436 @@ pub fn build(
       return Err("output dir is invalid".to_string());
   }
 
+  let validation = get_validation_settings(&args)?;
+  let export = get_export_settings(&args)?;
+  if let Some(serde_json::Value::Bool(true)) = args.get("dry-run").map(|arg| &arg.value) {
+    let file = std::fs::File::open(&in_path).map_err(|e| e.to_string())?;
+    let project = Package::from_file(file)?;
+    for file in project.export_plan(&validation, &export)? {
+      println!("{:?}\t{}", file.kind, out_dir.join(&file.relative_path).display());
+    }
+    return Ok(());
+  }
+  if let Some(serde_json::Value::Bool(true)) = args.get("zip").map(|arg| &arg.value) {
+    let file = std::fs::File::open(&in_path).map_err(|e| e.to_string())?;
+    let project = Package::from_file(file)?;
+    let name = if project.pack_name.is_empty() { "export" } else { project.pack_name.as_str() };
+    return project.build_zip(&out_dir.join(format!("{}.zip", name)), &validation, &export);
+  }
+  if let Some(serde_json::Value::Bool(true)) = args.get("changed").map(|arg| &arg.value) {
+    let file = std::fs::File::open(&in_path).map_err(|e| e.to_string())?;
+    let mut project = Package::from_file(file)?;
+    let changed = project.build_changed(out_dir, &validation, &export)?;
+    println!("Exported {} changed scenes", changed.len());
+    if changed.is_empty() {
+      return Ok(());
+    }
+    // Remember the exported hashes for the next build
+    return project.write(in_path);
+  }
+  if let Some(serde_json::Value::String(value)) = args.get("tags").map(|arg| &arg.value) {
+    let tags: Vec<String> = value.split(',').map(|tag| tag.to_string()).collect();
+    let match_all = matches!(args.get("match-all").map(|arg| &arg.value), Some(serde_json::Value::Bool(true)));
+    let file = std::fs::File::open(&in_path).map_err(|e| e.to_string())?;
+    let project = Package::from_file(file)?
+      .scenes_matching_tags(&tags, match_all)
+      .map_err(|e| e.to_string())?;
+    return project.build(out_dir, &validation, &export).map_err(|e| e.to_string());
+  }
+  build_project(&in_path, out_dir, &validation, &export)
+}
+
+pub fn validate(
+  args: std::collections::HashMap<String, tauri_plugin_cli::ArgData>,
+) -> Result<(), String> {
+  tee_log_file(&args)?;
+  let in_path = match &args.get("in").unwrap().value {
+      serde_json::Value::String(value) => PathBuf::from(value),
+      _ => return Err("input project file not provided".to_string()),
+  };
+  if !in_path.exists() || !in_path.is_file() || in_path.extension().unwrap() != "json" {
+      return Err("input project file is invalid".to_string());
+  }
+  let validation = get_validation_settings(&args)?;
+
+  let file = std::fs::File::open(&in_path).map_err(|e| e.to_string())?;
+  let project = Package::from_file(file)?;
+  let issues = project.validate(&validation);
+  log_issues(&issues);
+  let errors = issues.iter().filter(|issue| issue.severity == Severity::Error).count();
+  if let Some(serde_json::Value::String(value)) = args.get("target").map(|arg| &arg.value) {
+    for line in project.compatibility_report(value)? {
+      warn!("{}", line);
+    }
+  }
+  if errors > 0 {
+    return Err(format!("Validation failed with {} errors", errors));
+  }
+  Ok(())
+}
+
+pub fn fix(
+  args: std::collections::HashMap<String, tauri_plugin_cli::ArgData>,
+) -> Result<(), String> {
+  let in_path = match &args.get("in").unwrap().value {
+      serde_json::Value::String(value) => PathBuf::from(value),
+      _ => return Err("input project file not provided".to_string()),
+  };
+  if !in_path.exists() || !in_path.is_file() || in_path.extension().unwrap() != "json" {
+      return Err("input project file is invalid".to_string());
+  }
+  let dry_run = matches!(args.get("dry-run").map(|arg| &arg.value), Some(serde_json::Value::Bool(true)));
+  let validation = SETTINGS.lock().unwrap().validation.clone();
+
+  let file = std::fs::File::open(&in_path).map_err(|e| e.to_string())?;
+  let mut project = Package::from_file(file)?;
+  let race_keys = project.canonicalize_race_keys();
+  let nav_links = project.repair_nav_links();
+  // Outliers are offsets the validation warns about, rotations are left alone
+  let max = validation.max_offset;
+  let offsets = project.clamp_all_offsets(
+    &Offset { x: -max, y: -max, z: -max, r: f32::MIN },
+    &Offset { x: max, y: max, z: max, r: f32::MAX },
+  )?;
+  let ids: Vec<_> = project.scenes.keys().cloned().collect();
+  let positions: usize = ids.iter().map(|id| project.compact_positions(id)).sum();
+
+  println!("Canonicalized race keys: {}", race_keys);
+  println!("Repaired navigation links: {}", nav_links);
+  println!("Clamped offset outliers: {}", offsets);
+  println!("Removed unused position infos: {}", positions);
+  if dry_run {
+    println!("Dry run, {} was not changed", in_path.display());
+    return Ok(());
+  }
+  if race_keys + nav_links + offsets + positions == 0 {
+    println!("Nothing to fix in {}", in_path.display());
+    return Ok(());
+  }
+  project.write(in_path)
+}
+
+pub fn info(
+  args: std::collections::HashMap<String, tauri_plugin_cli::ArgData>,
+) -> Result<(), String> {
+  let in_path = match &args.get("in").unwrap().value {
+      serde_json::Value::String(value) => PathBuf::from(value),
+      _ => return Err("input project file not provided".to_string()),
+  };
+  if !in_path.exists() || !in_path.is_file() || in_path.extension().unwrap() != "json" {
+      return Err("input project file is invalid".to_string());
+  }
+  let as_json = matches!(args.get("json").map(|arg| &arg.value), Some(serde_json::Value::Bool(true)));
+  if as_json {
+    // Keep stdout parseable
+    log::set_max_level(log::LevelFilter::Warn);
+  }
+
+  let file = std::fs::File::open(&in_path).map_err(|e| e.to_string())?;
+  let stats = Package::from_file(file)?.stats();
+  if as_json {
+    println!("{}", serde_json::to_string_pretty(&stats).map_err(|e| e.to_string())?);
+    return Ok(());
+  }
+  println!("Name: {}", stats.name);
+  println!("Author: {}", stats.author);
+  if !stats.description.is_empty() {
+    println!("Description: {}", stats.description);
+  }
+  println!("Version: {}", stats.version);
+  println!("Scenes: {} ({} private)", stats.scenes, stats.private_scenes);
+  println!("Stages: {}", stats.stages);
+  println!("Positions: {}", stats.positions);
+  println!("Estimated duration: {:.0} s", stats.estimated_duration);
+  if !stats.stage_counts.is_empty() {
+    println!("Stage counts:");
+    for (stages, scenes) in &stats.stage_counts {
+      println!("  {:>3} stages: {} scenes", stages, scenes);
+    }
+  }
+  Ok(())
+}
+
+pub fn racekeys(
+  args: std::collections::HashMap<String, tauri_plugin_cli::ArgData>,
+) -> Result<(), String> {
+  let as_json = matches!(args.get("json").map(|arg| &arg.value), Some(serde_json::Value::Bool(true)));
+  if as_json {
+    // Keep stdout parseable
+    log::set_max_level(log::LevelFilter::Warn);
+  }
+  let keys = racekeys::get_sorted_race_keys();
+  if as_json {
+    println!("{}", serde_json::to_string_pretty(&keys).map_err(|e| e.to_string())?);
+    return Ok(());
+  }
+  for key in keys {
+    println!("{}", key);
+  }
+  Ok(())
+}
+
+pub fn build_all(
+  args: std::collections::HashMap<String, tauri_plugin_cli::ArgData>,
+) -> Result<(), String> {
+  let in_dir = match &args.get("in").unwrap().value {
+      serde_json::Value::String(value) => PathBuf::from(value),
+      _ => return Err("input project dir not provided".to_string()),
+  };
+  if !in_dir.exists() || !in_dir.is_dir() {
+      return Err("input project dir is invalid".to_string());
+  }
+
+  // Without a common output root, every project is built into its own folder next to it
+  let out_root = match &args.get("out").unwrap().value {
+      serde_json::Value::String(value) => Some(PathBuf::from(value)),
+      _ => None,
+  };
+  if let Some(out_root) = &out_root {
+    std::fs::create_dir_all(out_root).map_err(|e| e.to_string())?;
+  }
+
+  let mut projects: Vec<PathBuf> = std::fs::read_dir(&in_dir)
+    .map_err(|e| e.to_string())?
+    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+    .filter(|path| path.is_file() && project_stem(path).is_some())
+    .collect();
+  if projects.is_empty() {
+    return Err(format!("No project files found in {}", in_dir.display()));
+  }
+  projects.sort();
+
+  warn_shared_priorities(&projects);
+
+  let validation = get_validation_settings(&args)?;
+  let export = get_export_settings(&args)?;
+  let mut failed = 0;
+  for path in &projects {
+    let out_dir = match &out_root {
+      Some(out_root) => out_root.clone(),
+      None => in_dir.join(project_stem(path).unwrap()),
+    };
+    let res = std::fs::create_dir_all(&out_dir)
+      .map_err(|e| e.to_string())
+      .and_then(|_| build_project(path, out_dir, &validation, &export));
+    match res {
+      Ok(_) => info!("Built project {}", path.display()),
+      Err(e) => {
+        error!("Failed to build project {}: {}", path.display(), e);
+        failed += 1;
+      }
+    }
+  }
+
+  info!(
+    "Built {} projects, {} succeeded, {} failed",
+    projects.len(),
+    projects.len() - failed,
+    failed
+  );
+  if failed > 0 {
+    return Err(format!("{} of {} projects failed to build", failed, projects.len()));
+  }
+  Ok(())
+}
+
+pub fn docs(
+  args: std::collections::HashMap<String, tauri_plugin_cli::ArgData>,
+) -> Result<(), String> {
+  let in_path = match &args.get("in").unwrap().value {
+      serde_json::Value::String(value) => PathBuf::from(value),
+      _ => return Err("input project file not provided".to_string()),
+  };
+  if !in_path.exists() || !in_path.is_file() || in_path.extension().unwrap() != "json" {
+      return Err("input project file is invalid".to_string());
+  }
+
+  let out_dir = match &args.get("out").unwrap().value {
+      serde_json::Value::String(value) => PathBuf::from(value),
+      _ => return Err("output dir not provided".to_string()),
+  };
+  let html = matches!(args.get("html").map(|arg| &arg.value), Some(serde_json::Value::Bool(true)));
+
+  let file = std::fs::File::open(&in_path).map_err(|e| e.to_string())?;
+  let project = Package::from_file(file)?;
+  project.export_docs(Path::new(&out_dir))?;
+  if html {
+    project.export_docs_html(Path::new(&out_dir))?;
+  }
+  Ok(())
+}
+
+pub fn csv(
+  args: std::collections::HashMap<String, tauri_plugin_cli::ArgData>,
+) -> Result<(), String> {
+  let in_path = match &args.get("in").unwrap().value {
+      serde_json::Value::String(value) => PathBuf::from(value),
+      _ => return Err("input project file not provided".to_string()),
+  };
+  if !in_path.exists() || !in_path.is_file() || in_path.extension().unwrap() != "json" {
+      return Err("input project file is invalid".to_string());
+  }
+
+  let out_path = match &args.get("out").unwrap().value {
+      serde_json::Value::String(value) => PathBuf::from(value),
+      _ => return Err("output file not provided".to_string()),
+  };
+
+  let file = std::fs::File::open(&in_path).map_err(|e| e.to_string())?;
+  let project = Package::from_file(file)?;
+  project.export_positions_csv(&out_path)
+}
+
+pub fn changelog(
+  args: std::collections::HashMap<String, tauri_plugin_cli::ArgData>,
+) -> Result<(), String> {
+  let old_path = match &args.get("old").unwrap().value {
+      serde_json::Value::String(value) => PathBuf::from(value),
+      _ => return Err("old project file not provided".to_string()),
+  };
+  let new_path = match &args.get("new").unwrap().value {
+      serde_json::Value::String(value) => PathBuf::from(value),
+      _ => return Err("new project file not provided".to_string()),
+  };
+  for path in [&old_path, &new_path] {
+    if !path.exists() || !path.is_file() || path.extension().unwrap() != "json" {
+        return Err(format!("project file {} is invalid", path.display()));
+    }
+  }
+  let out_path = match &args.get("out").unwrap().value {
+      serde_json::Value::String(value) => PathBuf::from(value),
+      _ => return Err("output file not provided".to_string()),
+  };
+  export_changelog(&old_path, &new_path, &out_path)
+}
+
+/// Write a Markdown changelog between two versions of a project
+pub fn export_changelog(old: &Path, new: &Path, out: &Path) -> Result<(), String> {
+  let old_project = Package::from_file(std::fs::File::open(old).map_err(|e| e.to_string())?)?;
+  let new_project = Package::from_file(std::fs::File::open(new).map_err(|e| e.to_string())?)?;
+  std::fs::write(out, new_project.changelog(&old_project)).map_err(|e| e.to_string())?;
+  info!("Wrote changelog to {}", out.display());
+  Ok(())
+}
+
+pub fn graph(
+  args: std::collections::HashMap<String, tauri_plugin_cli::ArgData>,
+) -> Result<(), String> {
+  let in_path = match &args.get("in").unwrap().value {
+      serde_json::Value::String(value) => PathBuf::from(value),
+      _ => return Err("input project file not provided".to_string()),
+  };
+  if !in_path.exists() || !in_path.is_file() || in_path.extension().unwrap() != "json" {
+      return Err("input project file is invalid".to_string());
+  }
+
+  let out_dir = match &args.get("out").unwrap().value {
+      serde_json::Value::String(value) => PathBuf::from(value),
+      _ => return Err("output dir not provided".to_string()),
+  };
+  std::fs::create_dir_all(&out_dir).map_err(|e| e.to_string())?;
+
+  let nav_json = matches!(args.get("nav-json").map(|arg| &arg.value), Some(serde_json::Value::Bool(true)));
+
   let file = std::fs::File::open(&in_path).map_err(|e| e.to_string())?;
   let project = Package::from_file(file)?;
-  project.build(out_dir).map_err(|e| e.to_string())
+  for scene in project.scenes_by_name() {
+    let (out_path, content) = if nav_json {
+      (out_dir.join(format!("{}.nav.json", scene.file_name())), scene.to_nav_json()?)
+    } else {
+      (out_dir.join(format!("{}.dot", scene.file_name())), scene.to_dot())
+    };
+    std::fs::write(&out_path, content).map_err(|e| e.to_string())?;
+    info!("Wrote graph of Scene {} to {}", scene.id.0, out_path.display());
+  }
+  Ok(())
+}
+
+/// Warn about projects of a workspace sharing a load priority, SexLab can't order them against each other
+fn warn_shared_priorities(projects: &[PathBuf]) {
+  let mut by_priority: std::collections::BTreeMap<i32, Vec<String>> = Default::default();
+  for path in projects {
+    let project = match std::fs::File::open(path).map_err(|e| e.to_string()).and_then(Package::from_file) {
+      Ok(project) => project,
+      // Reported by the build itself
+      Err(_) => continue,
+    };
+    by_priority.entry(project.priority).or_default().push(project.pack_name);
+  }
+  for (priority, names) in by_priority.iter().filter(|(_, names)| names.len() > 1) {
+    warn!("Projects {} share the load priority {}", names.join(", "), priority);
+  }
+}
+
+fn build_project(
+  in_path: &PathBuf,
+  out_dir: PathBuf,
+  validation: &ValidationSettings,
+  export: &ExportSettings,
+) -> Result<(), String> {
+  let file = std::fs::File::open(in_path).map_err(|e| e.to_string())?;
+  let project = Package::from_file(file)?;
+  project.build(out_dir, validation, export).map_err(|e| e.to_string())
+}
+
+/// Copy all log output of this run into the file passed as --log-file, on top of the regular log
+fn tee_log_file(
+  args: &std::collections::HashMap<String, tauri_plugin_cli::ArgData>,
+) -> Result<(), String> {
+  if let Some(serde_json::Value::String(value)) = args.get("log-file").map(|arg| &arg.value) {
+    let path = PathBuf::from(value);
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+      std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let file = std::fs::File::create(&path)
+      .map_err(|e| format!("Unable to create log file {}: {}", path.display(), e))?;
+    *crate::LOG_TEE.lock().unwrap() = Some(file);
+    info!("Writing log to {}", path.display());
+  }
+  Ok(())
+}
+
+/// Validation settings of the user, overridden by any limits passed on the command line
+fn get_validation_settings(
+  args: &std::collections::HashMap<String, tauri_plugin_cli::ArgData>,
+) -> Result<ValidationSettings, String> {
+  let mut validation = SETTINGS.lock().unwrap().validation.clone();
+  if let Some(serde_json::Value::String(value)) = args.get("min-stages").map(|arg| &arg.value) {
+    validation.min_stages = value
+      .parse()
+      .ok()
+      .filter(|min_stages| *min_stages > 0)
+      .ok_or(format!("min-stages must be a positive number, got {}", value))?;
+  }
+  Ok(validation)
+}
+
+/// Export settings of the user, with flags passed on the command line enabled
+fn get_export_settings(
+  args: &std::collections::HashMap<String, tauri_plugin_cli::ArgData>,
+) -> Result<ExportSettings, String> {
+  let mut export = SETTINGS.lock().unwrap().export.clone();
+  if let Some(serde_json::Value::String(value)) = args.get("layout").map(|arg| &arg.value) {
+    export.layout = value.parse()?;
+  }
+  if let Some(serde_json::Value::Bool(true)) = args.get("sidecar").map(|arg| &arg.value) {
+    export.builder_sidecar = true;
+  }
+  if let Some(serde_json::Value::Bool(true)) = args.get("minify").map(|arg| &arg.value) {
+    export.minify = true;
+  }
+  if let Some(serde_json::Value::Bool(true)) = args.get("clean").map(|arg| &arg.value) {
+    export.clean = true;
+  }
+  if let Some(serde_json::Value::String(value)) = args.get("ext").map(|arg| &arg.value) {
+    export.extension = value.clone();
+  }
+  export.registry_extension()?;
+  Ok(export)
+}
+
+fn project_stem(path: &PathBuf) -> Option<&str> {
+  path.file_name()
+    .and_then(|name| name.to_str())
+    .and_then(|name| name.strip_suffix(PROJECT_EXTENSION))
 }
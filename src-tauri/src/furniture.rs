@@ -1,6 +1,8 @@
 use bitflags::bitflags;
 use std::collections::HashMap;
 
+use crate::settings::SETTINGS;
+
 macro_rules! bitflags_with_strings {
     ($name:ident: $type:ty { $($variant:ident = $value:expr),* $(,)? }) => {
         bitflags! {
@@ -18,6 +20,10 @@ macro_rules! bitflags_with_strings {
             //     }
             // }
 
+            pub fn names() -> Vec<&'static str> {
+                vec![$(stringify!($variant)),*]
+            }
+
             pub fn from_str(s: &str) -> Option<Self> {
                 let mapping: HashMap<&str, Self> = [
                     $( (stringify!($variant), Self::$variant) ),*
@@ -76,3 +82,50 @@ pub fn as_furnitre(list: &Vec<String>) -> Furniture {
         acc | Furniture::from_str(s).unwrap_or(Furniture::None)
     })
 }
+
+/// Built-in furniture types followed by the ones registered by the user
+pub fn get_furniture_types() -> Vec<String> {
+    let mut ret: Vec<String> = Furniture::names().into_iter().map(String::from).collect();
+    ret.extend(SETTINGS.lock().unwrap().custom_furniture.iter().cloned());
+    ret
+}
+
+pub fn is_builtin_furniture(name: &str) -> bool {
+    Furniture::from_str(name).is_some()
+}
+
+pub fn is_known_furniture(name: &str) -> bool {
+    is_builtin_furniture(name) || SETTINGS.lock().unwrap().custom_furniture.iter().any(|it| it == name)
+}
+
+/// Register a new furniture type, returns the updated list of custom types
+pub fn add_custom_furniture(name: &str) -> Result<Vec<String>, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Furniture type must not be empty".into());
+    }
+    let mut settings = SETTINGS.lock().unwrap();
+    if is_builtin_furniture(name)
+        || settings
+            .custom_furniture
+            .iter()
+            .any(|it| it.eq_ignore_ascii_case(name))
+    {
+        return Err(format!("Furniture type {} already exists", name));
+    }
+    settings.custom_furniture.push(name.to_string());
+    settings.save()?;
+    Ok(settings.custom_furniture.clone())
+}
+
+pub fn remove_custom_furniture(name: &str) -> Result<Vec<String>, String> {
+    let mut settings = SETTINGS.lock().unwrap();
+    let i = settings
+        .custom_furniture
+        .iter()
+        .position(|it| it == name)
+        .ok_or_else(|| format!("Unknown custom furniture type: {}", name))?;
+    settings.custom_furniture.remove(i);
+    settings.save()?;
+    Ok(settings.custom_furniture.clone())
+}
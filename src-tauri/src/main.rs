@@ -6,24 +6,48 @@ mod cli;
 mod furniture;
 mod project;
 mod racekeys;
+mod settings;
+mod voices;
 
-use log::{error, info};
+use log::{error, info, warn};
 use once_cell::sync::Lazy;
-use project::{package::Package, position::Position, scene::Scene, stage::Stage, NanoID};
+use project::{
+    define::{Offset, OffsetUnit, Sex},
+    package::{
+        ExportFile, FurnitureProfileSummary, Package, ProjectStats, SceneOrderImportSummary, SortKey,
+        StageOffsetImportSummary, TextIssue,
+    },
+    position::Position,
+    scene::{NavAnalysis, Scene},
+    stage::{Stage, StageDiff},
+    strings::StringsImportSummary,
+    NanoID,
+};
 use serde::{Deserialize, Serialize};
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Mutex,
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
 };
 use tauri::{
     menu::{CheckMenuItem, Menu, MenuBuilder, MenuItem, SubmenuBuilder},
+    webview::PageLoadEvent,
     AppHandle, Emitter, Listener, Manager, Runtime, WebviewWindowBuilder, Wry,
 };
 use tauri_plugin_cli::CliExt;
 use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
 use tauri_plugin_opener::OpenerExt;
 
-use crate::project::position_info::PositionInfo;
+use crate::{
+    project::position_info::PositionInfo,
+    settings::{
+        accelerator, DisplayUnits, ImportSettings, Keybindings, SceneDefaults, WindowGeometry, SETTINGS,
+    },
+};
 
 const DEFAULT_MAINWINDOW_TITLE: &str = "SexLab Scene Builder";
 
@@ -42,27 +66,65 @@ fn get_edited() -> bool {
     EDITED.load(Ordering::Relaxed)
 }
 
-static IS_DARKMODE: AtomicBool = AtomicBool::new(false);
+static IS_DARKMODE: Lazy<AtomicBool> =
+    Lazy::new(|| AtomicBool::new(SETTINGS.lock().unwrap().darkmode));
 #[inline]
 fn set_darkmode(val: bool) -> () {
-    IS_DARKMODE.store(val, Ordering::Relaxed)
+    IS_DARKMODE.store(val, Ordering::Relaxed);
+    let mut settings = SETTINGS.lock().unwrap();
+    settings.darkmode = val;
+    if let Err(err) = settings.save() {
+        error!("Failed to save dark mode preference: {}", err);
+    }
 }
 #[inline]
 fn get_darkmode() -> bool {
     IS_DARKMODE.load(Ordering::Relaxed)
 }
 
+/// Delay within which consecutive project updates are coalesced into a single event
+const PROJECT_UPDATE_DEBOUNCE: Duration = Duration::from_millis(100);
+static PROJECT_UPDATE_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// Schedule an `on_project_update` event. Updates requested while one is pending are merged
+/// into it, the event always carries the project state at the time it is sent
+fn emit_project_update<R: Runtime>(app: &AppHandle<R>) -> () {
+    if PROJECT_UPDATE_PENDING.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let app = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(PROJECT_UPDATE_DEBOUNCE);
+        // Reset before reading the project, so that any later change schedules another event
+        PROJECT_UPDATE_PENDING.store(false, Ordering::Release);
+        let prjct = PROJECT.lock().unwrap();
+        if let Err(err) = app.emit("on_project_update", &prjct.scenes) {
+            error!("Failed to emit project update: {}", err);
+        }
+    });
+}
+
+/// Additional file receiving a copy of the log, set by CLI commands for the duration of their run
+pub static LOG_TEE: Mutex<Option<std::fs::File>> = Mutex::new(None);
+
 fn setup_logger() -> Result<(), fern::InitError> {
     let mut dispatch = fern::Dispatch::new()
         .format(|out, message, record| out.finish(format_args!("[{}] {}", record.level(), message)))
         .level(log::LevelFilter::Info)
-        .chain(std::io::stdout());
+        .chain(std::io::stdout())
+        .chain(fern::Output::call(|record| {
+            use std::io::Write as _;
+            if let Some(file) = LOG_TEE.lock().unwrap().as_mut() {
+                let _ = writeln!(file, "{}", record.args());
+            }
+        }));
 
     // Try to create log file in user's data directory, fall back to stdout-only if not possible
-    if let Some(data_dir) = dirs::data_local_dir() {
-        let log_dir = data_dir.join("SexLabSceneBuilder");
-        if std::fs::create_dir_all(&log_dir).is_ok() {
-            let log_path = log_dir.join("SceneBuilder.log");
+    if let Some(log_path) = log_file_path() {
+        let created = log_path
+            .parent()
+            .is_some_and(|log_dir| std::fs::create_dir_all(log_dir).is_ok());
+        if created {
             if let Ok(log_file) = fern::log_file(&log_path) {
                 dispatch = dispatch.chain(log_file);
             }
@@ -73,13 +135,19 @@ fn setup_logger() -> Result<(), fern::InitError> {
     Ok(())
 }
 
+fn log_file_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|data_dir| data_dir.join("SexLabSceneBuilder").join("SceneBuilder.log"))
+}
+
 /// MAIN
 
 const MAIN_WINDOW: &str = "main_window";
 
 const NEW_PROJECT: &str = "new_prjct";
 const OPEN_PROJECT: &str = "open_prjct";
+const LOAD_EXAMPLE: &str = "load_example";
 const DARKMODE: &str = "darkmode";
+const REOPEN_STAGE_EDITOR: &str = "reopen_stage_editor";
 
 fn main() {
     setup_logger().expect("Unable to initialize logger");
@@ -91,22 +159,143 @@ fn main() {
         .invoke_handler(tauri::generate_handler![
             request_project_update,
             get_race_keys,
+            get_sorted_race_keys,
+            import_race_keys,
+            get_race_key_aliases,
+            set_race_key_alias,
+            remove_race_key_alias,
+            import_race_key_aliases,
+            resolve_race_aliases,
+            get_voices,
+            used_race_keys,
+            race_key_matrix,
+            missing_events,
+            project_stats,
+            stage_count_histogram,
+            compatibility_report,
+            project_content_hash,
+            scenes_changed_since_export,
+            export_plan,
+            estimate_export_size,
+            get_position_templates,
+            save_position_template,
+            apply_position_template,
+            get_pack_metadata,
+            set_pack_metadata,
+            get_furniture_types,
+            add_custom_furniture,
+            remove_custom_furniture,
             create_blank_scene,
             save_scene,
+            sorted_scenes,
             delete_scene,
             open_stage_editor,
             open_stage_editor_from,
             stage_save_and_close,
             make_position,
             mark_as_edited,
-            get_in_darkmode
+            get_in_darkmode,
+            reset_settings,
+            reassign_all_ids,
+            diff_stages,
+            set_scene_thumbnail,
+            pick_scene_thumbnail,
+            set_stage_thumbnail,
+            cache_stage_editor,
+            reopen_last_stage_editor,
+            canonicalize_race_keys,
+            stages_using_event,
+            dead_end_stages,
+            find_text_issues,
+            trim_all_text,
+            replace_event,
+            rename_tag,
+            clamp_all_offsets,
+            get_keybindings,
+            save_checkpoint,
+            list_checkpoints,
+            restore_checkpoint,
+            set_keybindings,
+            get_scene_defaults,
+            set_scene_defaults,
+            set_default_race_key,
+            get_display_units,
+            set_display_units,
+            get_import_settings,
+            set_import_settings,
+            normalize_offset_precision,
+            convert_offset_units,
+            reorder_stages,
+            get_scene_order,
+            reorder_scenes,
+            import_scene_order,
+            swap_positions,
+            find_identical_stages,
+            check_offset_symmetry,
+            set_start_stage,
+            set_scene_furniture,
+            apply_furniture_profile,
+            reset_position_offset,
+            mirror_position,
+            reset_all_offsets,
+            import_offset_string,
+            import_offset_by_stage_name,
+            rename_stages_pattern,
+            find_incomplete_scenes,
+            scenes_with_unreachable_climax,
+            scenes_with_mismatched_counts,
+            export_storyboard,
+            export_offset_sheet,
+            export_animation_checklist,
+            export_strings_template,
+            import_strings,
+            export_scene_frontmatter,
+            export_thumbnails,
+            check_thumbnails,
+            export_scene_json_anonymized,
+            preview_scene_export,
+            export_as_project,
+            save_canonical,
+            get_canonical_saves,
+            set_canonical_saves,
+            export_by_tags,
+            duplicate_project,
+            export_scene_toml,
+            import_scene_toml,
+            close_all_stage_editors,
+            scene_graph_dot,
+            find_nav_cycles,
+            analyze_nav_graph,
+            export_nav_json,
+            repair_nav_links,
+            set_scene_sexes,
+            set_scene_required_tags,
+            add_actor_to_scene,
+            remove_actor_from_scene,
+            merge_stages,
+            set_looping_by_criteria,
+            dedupe_stage_names,
+            scene_from_stages,
+            simulate_playback,
+            estimated_duration,
+            copy_positions
         ])
         .setup(|app| {
             let matches = app.cli().matches()?;
             if let Some(command) = matches.subcommand {
                 let res = match command.name.as_str() {
                     "convert" => cli::convert(command.matches.args),
+                    "convert-all" => cli::convert_all(command.matches.args),
                     "build" => cli::build(command.matches.args),
+                    "build-all" => cli::build_all(command.matches.args),
+                    "validate" => cli::validate(command.matches.args),
+                    "fix" => cli::fix(command.matches.args),
+                    "info" => cli::info(command.matches.args),
+                    "docs" => cli::docs(command.matches.args),
+                    "csv" => cli::csv(command.matches.args),
+                    "graph" => cli::graph(command.matches.args),
+                    "changelog" => cli::changelog(command.matches.args),
+                    "racekeys" => cli::racekeys(command.matches.args),
                     _ => Err(format!("Unrecognized subcommand: {}", command.name)),
                 }
                 .map_err(|e| {
@@ -116,20 +305,55 @@ fn main() {
                 app.handle().exit(res.is_err() as i32);
                 return res;
             }
+            // Launched with a project file, e.g. through "open with"
+            let startup_project = match matches.args.get("project").map(|arg| &arg.value) {
+                Some(serde_json::Value::String(value)) => Some(PathBuf::from(value)),
+                _ => None,
+            };
+            let mut startup_error = None;
+            let mut startup_loaded = false;
+            if let Some(path) = startup_project {
+                match Package::read_startup_project(path) {
+                    Ok(package) => {
+                        info!("Loaded project {} from the command line", package.pack_path.display());
+                        *PROJECT.lock().unwrap() = package;
+                        startup_loaded = true;
+                    }
+                    Err(e) => {
+                        error!("{}", e);
+                        startup_error = Some(e);
+                    }
+                }
+            }
             let app_handle = app.app_handle().clone();
-            WebviewWindowBuilder::new(
+            let main_window = WebviewWindowBuilder::new(
                 app.app_handle(),
                 MAIN_WINDOW.to_string(),
                 tauri::WebviewUrl::App("./index.html".into()),
             )
             .title(DEFAULT_MAINWINDOW_TITLE)
+            .on_page_load(move |window, payload| {
+                // The frontend only listens for project updates once the page is loaded
+                if startup_loaded && payload.event() == PageLoadEvent::Finished {
+                    finish_reload(&window);
+                }
+            })
             .menu(get_menu(&app.app_handle()).expect("Failed to create menu"))
             .min_inner_size(960.0, 540.0)
             .inner_size(1280.0, 720.0)
             .build()
-            .expect("Failed to create main window")
-            .on_window_event(move |event| window_event_listener(&app_handle, event));
+            .expect("Failed to create main window");
+            let geometry = SETTINGS.lock().unwrap().main_window;
+            restore_window_geometry(&main_window, geometry);
+            main_window.on_window_event(move |event| window_event_listener(&app_handle, event));
             app.on_menu_event(menu_event_listener);
+            if let Some(e) = startup_error {
+                app.dialog()
+                    .message(e)
+                    .title("Open Project")
+                    .kind(MessageDialogKind::Error)
+                    .show(|_| {});
+            }
             Ok(())
         })
         .run(tauri::generate_context!())
@@ -137,29 +361,72 @@ fn main() {
 }
 
 fn reload_project(reload_type: &str, window: &tauri::WebviewWindow) {
-    let mut prjct = PROJECT.lock().unwrap();
-    let result = match reload_type {
+    match reload_type {
         NEW_PROJECT => {
-            prjct.reset();
-            Ok(())
+            PROJECT.lock().unwrap().reset();
+            finish_reload(window);
+        }
+        LOAD_EXAMPLE => {
+            if let Err(e) = PROJECT.lock().unwrap().create_sample_project("basic") {
+                error!("Failed to create example project: {}", e);
+                return;
+            }
+            finish_reload(window);
+        }
+        OPEN_PROJECT => {
+            // Large projects take a while to parse, keep the UI responsive and report progress
+            let window = window.clone();
+            std::thread::spawn(move || open_project(&window));
+        }
+        _ => error!("Invalid reload type: {}", reload_type),
+    }
+}
+
+/// Load a project chosen by the user, replacing the current one only once it has been read completely
+fn open_project(window: &tauri::WebviewWindow) {
+    let app = window.app_handle();
+    let path = match Package::pick_project_file(app) {
+        Ok(path) => path,
+        Err(e) => {
+            info!("{}", e);
+            return;
         }
-        OPEN_PROJECT => prjct.load_project(window.app_handle()),
-        _ => Err(format!("Invalid reload type: {}", reload_type)),
     };
-    if let Err(e) = result {
-        error!("{}", e);
-        return;
+    let _ = app.emit("load_progress", 0);
+    // Reading may be followed by recovery or version dialogs, the editor stays blocked until those are answered
+    let result = Package::read_project(app, path, |percent| {
+        let _ = app.emit("load_progress", percent.min(99));
+    });
+    match result {
+        Ok(package) => {
+            *PROJECT.lock().unwrap() = package;
+            finish_reload(window);
+            let _ = app.emit("load_progress", 100);
+        }
+        Err(e) => {
+            error!("{}", e);
+            let _ = app.emit("load_failed", e);
+        }
     }
+}
+
+fn finish_reload(window: &tauri::WebviewWindow) {
+    // Checkpoints belong to the previous project
+    CHECKPOINTS.lock().unwrap().clear();
+    let prjct = PROJECT.lock().unwrap();
+    // Open editors refer to stages of the previous project
+    close_stage_editors(window.app_handle(), false);
     if prjct.pack_name == String::default() {
         let _ = window.set_title(DEFAULT_MAINWINDOW_TITLE);
     } else {
         let _ = window
             .set_title(format!("{} - {}", DEFAULT_MAINWINDOW_TITLE, prjct.pack_name).as_str());
     }
-    window.emit("on_project_update", &prjct.scenes).unwrap();
+    emit_project_update(window.app_handle());
 }
 
 fn get_menu(app: &AppHandle) -> Result<Menu<Wry>, Box<dyn std::error::Error>> {
+    let keys = SETTINGS.lock().unwrap().keybindings.clone();
     let file_menu = SubmenuBuilder::new(app, "File")
         .items(&[
             &MenuItem::with_id(
@@ -167,14 +434,21 @@ fn get_menu(app: &AppHandle) -> Result<Menu<Wry>, Box<dyn std::error::Error>> {
                 NEW_PROJECT,
                 "New Project",
                 true,
-                "cmdOrControl+N".into(),
+                accelerator(&keys.new_project),
             )?,
             &MenuItem::with_id(
                 app,
                 OPEN_PROJECT,
                 "Open Project",
                 true,
-                "cmdOrControl+O".into(),
+                accelerator(&keys.open_project),
+            )?,
+            &MenuItem::with_id(
+                app,
+                LOAD_EXAMPLE,
+                "Load Example Project",
+                true,
+                Option::<&str>::None,
             )?,
         ])
         .separator()
@@ -186,15 +460,64 @@ fn get_menu(app: &AppHandle) -> Result<Menu<Wry>, Box<dyn std::error::Error>> {
                 true,
                 Option::<&str>::None,
             )?,
-            &MenuItem::with_id(app, "save", "Save", true, "cmdOrControl+S".into())?,
+            &MenuItem::with_id(
+                app,
+                "import_offset_dir",
+                "Import Offset Folder...",
+                true,
+                Option::<&str>::None,
+            )?,
+            &MenuItem::with_id(
+                app,
+                "import_builder_metadata",
+                "Import Builder Metadata...",
+                true,
+                Option::<&str>::None,
+            )?,
+            &MenuItem::with_id(app, "save", "Save", true, accelerator(&keys.save))?,
             &MenuItem::with_id(
                 app,
                 "save_as",
                 "Save As...",
                 true,
-                "cmdOrControl+Shift+S".into(),
+                accelerator(&keys.save_as),
+            )?,
+            &MenuItem::with_id(
+                app,
+                "duplicate_project",
+                "Duplicate Project As...",
+                true,
+                Option::<&str>::None,
+            )?,
+            &MenuItem::with_id(app, "build", "Export", true, accelerator(&keys.build))?,
+            &MenuItem::with_id(
+                app,
+                "export_changed",
+                "Export Changed Scenes...",
+                true,
+                Option::<&str>::None,
+            )?,
+            &MenuItem::with_id(
+                app,
+                "export_zip",
+                "Export as Zip...",
+                true,
+                Option::<&str>::None,
+            )?,
+            &MenuItem::with_id(
+                app,
+                "export_docs",
+                "Export Documentation...",
+                true,
+                Option::<&str>::None,
+            )?,
+            &MenuItem::with_id(
+                app,
+                "export_csv",
+                "Export Positions CSV...",
+                true,
+                Option::<&str>::None,
             )?,
-            &MenuItem::with_id(app, "build", "Export", true, "cmdOrControl+B".into())?,
         ])
         .separator()
         .quit()
@@ -208,9 +531,12 @@ fn get_menu(app: &AppHandle) -> Result<Menu<Wry>, Box<dyn std::error::Error>> {
             get_darkmode(),
             Option::<&str>::None,
         )?)
+        .separator()
+        .text(REOPEN_STAGE_EDITOR, "Reopen Closed Stage Editor")
         .build()?;
     let help_menu = SubmenuBuilder::new(app, "Help")
         .text("open_docs", "Open Wiki")
+        .text("open_log", "Open Log File")
         .separator()
         .text("discord", "Discord")
         .text("patreon", "Patreon")
@@ -224,13 +550,17 @@ fn get_menu(app: &AppHandle) -> Result<Menu<Wry>, Box<dyn std::error::Error>> {
 
 fn menu_event_listener(app: &tauri::AppHandle, event: tauri::menu::MenuEvent) {
     match event.id().0.as_str() {
-        NEW_PROJECT | OPEN_PROJECT => {
+        NEW_PROJECT | OPEN_PROJECT | LOAD_EXAMPLE => {
             let event_id = event.id().0.clone();
             let window = app.get_webview_window(MAIN_WINDOW).unwrap();
             if get_edited() {
                 app.dialog()
                     .message("There are unsaved changes. Loading a new project will cause these changes to be lost.\nContinue?")
-                    .title(if event_id == NEW_PROJECT {"New Project"} else {"Open Project"})
+                    .title(match event_id.as_str() {
+                        NEW_PROJECT => "New Project",
+                        LOAD_EXAMPLE => "Load Example Project",
+                        _ => "Open Project",
+                    })
                     .buttons(MessageDialogButtons::YesNo)
                     .kind(MessageDialogKind::Warning)
                     .show(move |result| match result {
@@ -252,17 +582,71 @@ fn menu_event_listener(app: &tauri::AppHandle, event: tauri::menu::MenuEvent) {
             let _ = window
                 .set_title(format!("{} - {}", DEFAULT_MAINWINDOW_TITLE, prjct.pack_name).as_str());
         }
+        "duplicate_project" => {
+            let prjct = PROJECT.lock().unwrap();
+            if let Err(err) = prjct.duplicate_project_as(app) {
+                error!("Failed to duplicate project: {}", err);
+            }
+        }
         "build" => {
+            let settings = SETTINGS.lock().unwrap().clone();
+            let mut prjct = PROJECT.lock().unwrap();
+            let hashes = prjct.export_hashes.clone();
+            match prjct.export(app, &settings.validation, &settings.export) {
+                // The stored export hashes changed and need saving
+                Ok(()) if prjct.export_hashes != hashes => {
+                    set_edited(true);
+                    emit_project_update(app);
+                }
+                Ok(()) => {}
+                Err(err) => error!("Failed to build project: {}", err),
+            }
+        }
+        "export_changed" => {
+            let settings = SETTINGS.lock().unwrap().clone();
+            let mut prjct = PROJECT.lock().unwrap();
+            match prjct.export_changed(app, &settings.validation, &settings.export) {
+                // The stored export hashes changed and need saving
+                Ok(changed) if !changed.is_empty() => {
+                    set_edited(true);
+                    emit_project_update(app);
+                }
+                Ok(_) => {}
+                Err(err) => error!("Failed to export changed scenes: {}", err),
+            }
+        }
+        "export_zip" => {
+            let settings = SETTINGS.lock().unwrap().clone();
             let prjct = PROJECT.lock().unwrap();
-            if let Err(err) = prjct.export(app) {
-                error!("Failed to build project: {}", err);
+            if let Err(err) = prjct.export_zip(app, &settings.validation, &settings.export) {
+                error!("Failed to export archive: {}", err);
+            }
+        }
+        "export_docs" => {
+            let prjct = PROJECT.lock().unwrap();
+            if let Err(err) = prjct.export_documentation(app) {
+                error!("Failed to export documentation: {}", err);
+            }
+        }
+        "export_csv" => {
+            let prjct = PROJECT.lock().unwrap();
+            if let Err(err) = prjct.export_csv(app) {
+                error!("Failed to export positions: {}", err);
             }
         }
         DARKMODE => {
             let new_darkmode = !get_darkmode();
             set_darkmode(new_darkmode);
-            if let Err(err) = app.emit("toggle_darkmode", new_darkmode) {
-                error!("Unable to toggle darkmode, event failure: {}", err);
+            // Address every window directly, stage editors opened later receive it on creation
+            for label in app.webview_windows().into_keys() {
+                if let Err(err) = app.emit_to(label.as_str(), "toggle_darkmode", new_darkmode) {
+                    error!("Unable to toggle darkmode, event failure: {}", err);
+                }
+            }
+        }
+        REOPEN_STAGE_EDITOR => {
+            if let Err(err) = reopen_last_stage_editor_impl(app) {
+                info!("{}", err);
             }
         }
         "open_docs" => {
@@ -271,6 +655,27 @@ fn menu_event_listener(app: &tauri::AppHandle, event: tauri::menu::MenuEvent) {
                 Option::<String>::None,
             );
         }
+        "open_log" => {
+            let log_path = match log_file_path() {
+                Some(log_path) => log_path,
+                None => {
+                    error!("Unable to locate the log file");
+                    return;
+                }
+            };
+            // Without a log file, the folder it would be written to is the next best thing
+            let target = if log_path.exists() {
+                log_path
+            } else {
+                log_path.parent().map(|dir| dir.to_path_buf()).unwrap_or(log_path)
+            };
+            if let Err(err) = app
+                .opener()
+                .open_path(target.to_string_lossy(), Option::<String>::None)
+            {
+                error!("Failed to open {}: {}", target.display(), err);
+            }
+        }
         "discord" => {
             let _ = app
                 .opener()
@@ -288,11 +693,63 @@ fn menu_event_listener(app: &tauri::AppHandle, event: tauri::menu::MenuEvent) {
                 .open_url("https://ko-fi.com/scrab", Option::<String>::None);
         }
         "import_offset" => {
+            let import = SETTINGS.lock().unwrap().import.clone();
+            let round_to = import.normalize_offsets.then_some(import.offset_decimals);
             let mut prjct = PROJECT.lock().unwrap();
-            if let Err(err) = prjct.import_offset(app) {
+            if let Err(err) = prjct.import_offset(app, round_to) {
                 error!("{}", err);
             }
         }
+        "import_builder_metadata" => {
+            let mut prjct = PROJECT.lock().unwrap();
+            match prjct.import_builder_sidecar(app) {
+                Ok(0) => info!("No scene in the builder metadata matches this project"),
+                Ok(applied) => {
+                    info!("Imported builder metadata for {} scenes", applied);
+                    mark_main_window_edited(app);
+                    emit_project_update(app);
+                }
+                Err(err) => error!("{}", err),
+            }
+        }
+        "import_offset_dir" => {
+            let import = SETTINGS.lock().unwrap().import.clone();
+            let round_to = import.normalize_offsets.then_some(import.offset_decimals);
+            let mut prjct = PROJECT.lock().unwrap();
+            let summary = match prjct.import_offset_folder(app, round_to) {
+                Ok(summary) => summary,
+                Err(err) => {
+                    error!("{}", err);
+                    return;
+                }
+            };
+            if !summary.matched.is_empty() {
+                mark_main_window_edited(app);
+                emit_project_update(app);
+            }
+            drop(prjct);
+            info!(
+                "Imported offsets from {} files, {} unmatched, {} failed",
+                summary.matched.len(),
+                summary.unmatched.len(),
+                summary.failed.len()
+            );
+            let mut message = format!("Imported {} offset files.", summary.matched.len());
+            if !summary.unmatched.is_empty() {
+                message += &format!(
+                    "\n\nNo matching scene for:\n{}",
+                    summary.unmatched.join("\n")
+                );
+            }
+            if !summary.failed.is_empty() {
+                message += &format!("\n\nFailed to import:\n{}", summary.failed.join("\n"));
+            }
+            app.dialog()
+                .message(message)
+                .title("Import Offset Folder")
+                .kind(MessageDialogKind::Info)
+                .show(|_| {});
+        }
         _ => {
             error!("Unrecognized command: {}", event.id().0)
         }
@@ -315,182 +772,1934 @@ fn window_event_listener(app: &AppHandle, event: &tauri::WindowEvent) {
                     return;
                 }
             }
+            if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+                if let Some(geometry) = get_window_geometry(&window) {
+                    save_window_geometry(|settings| settings.main_window = Some(geometry));
+                }
+            }
             std::process::exit(0);
         }
         _ => {}
     }
 }
 
+/// Current geometry of the window, None while it is minimized or maximized as restoring that would be meaningless
+fn get_window_geometry<R: Runtime>(window: &tauri::WebviewWindow<R>) -> Option<WindowGeometry> {
+    if window.is_minimized().unwrap_or(true) || window.is_maximized().unwrap_or(true) {
+        return None;
+    }
+    let position = window.outer_position().ok()?;
+    let size = window.inner_size().ok()?;
+    Some(WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    })
+}
+
+fn save_window_geometry(update: impl FnOnce(&mut settings::Settings)) {
+    let mut settings = SETTINGS.lock().unwrap();
+    update(&mut settings);
+    if let Err(err) = settings.save() {
+        error!("Failed to save window geometry: {}", err);
+    }
+}
+
+/// Move and resize the window to a previously saved geometry. Monitors may have been unplugged or rearranged
+/// since, so the geometry is clamped onto the monitor it overlaps most, or the primary monitor if it overlaps none
+fn restore_window_geometry<R: Runtime>(window: &tauri::WebviewWindow<R>, geometry: Option<WindowGeometry>) {
+    let geometry = match geometry {
+        Some(geometry) => geometry,
+        None => return,
+    };
+    let monitors = window.available_monitors().unwrap_or_default();
+    let overlap = |monitor: &tauri::Monitor| {
+        let (pos, size) = (monitor.position(), monitor.size());
+        let w = (geometry.x + geometry.width as i32).min(pos.x + size.width as i32) - geometry.x.max(pos.x);
+        let h = (geometry.y + geometry.height as i32).min(pos.y + size.height as i32) - geometry.y.max(pos.y);
+        w.max(0) as i64 * h.max(0) as i64
+    };
+    let monitor = match monitors
+        .iter()
+        .filter(|monitor| overlap(monitor) > 0)
+        .max_by_key(|monitor| overlap(monitor))
+        .cloned()
+        .or_else(|| window.primary_monitor().ok().flatten())
+        .or_else(|| monitors.first().cloned())
+    {
+        Some(monitor) => monitor,
+        None => return,
+    };
+    let (pos, size) = (monitor.position(), monitor.size());
+    let width = geometry.width.min(size.width);
+    let height = geometry.height.min(size.height);
+    let x = geometry.x.clamp(pos.x, pos.x + (size.width - width) as i32);
+    let y = geometry.y.clamp(pos.y, pos.y + (size.height - height) as i32);
+    if let Err(err) = window
+        .set_size(tauri::PhysicalSize::new(width, height))
+        .and_then(|_| window.set_position(tauri::PhysicalPosition::new(x, y)))
+    {
+        warn!("Failed to restore geometry of window {}: {}", window.label(), err);
+    }
+}
+
 /// COMMANDS
 
 #[tauri::command]
 async fn request_project_update<R: Runtime>(window: tauri::Window<R>) -> () {
-    let prjct = PROJECT.lock().unwrap();
-    window.emit("on_project_update", &prjct.scenes).unwrap();
+    emit_project_update(window.app_handle());
 }
 
 #[tauri::command]
 async fn get_race_keys() -> Vec<String> {
-    racekeys::get_race_keys_string()
+    racekeys::get_all_race_keys()
 }
 
 #[tauri::command]
-async fn mark_as_edited<R: Runtime>(window: tauri::Window<R>) -> () {
-    set_edited(true);
-    if let Ok(title) = window.title() {
-        if !title.ends_with('*') {
-            window.set_title(format!("{}*", title).as_str()).unwrap();
-        }
-    }
+async fn get_sorted_race_keys() -> Vec<String> {
+    racekeys::get_sorted_race_keys()
 }
 
 #[tauri::command]
-fn get_in_darkmode() -> bool {
-    get_darkmode()
+fn import_race_keys(keys: Vec<String>) -> Result<usize, String> {
+    let added = racekeys::import_race_keys(keys)?;
+    info!("Imported {} custom race keys", added);
+    Ok(added)
 }
 
-/* Scene */
+#[tauri::command]
+fn get_race_key_aliases() -> BTreeMap<String, String> {
+    SETTINGS.lock().unwrap().race_key_aliases.clone()
+}
 
 #[tauri::command]
-fn create_blank_scene() -> Scene {
-    Scene::default()
+fn set_race_key_alias(alias: String, target: String) -> Result<String, String> {
+    let target = racekeys::set_race_key_alias(&alias, &target)?;
+    info!("Race key alias {} now stands for {}", alias.trim(), target);
+    Ok(target)
 }
 
 #[tauri::command]
-async fn save_scene<R: Runtime>(window: tauri::Window<R>, scene: Scene) -> () {
-    mark_as_edited(window).await;
-    PROJECT.lock().unwrap().save_scene(scene);
+fn remove_race_key_alias(alias: String) -> Result<bool, String> {
+    racekeys::remove_race_key_alias(&alias)
 }
 
 #[tauri::command]
-fn delete_scene<R: Runtime>(window: tauri::Window<R>, id: NanoID) -> Result<Scene, String> {
-    let ret = PROJECT.lock().unwrap().discard_scene(&id).ok_or_else(|| {
-        let msg = format!("Invalid Scene ID: {}", id.0);
-        error!("{}", msg);
-        msg
-    });
+fn import_race_key_aliases(aliases: BTreeMap<String, String>) -> Result<usize, String> {
+    let imported = racekeys::import_race_key_aliases(aliases)?;
+    info!("Imported {} race key aliases", imported);
+    Ok(imported)
+}
 
-    if ret.is_ok() {
-        set_edited(true);
-        if let Ok(title) = window.title() {
-            if !title.ends_with('*') {
-                window.set_title(format!("{}*", title).as_str()).unwrap();
-            }
-        }
+#[tauri::command]
+fn resolve_race_aliases<R: Runtime>(window: tauri::Window<R>) -> usize {
+    let changed = PROJECT.lock().unwrap().resolve_race_aliases();
+    if changed > 0 {
+        mark_window_edited(&window);
+        emit_project_update(window.app_handle());
     }
+    changed
+}
 
-    ret
+#[tauri::command]
+async fn get_voices() -> Vec<String> {
+    voices::get_voices()
 }
 
-/* Stage */
+#[tauri::command]
+fn project_stats() -> ProjectStats {
+    PROJECT.lock().unwrap().stats()
+}
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct EditorPayload {
-    pub scene: NanoID,
-    pub stage: Stage,
-    pub positions: Vec<PositionInfo>,
+#[tauri::command]
+fn compatibility_report(target_version: String) -> Result<Vec<String>, String> {
+    PROJECT.lock().unwrap().compatibility_report(&target_version)
 }
 
-fn open_stage_editor_impl<R: Runtime>(app: &tauri::AppHandle<R>, payload: EditorPayload) {
-    let stage = &payload.stage;
-    info!(
-        "Opening Stage {} from Scene {}",
-        stage.id.0, payload.scene.0
-    );
-    let window = WebviewWindowBuilder::new(
-        app,
-        format!("stage_editor_{}", stage.id.0),
-        tauri::WebviewUrl::App("./stage.html".into()),
-    )
-    .title(format!(
-        "Stage Editor [{}]",
-        if stage.name.is_empty() {
-            "Untitled"
-        } else {
-            stage.name.as_str()
-        }
-    ))
-    .min_inner_size(800.0, 600.0)
-    .inner_size(1152.0, 864.0)
-    .resizable(true)
-    .build()
-    .expect(&format!(
-        "Failed to create stage editor window for Stage {}",
-        stage.id.0
-    ));
-    window.clone().once("on_request_data", move |_| {
-        window.emit("on_data_received", payload.clone()).unwrap();
-    });
+#[tauri::command]
+fn stage_count_histogram() -> Vec<(usize, usize)> {
+    PROJECT.lock().unwrap().stage_count_histogram()
 }
 
 #[tauri::command]
-async fn open_stage_editor<R: Runtime>(
-    app: tauri::AppHandle<R>,
-    active_scene: Scene,
-    stage: Option<Stage>,
-) -> () {
-    open_stage_editor_impl(
-        &app,
-        EditorPayload {
-            scene: active_scene.id.clone(),
-            stage: stage.unwrap_or(Stage::new(&active_scene)),
-            positions: active_scene.positions.clone(),
-        },
-    );
+fn export_plan() -> Result<Vec<ExportFile>, String> {
+    let settings = SETTINGS.lock().unwrap().clone();
+    PROJECT
+        .lock()
+        .unwrap()
+        .export_plan(&settings.validation, &settings.export)
 }
 
+/// Approximate size of an export with the current settings, in bytes
 #[tauri::command]
-async fn open_stage_editor_from<R: Runtime>(
-    app: tauri::AppHandle<R>,
-    active_scene: Scene,
-    copy_stage: Stage,
-) -> () {
-    open_stage_editor_impl(
-        &app,
-        EditorPayload {
-            scene: active_scene.id.clone(),
-            stage: copy_stage.clone(),
-            positions: active_scene.positions.clone(),
-        },
-    );
+fn estimate_export_size() -> Result<u64, String> {
+    let settings = SETTINGS.lock().unwrap().clone();
+    PROJECT
+        .lock()
+        .unwrap()
+        .estimate_export_size(&settings.validation, &settings.export)
 }
 
 #[tauri::command]
-async fn stage_save_and_close<R: Runtime>(
-    app: tauri::AppHandle<R>,
-    window: tauri::Window<R>,
-    scene: NanoID,
-    positions: Vec<PositionInfo>,
-    stage: Stage,
-) -> () {
-    // IDEA: make give this event some unique id to allow
-    // front end distinguish the timings at which some stage editor has been opened
-    info!("Saving Stage {}", stage.id.0);
-    app.emit_to(
-        MAIN_WINDOW,
-        "on_stage_saved",
-        EditorPayload {
-            scene,
-            stage,
-            positions,
-        },
-    )
-    .unwrap();
-    let _ = window.close();
+fn scenes_changed_since_export() -> Vec<NanoID> {
+    PROJECT.lock().unwrap().scenes_changed_since_export()
 }
 
-/* Position related */
+#[tauri::command]
+fn project_content_hash() -> String {
+    PROJECT.lock().unwrap().content_hash()
+}
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct PositionPayload {
-    pub position: Position,
-    pub info: PositionInfo,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PackMetadata {
+    pub author: String,
+    pub description: String,
+    #[serde(default)]
+    pub priority: i32,
 }
 
 #[tauri::command]
-fn make_position() -> PositionPayload {
+fn get_pack_metadata() -> PackMetadata {
+    let prjct = PROJECT.lock().unwrap();
+    PackMetadata {
+        author: prjct.pack_author.clone(),
+        description: prjct.pack_description.clone(),
+        priority: prjct.priority,
+    }
+}
+
+#[tauri::command]
+fn set_pack_metadata<R: Runtime>(window: tauri::Window<R>, metadata: PackMetadata) -> () {
+    let mut prjct = PROJECT.lock().unwrap();
+    prjct.pack_author = metadata.author.trim().to_string();
+    prjct.pack_description = metadata.description.trim().to_string();
+    prjct.priority = metadata.priority;
+    info!("Updated metadata of project {}", prjct.pack_name);
+    mark_window_edited(&window);
+}
+
+#[tauri::command]
+fn missing_events(available: Vec<String>) -> Vec<String> {
+    PROJECT.lock().unwrap().missing_events(available)
+}
+
+/// Race keys used by the project, with the number of positions using them
+#[tauri::command]
+fn used_race_keys() -> BTreeMap<String, usize> {
+    PROJECT.lock().unwrap().race_key_usage()
+}
+
+/// Race keys used by each actor slot of the project
+#[tauri::command]
+fn race_key_matrix() -> Vec<Vec<String>> {
+    PROJECT.lock().unwrap().race_key_matrix()
+}
+
+#[tauri::command]
+fn get_furniture_types() -> Vec<String> {
+    furniture::get_furniture_types()
+}
+
+#[tauri::command]
+fn add_custom_furniture(name: String) -> Result<Vec<String>, String> {
+    furniture::add_custom_furniture(&name).map(|types| {
+        info!("Added custom furniture type {}", name);
+        types
+    })
+}
+
+#[tauri::command]
+fn remove_custom_furniture(name: String) -> Result<Vec<String>, String> {
+    furniture::remove_custom_furniture(&name).map(|types| {
+        info!("Removed custom furniture type {}", name);
+        types
+    })
+}
+
+fn mark_main_window_edited<R: Runtime>(app: &tauri::AppHandle<R>) -> () {
+    set_edited(true);
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+        if let Ok(title) = window.title() {
+            if !title.ends_with('*') {
+                let _ = window.set_title(format!("{}*", title).as_str());
+            }
+        }
+    }
+}
+
+fn mark_window_edited<R: Runtime>(window: &tauri::Window<R>) -> () {
+    set_edited(true);
+    if let Ok(title) = window.title() {
+        if !title.ends_with('*') {
+            window.set_title(format!("{}*", title).as_str()).unwrap();
+        }
+    }
+}
+
+#[tauri::command]
+async fn mark_as_edited<R: Runtime>(window: tauri::Window<R>) -> () {
+    mark_window_edited(&window);
+}
+
+#[tauri::command]
+fn get_in_darkmode() -> bool {
+    get_darkmode()
+}
+
+#[tauri::command]
+async fn clamp_all_offsets<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    window: tauri::Window<R>,
+    min: Offset,
+    max: Offset,
+) -> Result<usize, String> {
+    let affected = PROJECT.lock().unwrap().count_offsets_outside(&min, &max)?;
+    if affected == 0 {
+        info!("All offsets are within bounds, nothing to clamp");
+        return Ok(0);
+    }
+    let confirmed = app
+        .dialog()
+        .message(format!(
+            "{} position offsets lie outside the given range and will be clamped.\nContinue?",
+            affected
+        ))
+        .title("Clamp Offsets")
+        .buttons(MessageDialogButtons::YesNo)
+        .kind(MessageDialogKind::Warning)
+        .blocking_show();
+    if !confirmed {
+        info!("User cancelled clamping offsets.");
+        return Ok(0);
+    }
+    let mut prjct = PROJECT.lock().unwrap();
+    let changed = prjct.clamp_all_offsets(&min, &max)?;
+    if changed > 0 {
+        mark_window_edited(&window);
+        emit_project_update(window.app_handle());
+    }
+    info!("Clamped {} offsets", changed);
+    Ok(changed)
+}
+
+/// Most checkpoints kept at once, and the serialized size they may take up together
+const MAX_CHECKPOINTS: usize = 10;
+const MAX_CHECKPOINT_BYTES: usize = 64 * 1024 * 1024;
+
+/// Named snapshots of the project's scenes, oldest first. Held in memory only
+static CHECKPOINTS: Lazy<Mutex<Vec<Checkpoint>>> = Lazy::new(|| Mutex::new(vec![]));
+
+struct Checkpoint {
+    name: String,
+    scenes: HashMap<NanoID, Scene>,
+    size: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct CheckpointInfo {
+    name: String,
+    scenes: usize,
+    size: usize,
+}
+
+#[tauri::command]
+fn save_checkpoint(name: String) -> Result<(), String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Checkpoint name must not be empty".into());
+    }
+    let scenes = PROJECT.lock().unwrap().scenes.clone();
+    let size = serde_json::to_vec(&scenes).map_err(|e| e.to_string())?.len();
+    if size > MAX_CHECKPOINT_BYTES {
+        return Err(format!(
+            "Project is too large for a checkpoint ({} bytes, at most {})",
+            size, MAX_CHECKPOINT_BYTES
+        ));
+    }
+    let mut checkpoints = CHECKPOINTS.lock().unwrap();
+    checkpoints.retain(|it| it.name != name);
+    checkpoints.push(Checkpoint {
+        name: name.clone(),
+        scenes,
+        size,
+    });
+    // Drop the oldest checkpoints until both limits are met
+    while checkpoints.len() > MAX_CHECKPOINTS
+        || checkpoints.iter().map(|it| it.size).sum::<usize>() > MAX_CHECKPOINT_BYTES
+    {
+        let dropped = checkpoints.remove(0);
+        info!("Dropped checkpoint {}", dropped.name);
+    }
+    info!("Saved checkpoint {}", name);
+    Ok(())
+}
+
+#[tauri::command]
+fn list_checkpoints() -> Vec<CheckpointInfo> {
+    CHECKPOINTS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|it| CheckpointInfo {
+            name: it.name.clone(),
+            scenes: it.scenes.len(),
+            size: it.size,
+        })
+        .collect()
+}
+
+#[tauri::command]
+async fn restore_checkpoint<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    window: tauri::Window<R>,
+    name: String,
+) -> Result<bool, String> {
+    if !CHECKPOINTS.lock().unwrap().iter().any(|it| it.name == name) {
+        return Err(format!("Unknown checkpoint: {}", name));
+    }
+    let confirmed = app
+        .dialog()
+        .message(format!(
+            "All scenes will be replaced by the state of checkpoint '{}'. Changes made since then will be lost.\nContinue?",
+            name
+        ))
+        .title("Restore Checkpoint")
+        .buttons(MessageDialogButtons::YesNo)
+        .kind(MessageDialogKind::Warning)
+        .blocking_show();
+    if !confirmed {
+        info!("User cancelled restoring checkpoint {}.", name);
+        return Ok(false);
+    }
+    let scenes = CHECKPOINTS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|it| it.name == name)
+        .map(|it| it.scenes.clone())
+        .ok_or_else(|| format!("Unknown checkpoint: {}", name))?;
+    PROJECT.lock().unwrap().scenes = scenes;
+    // Open editors may refer to stages which do not exist in the checkpoint
+    close_stage_editors(&app, false);
+    info!("Restored checkpoint {}", name);
+    mark_window_edited(&window);
+    emit_project_update(&app);
+    Ok(true)
+}
+
+#[tauri::command]
+async fn reassign_all_ids<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    window: tauri::Window<R>,
+) -> Result<usize, String> {
+    let confirmed = app
+        .dialog()
+        .message("Every scene and stage will receive a new id. Exports of this project made before will no longer match it, and open stage editors will be closed.\nContinue?")
+        .title("Reassign IDs")
+        .buttons(MessageDialogButtons::YesNo)
+        .kind(MessageDialogKind::Warning)
+        .blocking_show();
+    if !confirmed {
+        info!("User cancelled reassigning ids.");
+        return Ok(0);
+    }
+    // Open editors refer to the old stage ids
+    close_stage_editors(&app, false);
+    let reassigned = PROJECT.lock().unwrap().reassign_all_ids();
+    info!("Reassigned {} ids", reassigned);
+    mark_window_edited(&window);
+    emit_project_update(&app);
+    Ok(reassigned)
+}
+
+#[tauri::command]
+async fn reset_settings(app: tauri::AppHandle) -> Result<bool, String> {
+    let confirmed = app
+        .dialog()
+        .message("This will restore all preferences to their defaults, including keybindings, custom furniture and race keys, new scene defaults and window sizes. Position templates and race key aliases are kept.\nContinue?")
+        .title("Reset Settings")
+        .buttons(MessageDialogButtons::YesNo)
+        .kind(MessageDialogKind::Warning)
+        .blocking_show();
+    if !confirmed {
+        info!("User cancelled resetting settings.");
+        return Ok(false);
+    }
+    let darkmode = {
+        let mut settings = SETTINGS.lock().unwrap();
+        // Templates and aliases are user data rather than preferences
+        let position_templates = std::mem::take(&mut settings.position_templates);
+        let race_key_aliases = std::mem::take(&mut settings.race_key_aliases);
+        *settings = settings::Settings {
+            position_templates,
+            race_key_aliases,
+            ..Default::default()
+        };
+        settings.save()?;
+        settings.darkmode
+    };
+    IS_DARKMODE.store(darkmode, Ordering::Relaxed);
+    // Rebuild the menu for the default accelerators and dark mode check state
+    let menu = get_menu(&app).map_err(|e| e.to_string())?;
+    let window = app
+        .get_webview_window(MAIN_WINDOW)
+        .ok_or("Main window not found".to_string())?;
+    window.set_menu(menu).map_err(|e| e.to_string())?;
+    for label in app.webview_windows().into_keys() {
+        if let Err(err) = app.emit_to(label.as_str(), "toggle_darkmode", darkmode) {
+            error!("Unable to toggle darkmode, event failure: {}", err);
+        }
+    }
+    info!("Reset settings to defaults");
+    Ok(true)
+}
+
+#[tauri::command]
+fn get_keybindings() -> Keybindings {
+    SETTINGS.lock().unwrap().keybindings.clone()
+}
+
+#[tauri::command]
+async fn set_keybindings(app: tauri::AppHandle, keybindings: Keybindings) -> Result<(), String> {
+    keybindings.validate()?;
+    {
+        let mut settings = SETTINGS.lock().unwrap();
+        settings.keybindings = keybindings;
+        settings.save()?;
+    }
+    // Rebuild the menu so the new accelerators apply immediately
+    let menu = get_menu(&app).map_err(|e| e.to_string())?;
+    let window = app
+        .get_webview_window(MAIN_WINDOW)
+        .ok_or("Main window not found".to_string())?;
+    window.set_menu(menu).map_err(|e| e.to_string())?;
+    info!("Updated keybindings");
+    Ok(())
+}
+
+#[tauri::command]
+fn get_scene_defaults() -> SceneDefaults {
+    SETTINGS.lock().unwrap().new_scene.clone()
+}
+
+#[tauri::command]
+fn set_scene_defaults(defaults: SceneDefaults) -> Result<(), String> {
+    let mut settings = SETTINGS.lock().unwrap();
+    settings.new_scene = defaults;
+    settings.save()?;
+    info!("Updated new scene defaults");
+    Ok(())
+}
+
+#[tauri::command]
+fn set_default_race_key(key: String) -> Result<String, String> {
+    let race = if key.trim().is_empty() {
+        String::new()
+    } else {
+        racekeys::find_race_key(&key).ok_or_else(|| format!("Unknown race key {}", key))?
+    };
+    let mut settings = SETTINGS.lock().unwrap();
+    settings.new_scene.race = race.clone();
+    settings.save()?;
+    info!("Set default race key of new positions to {}", race);
+    Ok(race)
+}
+
+#[tauri::command]
+fn get_position_templates() -> BTreeMap<String, Vec<PositionInfo>> {
+    SETTINGS.lock().unwrap().position_templates.clone()
+}
+
+#[tauri::command]
+fn save_position_template(name: String, positions: Vec<PositionInfo>) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Template name must not be empty".into());
+    }
+    if positions.is_empty() {
+        return Err("Template must have at least one position".into());
+    }
+    let mut settings = SETTINGS.lock().unwrap();
+    settings.position_templates.insert(name.to_string(), positions);
+    settings.save()?;
+    info!("Saved position template {}", name);
+    Ok(())
+}
+
+#[tauri::command]
+async fn apply_position_template<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    name: String,
+    overwrite: bool,
+) -> Result<usize, String> {
+    let template = SETTINGS
+        .lock()
+        .unwrap()
+        .position_templates
+        .get(&name)
+        .cloned()
+        .ok_or_else(|| format!("Unknown position template {}", name))?;
+    let message = if overwrite {
+        format!(
+            "This will replace the actors of the scene with the {} positions of template \"{}\" and resize every stage to match.\nContinue?",
+            template.len(),
+            name
+        )
+    } else {
+        format!(
+            "This will add the positions of template \"{}\" the scene doesn't have yet to every stage.\nContinue?",
+            name
+        )
+    };
+    let confirmed = app
+        .dialog()
+        .message(message)
+        .title("Apply Position Template")
+        .buttons(MessageDialogButtons::YesNo)
+        .kind(MessageDialogKind::Info)
+        .blocking_show();
+    if !confirmed {
+        info!("User cancelled applying position template.");
+        return Ok(0);
+    }
+    let mut prjct = PROJECT.lock().unwrap();
+    let applied = prjct
+        .get_scene_mut(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?
+        .apply_position_template(&template, overwrite)?;
+    info!(
+        "Applied {} positions of template {} to Scene {}",
+        applied, name, scene_id.0
+    );
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+    Ok(applied)
+}
+
+#[tauri::command]
+fn get_import_settings() -> ImportSettings {
+    SETTINGS.lock().unwrap().import.clone()
+}
+
+#[tauri::command]
+fn set_import_settings(import: ImportSettings) -> Result<(), String> {
+    let mut settings = SETTINGS.lock().unwrap();
+    settings.import = import;
+    settings.save()
+}
+
+#[tauri::command]
+fn normalize_offset_precision<R: Runtime>(window: tauri::Window<R>) -> usize {
+    let decimals = SETTINGS.lock().unwrap().import.offset_decimals;
+    let mut prjct = PROJECT.lock().unwrap();
+    let adjusted = prjct.normalize_offset_precision(decimals);
+    if adjusted > 0 {
+        mark_window_edited(&window);
+        emit_project_update(window.app_handle());
+    }
+    adjusted
+}
+
+#[tauri::command]
+fn get_display_units() -> DisplayUnits {
+    SETTINGS.lock().unwrap().display_units.clone()
+}
+
+#[tauri::command]
+fn set_display_units(units: DisplayUnits) -> Result<(), String> {
+    if !units.per_game_unit.is_finite() || units.per_game_unit <= 0.0 {
+        return Err("Unit conversion factor must be a positive number".into());
+    }
+    let mut settings = SETTINGS.lock().unwrap();
+    settings.display_units = units;
+    settings.save()?;
+    info!(
+        "Displaying offsets in {} ({} per game unit)",
+        settings.display_units.name, settings.display_units.per_game_unit
+    );
+    Ok(())
+}
+
+#[tauri::command]
+fn convert_offset_units(offset: Offset, from: OffsetUnit, to: OffsetUnit) -> Offset {
+    let factor = SETTINGS.lock().unwrap().display_units.per_game_unit;
+    project::define::convert_offset_units(&offset, from, to, factor)
+}
+
+#[tauri::command]
+async fn canonicalize_race_keys<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    window: tauri::Window<R>,
+) -> usize {
+    let confirmed = app
+        .dialog()
+        .message("This will rewrite every race key in the project to its canonical spelling.\nContinue?")
+        .title("Normalize Race Keys")
+        .buttons(MessageDialogButtons::YesNo)
+        .kind(MessageDialogKind::Info)
+        .blocking_show();
+    if !confirmed {
+        info!("User cancelled race key normalization.");
+        return 0;
+    }
+    let mut prjct = PROJECT.lock().unwrap();
+    let changed = prjct.canonicalize_race_keys();
+    let unknown = prjct.unknown_race_keys();
+    if changed > 0 {
+        mark_window_edited(&window);
+        emit_project_update(window.app_handle());
+    }
+    drop(prjct);
+    info!("Normalized {} race keys", changed);
+    if !unknown.is_empty() {
+        app.dialog()
+            .message(format!(
+                "Normalized {} race keys. The following race keys are unknown and have been left unchanged:\n{}",
+                changed,
+                unknown.join(", ")
+            ))
+            .title("Normalize Race Keys")
+            .kind(MessageDialogKind::Warning)
+            .blocking_show();
+    }
+    changed
+}
+
+#[tauri::command]
+fn find_text_issues() -> Vec<TextIssue> {
+    PROJECT.lock().unwrap().find_text_issues()
+}
+
+#[tauri::command]
+fn trim_all_text<R: Runtime>(window: tauri::Window<R>) -> usize {
+    let fixed = PROJECT.lock().unwrap().trim_all_text();
+    if fixed > 0 {
+        mark_window_edited(&window);
+        emit_project_update(window.app_handle());
+    }
+    fixed
+}
+
+#[tauri::command]
+fn dead_end_stages() -> Vec<(NanoID, NanoID)> {
+    PROJECT.lock().unwrap().dead_end_stages()
+}
+
+#[tauri::command]
+fn stages_using_event(event: String) -> Vec<(NanoID, NanoID)> {
+    PROJECT.lock().unwrap().stages_using_event(event.trim())
+}
+
+#[tauri::command]
+async fn replace_event<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    window: tauri::Window<R>,
+    old: String,
+    new: String,
+) -> Result<usize, String> {
+    let (old, new) = (old.trim().to_string(), new.trim().to_string());
+    if old.is_empty() || new.is_empty() {
+        return Err("Event names must not be empty".into());
+    }
+    let count = PROJECT.lock().unwrap().count_event(&old);
+    if count == 0 || old == new {
+        return Ok(0);
+    }
+    let confirmed = app
+        .dialog()
+        .message(format!(
+            "This will rename {} occurrences of the event \"{}\" to \"{}\".\nContinue?",
+            count, old, new
+        ))
+        .title("Replace Event")
+        .buttons(MessageDialogButtons::YesNo)
+        .kind(MessageDialogKind::Info)
+        .blocking_show();
+    if !confirmed {
+        info!("User cancelled event replacement.");
+        return Ok(0);
+    }
+    let changed = PROJECT.lock().unwrap().replace_event(&old, &new);
+    if changed > 0 {
+        mark_window_edited(&window);
+        emit_project_update(window.app_handle());
+    }
+    info!("Renamed {} events from {} to {}", changed, old, new);
+    Ok(changed)
+}
+
+#[tauri::command]
+fn rename_tag<R: Runtime>(window: tauri::Window<R>, old: String, new: String) -> Result<usize, String> {
+    let (old, new) = (old.trim(), new.trim());
+    if old.is_empty() || new.is_empty() {
+        return Err("Tags must not be empty".into());
+    }
+    let changed = PROJECT.lock().unwrap().rename_tag(old, new);
+    info!("Renamed tag {} to {} in {} tag lists", old, new, changed);
+    if changed > 0 {
+        mark_window_edited(&window);
+        emit_project_update(window.app_handle());
+    }
+    Ok(changed)
+}
+
+/* Scene */
+
+#[tauri::command]
+fn create_blank_scene() -> Scene {
+    let defaults = SETTINGS.lock().unwrap().new_scene.clone();
+    let mut scene = Scene::default();
+    if !defaults.furniture.is_empty() {
+        scene.furniture.furni_types = defaults.furniture;
+    }
+    scene.tags = defaults.tags;
+    if !defaults.race.is_empty() {
+        for info in &mut scene.positions {
+            info.race = defaults.race.clone();
+        }
+    }
+    scene
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct SaveSceneResult {
+    /// False if the stored scene already held the same data, the project is left unedited then
+    changed: bool,
+    /// Set if the name is very similar to the name of another scene
+    warning: Option<String>,
+}
+
+#[tauri::command]
+async fn save_scene<R: Runtime>(
+    window: tauri::Window<R>,
+    mut scene: Scene,
+) -> Result<SaveSceneResult, String> {
+    // Never persist offsets the export cannot represent
+    if !scene.furniture.offset.is_finite() {
+        return Err(format!("Scene {}: Furniture offset is not a finite number", scene.name));
+    }
+    if let Some((stage, i)) = scene.non_finite_offsets().first() {
+        return Err(format!(
+            "Stage {} Position {}: Offset is not a finite number",
+            stage.name,
+            i + 1
+        ));
+    }
+    // Stage edits are only complete here, the stage editor saves a single stage
+    scene.compact_positions();
+    let mut prjct = PROJECT.lock().unwrap();
+    let changed = !prjct
+        .get_scene(&scene.id)
+        .is_some_and(|stored| stored.same_content(&scene));
+    // Only warn about names which are new or changed, not on every save of the scene
+    let renamed = prjct
+        .get_scene(&scene.id)
+        .map_or(true, |stored| stored.name != scene.name);
+    let similar = if renamed {
+        prjct.similar_scene_names(&scene.id, &scene.name)
+    } else {
+        vec![]
+    };
+    let warning = if similar.is_empty() {
+        None
+    } else {
+        let msg = format!(
+            "Scene name '{}' is very similar to: {}",
+            scene.name,
+            similar.join(", ")
+        );
+        warn!("{}", msg);
+        Some(msg)
+    };
+    if changed {
+        prjct.save_scene(scene);
+        mark_window_edited(&window);
+    } else {
+        info!("Scene {} is unchanged", scene.id.0);
+    }
+    Ok(SaveSceneResult { changed, warning })
+}
+
+#[tauri::command]
+fn sorted_scenes(key: SortKey, ascending: bool) -> Vec<Scene> {
+    PROJECT
+        .lock()
+        .unwrap()
+        .sorted_scenes(key, ascending)
+        .into_iter()
+        .cloned()
+        .collect()
+}
+
+#[tauri::command]
+fn delete_scene<R: Runtime>(window: tauri::Window<R>, id: NanoID) -> Result<Scene, String> {
+    let ret = PROJECT.lock().unwrap().discard_scene(&id).ok_or_else(|| {
+        let msg = format!("Invalid Scene ID: {}", id.0);
+        error!("{}", msg);
+        msg
+    });
+
+    if ret.is_ok() {
+        mark_window_edited(&window);
+    }
+
+    ret
+}
+
+#[tauri::command]
+fn preview_scene_export(id: NanoID) -> Result<String, String> {
+    PROJECT.lock().unwrap().preview_scene_export(&id)
+}
+
+#[tauri::command]
+fn export_scene_json_anonymized(id: NanoID) -> Result<String, String> {
+    let prjct = PROJECT.lock().unwrap();
+    let scene = prjct
+        .get_scene(&id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", id.0))?;
+    serde_json::to_string_pretty(&scene.anonymized()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn export_by_tags(app: tauri::AppHandle, tags: Vec<String>, match_all: bool) -> Result<usize, String> {
+    let settings = SETTINGS.lock().unwrap().clone();
+    let mut prjct = PROJECT.lock().unwrap();
+    let hashes = prjct.export_hashes.clone();
+    let exported = prjct
+        .export_by_tags(&tags, match_all, &app, &settings.validation, &settings.export)
+        .map_err(|e| e.to_string())?;
+    // The stored export hashes changed and need saving
+    if prjct.export_hashes != hashes {
+        mark_main_window_edited(&app);
+        emit_project_update(&app);
+    }
+    Ok(exported)
+}
+
+#[tauri::command]
+fn save_canonical(path: PathBuf) -> Result<(), String> {
+    PROJECT.lock().unwrap().save_canonical(&path)?;
+    info!("Saved canonical project to {}", path.display());
+    Ok(())
+}
+
+#[tauri::command]
+fn get_canonical_saves() -> bool {
+    SETTINGS.lock().unwrap().canonical_saves
+}
+
+#[tauri::command]
+fn set_canonical_saves(enabled: bool) -> Result<(), String> {
+    let mut settings = SETTINGS.lock().unwrap();
+    settings.canonical_saves = enabled;
+    settings.save()
+}
+
+#[tauri::command]
+fn export_as_project(ids: Vec<NanoID>, path: PathBuf) -> Result<PathBuf, String> {
+    PROJECT.lock().unwrap().export_as_project(&ids, &path)
+}
+
+#[tauri::command]
+fn duplicate_project(new_name: String, path: PathBuf, reassign_ids: bool) -> Result<PathBuf, String> {
+    PROJECT
+        .lock()
+        .unwrap()
+        .duplicate_project(&new_name, &path, reassign_ids)
+}
+
+#[tauri::command]
+fn export_scene_toml(id: NanoID) -> Result<String, String> {
+    let prjct = PROJECT.lock().unwrap();
+    prjct
+        .get_scene(&id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", id.0))?
+        .to_toml()
+}
+
+#[tauri::command]
+fn import_scene_toml<R: Runtime>(window: tauri::Window<R>, toml: String) -> Result<Scene, String> {
+    let mut scene = Scene::from_toml(&toml)?;
+    let mut prjct = PROJECT.lock().unwrap();
+    // Importing a scene twice should not overwrite the first copy or reuse its stage ids
+    scene.reassign_ids();
+    info!("Imported Scene {} from TOML", scene.name);
+    let scene = prjct.save_scene(scene).clone();
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+    Ok(scene)
+}
+
+#[tauri::command]
+fn scene_graph_dot(scene_id: NanoID) -> Result<String, String> {
+    let prjct = PROJECT.lock().unwrap();
+    prjct
+        .get_scene(&scene_id)
+        .map(|scene| scene.to_dot())
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))
+}
+
+#[tauri::command]
+fn find_incomplete_scenes() -> Vec<NanoID> {
+    PROJECT.lock().unwrap().find_incomplete_scenes()
+}
+
+#[tauri::command]
+fn scenes_with_unreachable_climax() -> Vec<NanoID> {
+    PROJECT.lock().unwrap().scenes_with_unreachable_climax()
+}
+
+#[tauri::command]
+fn scenes_with_mismatched_counts() -> Vec<NanoID> {
+    PROJECT.lock().unwrap().scenes_with_mismatched_counts()
+}
+
+#[tauri::command]
+fn export_storyboard(scene_id: NanoID, path: PathBuf) -> Result<(), String> {
+    PROJECT.lock().unwrap().export_storyboard(&scene_id, &path)
+}
+
+#[tauri::command]
+fn export_scene_frontmatter(id: NanoID) -> Result<String, String> {
+    PROJECT.lock().unwrap().export_scene_frontmatter(&id)
+}
+
+#[tauri::command]
+fn export_offset_sheet(scene_id: NanoID, path: PathBuf) -> Result<(), String> {
+    PROJECT.lock().unwrap().export_offset_sheet(&scene_id, &path)
+}
+
+#[tauri::command]
+fn export_strings_template(path: PathBuf) -> Result<usize, String> {
+    PROJECT.lock().unwrap().export_strings_template(&path)
+}
+
+#[tauri::command]
+fn import_strings<R: Runtime>(
+    window: tauri::Window<R>,
+    path: PathBuf,
+) -> Result<StringsImportSummary, String> {
+    let summary = PROJECT.lock().unwrap().import_strings(&path)?;
+    if summary.applied > 0 {
+        mark_window_edited(&window);
+        emit_project_update(window.app_handle());
+    }
+    Ok(summary)
+}
+
+#[tauri::command]
+fn export_animation_checklist(path: PathBuf) -> Result<usize, String> {
+    PROJECT.lock().unwrap().export_animation_checklist(&path)
+}
+
+#[tauri::command]
+fn check_thumbnails() -> Vec<(NanoID, PathBuf)> {
+    PROJECT.lock().unwrap().check_thumbnails()
+}
+
+#[tauri::command]
+fn export_thumbnails(dir: PathBuf) -> Result<usize, String> {
+    PROJECT.lock().unwrap().export_thumbnails(&dir)
+}
+
+#[tauri::command]
+fn rename_stages_pattern<R: Runtime>(
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    pattern: String,
+) -> Result<Scene, String> {
+    if pattern.trim().is_empty() {
+        return Err("Stage name pattern must not be empty".into());
+    }
+    let mut prjct = PROJECT.lock().unwrap();
+    let scene = prjct
+        .get_scene_mut(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?;
+    scene.rename_stages(&pattern);
+    info!("Renamed stages of Scene {} to pattern {}", scene_id.0, pattern);
+    let scene = scene.clone();
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+    Ok(scene)
+}
+
+#[tauri::command]
+fn import_offset_string<R: Runtime>(
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    yaml: String,
+) -> Result<usize, String> {
+    let mut prjct = PROJECT.lock().unwrap();
+    let applied = prjct.import_offset_string(&scene_id, &yaml)?;
+    info!("Imported {} offsets into Scene {}", applied, scene_id.0);
+    if applied > 0 {
+        mark_window_edited(&window);
+        emit_project_update(window.app_handle());
+    }
+    Ok(applied)
+}
+
+#[tauri::command]
+fn import_offset_by_stage_name<R: Runtime>(
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    yaml: String,
+) -> Result<StageOffsetImportSummary, String> {
+    let mut prjct = PROJECT.lock().unwrap();
+    let summary = prjct.import_offset_string_by_stage_name(&scene_id, &yaml)?;
+    info!(
+        "Imported {} offsets into Scene {}, {} stage names unmatched, {} stages without offsets",
+        summary.applied,
+        scene_id.0,
+        summary.unmatched.len(),
+        summary.missing.len()
+    );
+    if summary.applied > 0 {
+        mark_window_edited(&window);
+        emit_project_update(window.app_handle());
+    }
+    Ok(summary)
+}
+
+#[tauri::command]
+fn reset_position_offset<R: Runtime>(
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    stage_id: NanoID,
+    position_index: usize,
+    include_scale: bool,
+) -> Result<Scene, String> {
+    let mut prjct = PROJECT.lock().unwrap();
+    let scene = prjct
+        .get_scene_mut(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?;
+    scene.reset_position_offset(&stage_id, position_index, include_scale)?;
+    info!(
+        "Reset offset of Position {} in Stage {}",
+        position_index, stage_id.0
+    );
+    let scene = scene.clone();
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+    Ok(scene)
+}
+
+#[tauri::command]
+fn mirror_position<R: Runtime>(
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    stage_id: NanoID,
+    source_index: usize,
+    target_index: usize,
+) -> Result<Scene, String> {
+    let mut prjct = PROJECT.lock().unwrap();
+    let scene = prjct
+        .get_scene_mut(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?;
+    scene.mirror_position(&stage_id, source_index, target_index)?;
+    info!(
+        "Mirrored offset of Position {} onto Position {} in Stage {}",
+        source_index, target_index, stage_id.0
+    );
+    let scene = scene.clone();
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+    Ok(scene)
+}
+
+#[tauri::command]
+fn reset_all_offsets<R: Runtime>(
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    include_scale: bool,
+) -> Result<Scene, String> {
+    let mut prjct = PROJECT.lock().unwrap();
+    let scene = prjct
+        .get_scene_mut(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?;
+    scene.reset_all_offsets(include_scale);
+    info!("Reset all offsets of Scene {}", scene_id.0);
+    let scene = scene.clone();
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+    Ok(scene)
+}
+
+#[tauri::command]
+fn set_scene_furniture<R: Runtime>(
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    furniture: String,
+    apply_offsets: bool,
+) -> Result<bool, String> {
+    if !furniture::is_known_furniture(&furniture) {
+        return Err(format!("Unknown furniture type: {}", furniture));
+    }
+    let mut prjct = PROJECT.lock().unwrap();
+    let has_offsets = prjct
+        .get_scene_mut(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?
+        .set_furniture(&furniture, apply_offsets);
+    info!("Set furniture of Scene {} to {}", scene_id.0, furniture);
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+    Ok(has_offsets)
+}
+
+#[tauri::command]
+fn apply_furniture_profile<R: Runtime>(
+    window: tauri::Window<R>,
+    furniture: String,
+    offsets: Vec<Offset>,
+) -> Result<FurnitureProfileSummary, String> {
+    if !furniture::is_known_furniture(&furniture) {
+        return Err(format!("Unknown furniture type: {}", furniture));
+    }
+    let mut prjct = PROJECT.lock().unwrap();
+    let summary = prjct.apply_furniture_profile(&furniture, &offsets)?;
+    info!(
+        "Applied {} furniture profile to {} positions in {} scenes",
+        furniture, summary.positions, summary.scenes
+    );
+    for scene in &summary.mismatched {
+        warn!("Skipped {} furniture profile for Scene {}", furniture, scene);
+    }
+    if summary.positions > 0 {
+        mark_window_edited(&window);
+        emit_project_update(window.app_handle());
+    }
+    Ok(summary)
+}
+
+#[tauri::command]
+fn set_start_stage<R: Runtime>(
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    stage_id: Option<NanoID>,
+) -> Result<(), String> {
+    let mut prjct = PROJECT.lock().unwrap();
+    prjct
+        .get_scene_mut(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?
+        .set_start_stage(stage_id)?;
+    info!("Updated start stage of Scene {}", scene_id.0);
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+    Ok(())
+}
+
+/// Offsets closer than this are considered equal when comparing stages
+const DEFAULT_OFFSET_TOLERANCE: f32 = 0.01;
+
+#[tauri::command]
+fn find_identical_stages(
+    scene_id: NanoID,
+    tolerance: Option<f32>,
+) -> Result<Vec<Vec<NanoID>>, String> {
+    let prjct = PROJECT.lock().unwrap();
+    prjct
+        .get_scene(&scene_id)
+        .map(|scene| scene.find_identical_stages(tolerance.unwrap_or(DEFAULT_OFFSET_TOLERANCE)))
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))
+}
+
+#[tauri::command]
+fn check_offset_symmetry(scene_id: NanoID, tolerance: Option<f32>) -> Result<Vec<NanoID>, String> {
+    let prjct = PROJECT.lock().unwrap();
+    prjct
+        .get_scene(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?
+        .check_offset_symmetry(tolerance.unwrap_or(DEFAULT_OFFSET_TOLERANCE))
+}
+
+#[tauri::command]
+fn swap_positions<R: Runtime>(
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    a: usize,
+    b: usize,
+) -> Result<(), String> {
+    let mut prjct = PROJECT.lock().unwrap();
+    prjct
+        .get_scene_mut(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?
+        .swap_positions(a, b)?;
+    info!("Swapped positions {} and {} of Scene {}", a, b, scene_id.0);
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+    Ok(())
+}
+
+#[tauri::command]
+fn copy_positions<R: Runtime>(
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    from_stage: NanoID,
+    to_stage: NanoID,
+    overwrite: bool,
+) -> Result<usize, String> {
+    let mut prjct = PROJECT.lock().unwrap();
+    let copied = prjct
+        .get_scene_mut(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?
+        .copy_positions(&from_stage, &to_stage, overwrite)?;
+    info!(
+        "Copied {} positions from Stage {} to Stage {}",
+        copied, from_stage.0, to_stage.0
+    );
+    if copied > 0 {
+        mark_window_edited(&window);
+        emit_project_update(window.app_handle());
+    }
+    Ok(copied)
+}
+
+#[tauri::command]
+fn simulate_playback(scene_id: NanoID, max_steps: usize) -> Result<Vec<NanoID>, String> {
+    let prjct = PROJECT.lock().unwrap();
+    prjct
+        .get_scene(&scene_id)
+        .map(|scene| scene.simulate_playback(max_steps))
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))
+}
+
+#[tauri::command]
+fn scene_from_stages<R: Runtime>(
+    window: tauri::Window<R>,
+    stage_refs: Vec<(NanoID, NanoID)>,
+) -> Result<Scene, String> {
+    let mut prjct = PROJECT.lock().unwrap();
+    let scene = prjct.scene_from_stages(&stage_refs)?.clone();
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+    Ok(scene)
+}
+
+#[tauri::command]
+fn estimated_duration(scene_id: NanoID) -> Result<f32, String> {
+    let prjct = PROJECT.lock().unwrap();
+    prjct
+        .get_scene(&scene_id)
+        .map(|scene| scene.estimated_duration())
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))
+}
+
+#[tauri::command]
+fn set_scene_sexes<R: Runtime>(
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    sexes: Vec<Sex>,
+) -> Result<(), String> {
+    let mut prjct = PROJECT.lock().unwrap();
+    prjct
+        .get_scene_mut(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?
+        .set_sexes(sexes)?;
+    info!("Set position sexes of Scene {}", scene_id.0);
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+    Ok(())
+}
+
+#[tauri::command]
+fn dedupe_stage_names<R: Runtime>(window: tauri::Window<R>, scene_id: NanoID) -> Result<usize, String> {
+    let mut prjct = PROJECT.lock().unwrap();
+    let renamed = prjct
+        .get_scene_mut(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?
+        .dedupe_stage_names();
+    info!("Renamed {} duplicate stages of Scene {}", renamed, scene_id.0);
+    if renamed > 0 {
+        mark_window_edited(&window);
+        emit_project_update(window.app_handle());
+    }
+    Ok(renamed)
+}
+
+#[tauri::command]
+fn add_actor_to_scene<R: Runtime>(
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    race_key: String,
+    sex: Sex,
+) -> Result<Scene, String> {
+    let max_positions = SETTINGS.lock().unwrap().validation.max_positions;
+    let race = racekeys::find_race_key(&race_key).ok_or_else(|| format!("Unknown race key {}", race_key))?;
+    let mut prjct = PROJECT.lock().unwrap();
+    let scene = prjct
+        .get_scene_mut(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?;
+    let count = scene.add_actor(race.clone(), sex, max_positions)?;
+    info!("Added a {} actor to Scene {}, it now has {} actors", race, scene_id.0, count);
+    let scene = scene.clone();
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+    Ok(scene)
+}
+
+#[tauri::command]
+fn set_looping_by_criteria<R: Runtime>(
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    only_non_climax: bool,
+    looping: bool,
+) -> Result<usize, String> {
+    let mut prjct = PROJECT.lock().unwrap();
+    let changed = prjct
+        .get_scene_mut(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?
+        .set_looping(only_non_climax, looping);
+    info!("Set looping of {} stages of Scene {} to {}", changed, scene_id.0, looping);
+    if changed > 0 {
+        mark_window_edited(&window);
+        emit_project_update(window.app_handle());
+    }
+    Ok(changed)
+}
+
+#[tauri::command]
+fn merge_stages<R: Runtime>(
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    keep: NanoID,
+    remove: NanoID,
+) -> Result<usize, String> {
+    let mut prjct = PROJECT.lock().unwrap();
+    let redirected = prjct
+        .get_scene_mut(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?
+        .merge_stages(&keep, &remove)?;
+    info!(
+        "Merged Stage {} into Stage {}, redirected {} nav links",
+        remove.0, keep.0, redirected
+    );
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+    Ok(redirected)
+}
+
+#[tauri::command]
+fn remove_actor_from_scene<R: Runtime>(
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    index: usize,
+) -> Result<Scene, String> {
+    let mut prjct = PROJECT.lock().unwrap();
+    let scene = prjct
+        .get_scene_mut(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?;
+    let count = scene.remove_actor(index)?;
+    info!("Removed actor {} of Scene {}, it now has {} actors", index, scene_id.0, count);
+    let scene = scene.clone();
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+    Ok(scene)
+}
+
+#[tauri::command]
+fn set_scene_required_tags<R: Runtime>(
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    tags: Vec<String>,
+) -> Result<Vec<String>, String> {
+    let mut prjct = PROJECT.lock().unwrap();
+    let scene = prjct
+        .get_scene_mut(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?;
+    scene.set_required_tags(tags)?;
+    info!("Set required actor tags of Scene {}", scene_id.0);
+    let tags = scene.required_tags.clone();
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+    Ok(tags)
+}
+
+#[tauri::command]
+fn repair_nav_links<R: Runtime>(window: tauri::Window<R>, scene_id: NanoID) -> Result<usize, String> {
+    let mut prjct = PROJECT.lock().unwrap();
+    let repaired = prjct
+        .get_scene_mut(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?
+        .repair_nav_links();
+    info!("Repaired {} nav links of Scene {}", repaired, scene_id.0);
+    if repaired > 0 {
+        mark_window_edited(&window);
+        emit_project_update(window.app_handle());
+    }
+    Ok(repaired)
+}
+
+#[tauri::command]
+fn find_nav_cycles(scene_id: NanoID) -> Result<Vec<Vec<NanoID>>, String> {
+    let prjct = PROJECT.lock().unwrap();
+    prjct
+        .get_scene(&scene_id)
+        .map(|scene| scene.find_nav_cycles())
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))
+}
+
+#[tauri::command]
+fn export_nav_json(scene_id: NanoID) -> Result<String, String> {
+    PROJECT
+        .lock()
+        .unwrap()
+        .get_scene(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?
+        .to_nav_json()
+}
+
+#[tauri::command]
+fn analyze_nav_graph(scene_id: NanoID) -> Result<NavAnalysis, String> {
+    let prjct = PROJECT.lock().unwrap();
+    prjct
+        .get_scene(&scene_id)
+        .map(|scene| scene.analyze_nav_graph())
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))
+}
+
+#[tauri::command]
+fn get_scene_order() -> Vec<NanoID> {
+    PROJECT
+        .lock()
+        .unwrap()
+        .ordered_scenes()
+        .into_iter()
+        .map(|scene| scene.id.clone())
+        .collect()
+}
+
+#[tauri::command]
+fn reorder_scenes<R: Runtime>(window: tauri::Window<R>, ordered_ids: Vec<NanoID>) -> () {
+    PROJECT.lock().unwrap().reorder_scenes(&ordered_ids);
+    info!("Reordered scenes");
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+}
+
+#[tauri::command]
+fn import_scene_order<R: Runtime>(
+    window: tauri::Window<R>,
+    path: PathBuf,
+) -> Result<SceneOrderImportSummary, String> {
+    let summary = PROJECT.lock().unwrap().import_scene_order(&path)?;
+    for line in &summary.unmatched {
+        warn!("No scene matches {} of the scene order", line);
+    }
+    if summary.matched > 0 {
+        mark_window_edited(&window);
+        emit_project_update(window.app_handle());
+    }
+    Ok(summary)
+}
+
+#[tauri::command]
+fn reorder_stages<R: Runtime>(
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    ordered_ids: Vec<NanoID>,
+) -> Result<Scene, String> {
+    let mut prjct = PROJECT.lock().unwrap();
+    let scene = prjct
+        .get_scene_mut(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?;
+    scene.reorder_stages(&ordered_ids);
+    info!("Reordered stages of Scene {}", scene_id.0);
+    let scene = scene.clone();
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+    Ok(scene)
+}
+
+#[tauri::command]
+fn set_scene_thumbnail<R: Runtime>(
+    window: tauri::Window<R>,
+    id: NanoID,
+    path: Option<PathBuf>,
+) -> Result<Scene, String> {
+    let scene = PROJECT
+        .lock()
+        .unwrap()
+        .set_scene_image(&id, path)
+        .map(|scene| scene.clone())?;
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+    Ok(scene)
+}
+
+#[tauri::command]
+fn set_stage_thumbnail<R: Runtime>(
+    window: tauri::Window<R>,
+    scene_id: NanoID,
+    stage_id: NanoID,
+    path: Option<PathBuf>,
+) -> Result<Scene, String> {
+    let scene = PROJECT
+        .lock()
+        .unwrap()
+        .set_stage_image(&scene_id, &stage_id, path)
+        .map(|scene| scene.clone())?;
+    mark_window_edited(&window);
+    emit_project_update(window.app_handle());
+    Ok(scene)
+}
+
+#[tauri::command]
+async fn pick_scene_thumbnail<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    window: tauri::Window<R>,
+    id: NanoID,
+) -> Result<Scene, String> {
+    let path = app
+        .dialog()
+        .file()
+        .set_title("Set Thumbnail...")
+        .add_filter("Image", &["png", "jpg", "jpeg", "bmp", "webp"])
+        .blocking_pick_file()
+        .ok_or("No thumbnail selected".to_string())?
+        .into_path()
+        .map_err(|e| e.to_string())?;
+    set_scene_thumbnail(window, id, Some(path))
+}
+
+/* Stage */
+
+const STAGE_EDITOR_PREFIX: &str = "stage_editor_";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct EditorPayload {
+    pub scene: NanoID,
+    pub stage: Stage,
+    pub positions: Vec<PositionInfo>,
+}
+
+/// Last known payload of every open stage editor, keyed by stage id
+static OPEN_EDITORS: Lazy<Mutex<HashMap<NanoID, EditorPayload>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Payloads of stage editors which have been closed without saving
+static CLOSED_EDITORS: Lazy<Mutex<ClosedEditors>> =
+    Lazy::new(|| Mutex::new(ClosedEditors::default()));
+
+#[derive(Default)]
+struct ClosedEditors {
+    payloads: HashMap<NanoID, EditorPayload>,
+    order: Vec<NanoID>, // most recently closed last
+}
+
+impl ClosedEditors {
+    fn push(&mut self, payload: EditorPayload) {
+        let id = payload.stage.id.clone();
+        self.order.retain(|it| it != &id);
+        self.order.push(id.clone());
+        self.payloads.insert(id, payload);
+    }
+
+    fn pop(&mut self) -> Option<EditorPayload> {
+        while let Some(id) = self.order.pop() {
+            if let Some(payload) = self.payloads.remove(&id) {
+                return Some(payload);
+            }
+        }
+        None
+    }
+
+    fn remove(&mut self, id: &NanoID) {
+        self.order.retain(|it| it != id);
+        self.payloads.remove(id);
+    }
+}
+
+fn open_stage_editor_impl<R: Runtime>(app: &tauri::AppHandle<R>, payload: EditorPayload) {
+    let stage = &payload.stage;
+    info!(
+        "Opening Stage {} from Scene {}",
+        stage.id.0, payload.scene.0
+    );
+    let window = WebviewWindowBuilder::new(
+        app,
+        format!("{}{}", STAGE_EDITOR_PREFIX, stage.id.0),
+        tauri::WebviewUrl::App("./stage.html".into()),
+    )
+    .title(format!(
+        "Stage Editor [{}]",
+        if stage.name.is_empty() {
+            "Untitled"
+        } else {
+            stage.name.as_str()
+        }
+    ))
+    .min_inner_size(800.0, 600.0)
+    .inner_size(1152.0, 864.0)
+    .resizable(true)
+    .build()
+    .expect(&format!(
+        "Failed to create stage editor window for Stage {}",
+        stage.id.0
+    ));
+    let geometry = SETTINGS.lock().unwrap().stage_editor_window;
+    restore_window_geometry(&window, geometry);
+    let stage_id = stage.id.clone();
+    OPEN_EDITORS
+        .lock()
+        .unwrap()
+        .insert(stage_id.clone(), payload.clone());
+    let editor = window.clone();
+    window.on_window_event(move |event| match event {
+        tauri::WindowEvent::CloseRequested { .. } => {
+            if let Some(geometry) = get_window_geometry(&editor) {
+                save_window_geometry(|settings| settings.stage_editor_window = Some(geometry));
+            }
+        }
+        tauri::WindowEvent::Destroyed => {
+            // Editors which have been saved are no longer listed as open
+            if let Some(last_known) = OPEN_EDITORS.lock().unwrap().remove(&stage_id) {
+                info!("Stage Editor for Stage {} closed without saving", stage_id.0);
+                CLOSED_EDITORS.lock().unwrap().push(last_known);
+            }
+        }
+        _ => {}
+    });
+    window.clone().once("on_request_data", move |_| {
+        window.emit("on_data_received", payload.clone()).unwrap();
+        let _ = window.emit_to(window.label(), "toggle_darkmode", get_darkmode());
+    });
+}
+
+#[tauri::command]
+async fn open_stage_editor<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    active_scene: Scene,
+    stage: Option<Stage>,
+) -> () {
+    open_stage_editor_impl(
+        &app,
+        EditorPayload {
+            scene: active_scene.id.clone(),
+            stage: stage.unwrap_or_else(|| {
+                let mut stage = Stage::new(&active_scene);
+                if SETTINGS.lock().unwrap().auto_name_stages {
+                    stage.name = active_scene.next_stage_name();
+                }
+                stage
+            }),
+            positions: active_scene.positions.clone(),
+        },
+    );
+}
+
+#[tauri::command]
+async fn open_stage_editor_from<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    active_scene: Scene,
+    copy_stage: Stage,
+) -> () {
+    open_stage_editor_impl(
+        &app,
+        EditorPayload {
+            scene: active_scene.id.clone(),
+            stage: copy_stage.clone(),
+            positions: active_scene.positions.clone(),
+        },
+    );
+}
+
+#[tauri::command]
+async fn stage_save_and_close<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    window: tauri::Window<R>,
+    scene: NanoID,
+    positions: Vec<PositionInfo>,
+    stage: Stage,
+) -> Result<(), String> {
+    // IDEA: make give this event some unique id to allow
+    // front end distinguish the timings at which some stage editor has been opened
+    info!("Saving Stage {}", stage.id.0);
+    let camera = stage.camera.as_ref().map(|camera| camera.wrapped());
+    let payload = EditorPayload {
+        scene,
+        stage: Stage { camera, ..stage },
+        positions,
+    };
+    if let Err(e) = validate_editor_payload(&payload) {
+        error!("Refusing to save Stage {}: {}", payload.stage.id.0, e);
+        return Err(e);
+    }
+    OPEN_EDITORS.lock().unwrap().remove(&payload.stage.id);
+    CLOSED_EDITORS.lock().unwrap().remove(&payload.stage.id);
+    app.emit_to(MAIN_WINDOW, "on_stage_saved", payload)
+        .map_err(|e| e.to_string())?;
+    let _ = window.close();
+    Ok(())
+}
+
+/// Check a stage editor payload for consistency before handing it to the main window
+fn validate_editor_payload(payload: &EditorPayload) -> Result<(), String> {
+    if !payload.scene.is_well_formed() {
+        return Err(format!("Invalid Scene ID: {}", payload.scene.0));
+    }
+    let stage = &payload.stage;
+    if !stage.id.is_well_formed() {
+        return Err(format!("Invalid Stage ID: {}", stage.id.0));
+    }
+    if stage.positions.len() != payload.positions.len() {
+        return Err(format!(
+            "Stage has {} positions but the scene has {}",
+            stage.positions.len(),
+            payload.positions.len()
+        ));
+    }
+    for (i, position) in stage.positions.iter().enumerate() {
+        if !position.offset.is_finite()
+            || !position.furniture_offsets.values().all(|offset| offset.is_finite())
+        {
+            return Err(format!("Position {} has an invalid offset", i + 1));
+        }
+    }
+    if stage.camera.as_ref().is_some_and(|camera| !camera.is_finite()) {
+        return Err("Camera hint is not a finite number".into());
+    }
+    for (i, info) in payload.positions.iter().enumerate() {
+        if !info.scale.is_finite() {
+            return Err(format!("Position {} has an invalid scale", i + 1));
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn cache_stage_editor(scene: NanoID, positions: Vec<PositionInfo>, stage: Stage) -> () {
+    let mut editors = OPEN_EDITORS.lock().unwrap();
+    if let Some(payload) = editors.get_mut(&stage.id) {
+        *payload = EditorPayload {
+            scene,
+            stage,
+            positions,
+        };
+    }
+}
+
+/// Number of open stage editors whose stage or positions differ from the ones stored in the project,
+/// an editor of a stage not yet in the project always counts
+fn unsaved_stage_editors() -> usize {
+    fn as_value(value: impl Serialize) -> serde_json::Value {
+        serde_json::to_value(value).unwrap_or_default()
+    }
+    let editors: Vec<EditorPayload> = OPEN_EDITORS.lock().unwrap().values().cloned().collect();
+    let prjct = PROJECT.lock().unwrap();
+    editors
+        .into_iter()
+        .filter(|payload| {
+            let scene = prjct.get_scene(&payload.scene);
+            match scene.and_then(|scene| scene.get_stage(&payload.stage.id).map(|stage| (scene, stage))) {
+                Some((scene, stage)) => {
+                    as_value(&payload.stage) != as_value(stage)
+                        || as_value(&payload.positions) != as_value(&scene.positions)
+                }
+                None => true,
+            }
+        })
+        .count()
+}
+
+/// Close all stage editor windows, returning how many have been closed. Unless `keep_cache`
+/// is set, the closed editors cannot be reopened afterwards
+fn close_stage_editors<R: Runtime>(app: &tauri::AppHandle<R>, keep_cache: bool) -> usize {
+    if !keep_cache {
+        OPEN_EDITORS.lock().unwrap().clear();
+        *CLOSED_EDITORS.lock().unwrap() = ClosedEditors::default();
+    }
+    let editors: Vec<_> = app
+        .webview_windows()
+        .into_iter()
+        .filter(|(label, _)| label.starts_with(STAGE_EDITOR_PREFIX))
+        .map(|(_, window)| window)
+        .collect();
+    for window in &editors {
+        if let Err(e) = window.close() {
+            error!("Failed to close {}: {}", window.label(), e);
+        }
+    }
+    info!("Closed {} stage editors", editors.len());
+    editors.len()
+}
+
+#[tauri::command]
+async fn close_all_stage_editors<R: Runtime>(app: tauri::AppHandle<R>, prompt: bool) -> usize {
+    let unsaved = unsaved_stage_editors();
+    if prompt && unsaved > 0 {
+        let do_close = app
+            .dialog()
+            .message(format!(
+                "{} stage editors have unsaved changes. Close them anyway?",
+                unsaved
+            ))
+            .title("Close Stage Editors")
+            .buttons(MessageDialogButtons::YesNo)
+            .kind(MessageDialogKind::Warning)
+            .blocking_show();
+        if !do_close {
+            return 0;
+        }
+    }
+    close_stage_editors(&app, true)
+}
+
+fn reopen_last_stage_editor_impl<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<(), String> {
+    let payload = CLOSED_EDITORS
+        .lock()
+        .unwrap()
+        .pop()
+        .ok_or("No closed stage editor to reopen".to_string())?;
+    let label = format!("{}{}", STAGE_EDITOR_PREFIX, payload.stage.id.0);
+    if let Some(window) = app.get_webview_window(&label) {
+        // Stage has been opened again in the meantime
+        let _ = window.set_focus();
+        return Ok(());
+    }
+    open_stage_editor_impl(app, payload);
+    Ok(())
+}
+
+#[tauri::command]
+async fn reopen_last_stage_editor<R: Runtime>(app: tauri::AppHandle<R>) -> Result<(), String> {
+    reopen_last_stage_editor_impl(&app)
+}
+
+#[tauri::command]
+fn diff_stages(scene_id: NanoID, a: NanoID, b: NanoID) -> Result<StageDiff, String> {
+    let prjct = PROJECT.lock().unwrap();
+    let scene = prjct
+        .get_scene(&scene_id)
+        .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?;
+    let stage_a = scene
+        .get_stage(&a)
+        .ok_or_else(|| format!("Scene {} has no stage with id {}", scene_id.0, a.0))?;
+    let stage_b = scene
+        .get_stage(&b)
+        .ok_or_else(|| format!("Scene {} has no stage with id {}", scene_id.0, b.0))?;
+    Ok(stage_a.diff(stage_b))
+}
+
+/* Position related */
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct PositionPayload {
+    pub position: Position,
+    pub info: PositionInfo,
+}
+
+#[tauri::command]
+fn make_position() -> PositionPayload {
+    let race = SETTINGS.lock().unwrap().new_scene.race.clone();
+    let mut info = PositionInfo::default();
+    if !race.is_empty() {
+        info.race = race;
+    }
     PositionPayload {
         position: Position::new(None),
-        info: PositionInfo::default(),
+        info,
     }
 }
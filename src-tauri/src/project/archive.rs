@@ -0,0 +1,130 @@
+use log::{info, warn};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+use tauri_plugin_dialog::DialogExt;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::settings::{ExportSettings, ValidationSettings};
+
+use super::{package::Package, NanoID};
+
+impl Package {
+    pub fn export_zip(
+        &self,
+        app: &tauri::AppHandle,
+        validation: &ValidationSettings,
+        export: &ExportSettings,
+    ) -> Result<PathBuf, String> {
+        let path = app
+            .dialog()
+            .file()
+            .set_title("Export Project as Zip")
+            .set_file_name(format!("{}.zip", self.pack_name))
+            .add_filter("Zip Archive", &["zip"])
+            .blocking_save_file()
+            .ok_or("No path to export the archive to".to_string())?
+            .into_path()
+            .map_err(|e| e.to_string())?;
+        self.build_zip(&path, validation, export)?;
+        Ok(path)
+    }
+
+    /// Build the project into a temporary folder and pack its content, along with scene and stage images, into a zip archive
+    pub fn build_zip(
+        &self,
+        path: &Path,
+        validation: &ValidationSettings,
+        export: &ExportSettings,
+    ) -> Result<(), String> {
+        let tmp_dir = std::env::temp_dir().join(format!("slsb-export-{}", NanoID::new_nanoid().0));
+        fs::create_dir_all(&tmp_dir).map_err(|e| e.to_string())?;
+        let export = ExportSettings {
+            clean: false,
+            ..export.clone()
+        };
+        let result = self
+            .build(tmp_dir.clone(), validation, &export)
+            .map_err(|e| e.to_string())
+            .and_then(|_| self.write_zip(path, &tmp_dir));
+        if let Err(e) = fs::remove_dir_all(&tmp_dir) {
+            warn!("Failed to remove temporary export {}: {}", tmp_dir.display(), e);
+        }
+        result
+    }
+
+    fn write_zip(&self, path: &Path, root_dir: &Path) -> Result<(), String> {
+        let file = fs::File::create(path).map_err(|e| e.to_string())?;
+        let mut zip = ZipWriter::new(file);
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        let mut entries = vec![];
+        collect_files(root_dir, &mut entries).map_err(|e| e.to_string())?;
+        let mut entries: Vec<(String, PathBuf)> = entries
+            .into_iter()
+            .filter_map(|file| archive_name(&file, root_dir).map(|name| (name, file)))
+            .collect();
+        entries.extend(self.thumbnail_entries());
+        entries.sort();
+
+        for (name, file) in &entries {
+            let content = fs::read(file).map_err(|e| format!("{}: {}", file.display(), e))?;
+            zip.start_file(name.as_str(), options)
+                .map_err(|e| e.to_string())?;
+            zip.write_all(&content).map_err(|e| e.to_string())?;
+        }
+        zip.finish().map_err(|e| e.to_string())?;
+        info!("Wrote {} files to {}", entries.len(), path.display());
+        Ok(())
+    }
+
+    /// Images of scenes and stages, stored under `thumbnails/<scene>/` in the archive
+    fn thumbnail_entries(&self) -> Vec<(String, PathBuf)> {
+        let mut ret = vec![];
+        for scene in self.scenes.values() {
+            let images = scene
+                .image
+                .iter()
+                .chain(scene.stages.iter().filter_map(|stage| stage.image.as_ref()));
+            for image in images {
+                if !image.is_file() {
+                    warn!("Scene {}: Image {} not found", scene.name, image.display());
+                    continue;
+                }
+                let file_name = match image.file_name() {
+                    Some(file_name) => file_name.to_string_lossy(),
+                    None => continue,
+                };
+                let name = format!("thumbnails/{}/{}", scene.file_name(), file_name);
+                if ret.iter().all(|(it, _)| *it != name) {
+                    ret.push((name, image.clone()));
+                }
+            }
+        }
+        ret
+    }
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Path of a file relative to the root, forward-slashed so the archive extracts the same on every platform
+fn archive_name(file: &Path, root_dir: &Path) -> Option<String> {
+    let relative = file.strip_prefix(root_dir).ok()?;
+    let parts: Vec<String> = relative
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    Some(parts.join("/"))
+}
@@ -0,0 +1,95 @@
+use super::{
+    package::{Package, VERSION},
+    scene::Scene,
+};
+
+/// A field of the registry format and the registry version which introduced it
+struct Feature {
+    name: &'static str,
+    version: u8,
+    // Number of uses of the feature in a scene
+    uses: fn(&Scene) -> usize,
+}
+
+/// Scene level features, kept in sync with the version history next to `VERSION`
+const FEATURES: &[Feature] = &[
+    Feature {
+        name: "transition conditions",
+        version: 5,
+        uses: |scene| {
+            scene
+                .graph
+                .values()
+                .flat_map(|node| node.conditions.values())
+                .filter(|condition| !condition.trim().is_empty())
+                .count()
+        },
+    },
+    Feature {
+        name: "skeleton requirements",
+        version: 5,
+        uses: |scene| scene.positions.iter().filter(|info| info.skeleton.is_some()).count(),
+    },
+    Feature {
+        name: "voice sets",
+        version: 5,
+        uses: |scene| scene.positions.iter().filter(|info| info.voice.is_some()).count(),
+    },
+    Feature {
+        name: "actor start offsets",
+        version: 5,
+        uses: |scene| scene.positions.iter().filter(|info| info.start_offset != 0.0).count(),
+    },
+    Feature {
+        name: "required actor tags",
+        version: 5,
+        uses: |scene| scene.required_tags.len(),
+    },
+    Feature {
+        name: "camera hints",
+        version: 5,
+        uses: |scene| scene.stages.iter().filter(|stage| stage.camera.is_some()).count(),
+    },
+];
+
+// v5: packs carry a description
+const PACK_DESCRIPTION_VERSION: u8 = 5;
+
+impl Package {
+    /// Features the project uses which a SexLab build reading the given registry version, e.g. `4` or `v4`,
+    /// does not support. Returns one line per feature and scene
+    pub fn compatibility_report(&self, target_version: &str) -> Result<Vec<String>, String> {
+        let target = parse_registry_version(target_version)?;
+        let mut ret = vec![];
+        if target < PACK_DESCRIPTION_VERSION && !self.pack_description.is_empty() {
+            ret.push(format!(
+                "Pack description requires registry version {}",
+                PACK_DESCRIPTION_VERSION
+            ));
+        }
+        for feature in FEATURES.iter().filter(|feature| feature.version > target) {
+            for scene in self.scenes_by_name() {
+                let uses = (feature.uses)(scene);
+                if uses > 0 {
+                    ret.push(format!(
+                        "Scene {}: Uses {} ({}), requires registry version {}",
+                        scene.name, feature.name, uses, feature.version
+                    ));
+                }
+            }
+        }
+        Ok(ret)
+    }
+}
+
+fn parse_registry_version(version: &str) -> Result<u8, String> {
+    let trimmed = version.trim();
+    let number = trimmed.strip_prefix(['v', 'V']).unwrap_or(trimmed);
+    match number.parse::<u8>() {
+        Ok(version) if (1..=VERSION).contains(&version) => Ok(version),
+        _ => Err(format!(
+            "Invalid registry version {}, expected a version between 1 and {}",
+            version, VERSION
+        )),
+    }
+}
@@ -0,0 +1,45 @@
+use std::mem::size_of;
+use serde::{Deserialize, Serialize};
+use crate::project::serialize::EncodeBinary;
+
+/// Recommended camera placement for a stage, relative to the scene origin. Angles are in degrees
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct CameraHint {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+}
+
+impl CameraHint {
+    pub fn is_finite(&self) -> bool {
+        [self.x, self.y, self.z, self.pitch, self.yaw]
+            .iter()
+            .all(|v| v.is_finite())
+    }
+
+    /// This hint with the pitch wrapped into [-180, 180) and the yaw into [0, 360)
+    pub fn wrapped(&self) -> CameraHint {
+        CameraHint {
+            pitch: (self.pitch + 180.0).rem_euclid(360.0) - 180.0,
+            yaw: self.yaw.rem_euclid(360.0),
+            ..self.clone()
+        }
+    }
+}
+
+impl EncodeBinary for CameraHint {
+    fn get_byte_size(&self) -> usize {
+        5 * size_of::<f32>()
+    }
+
+    fn write_byte(&self, buf: &mut Vec<u8>) -> () {
+        let wrapped = self.wrapped();
+        wrapped.x.write_byte(buf);
+        wrapped.y.write_byte(buf);
+        wrapped.z.write_byte(buf);
+        wrapped.pitch.write_byte(buf);
+        wrapped.yaw.write_byte(buf);
+    }
+}
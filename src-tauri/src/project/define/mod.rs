@@ -1,5 +1,5 @@
 mod offset;
-pub use offset::Offset;
+pub use offset::{convert_offset_units, Offset, OffsetUnit};
 
 mod sex;
 pub use sex::Sex;
@@ -12,3 +12,6 @@ pub use furniture_data::FurnitureData;
 
 mod node;
 pub use node::Node;
+
+mod camera_hint;
+pub use camera_hint::CameraHint;
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 use crate::project::{serialize::EncodeBinary, NanoID};
 
@@ -6,6 +7,9 @@ use crate::project::{serialize::EncodeBinary, NanoID};
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Node {
     pub dest: Vec<NanoID>,
+    // SexLab condition expression gating the link into a destination, links without one are unconditional
+    #[serde(default)] // addition 2.1
+    pub conditions: HashMap<NanoID, String>,
     pub x: f32,
     pub y: f32,
 }
@@ -14,18 +18,42 @@ impl Default for Node {
     fn default() -> Self {
         Self {
             dest: Default::default(),
+            conditions: Default::default(),
             x: 40.0,
             y: 40.0,
         }
     }
 }
 
+impl Node {
+    /// Condition of the link into the given destination, if the link has one
+    pub fn condition(&self, dest: &NanoID) -> Option<&str> {
+        self.conditions.get(dest).map(|condition| condition.as_str())
+    }
+
+    /// Drop the conditions of links which no longer exist
+    pub fn prune_conditions(&mut self) -> () {
+        let dest = &self.dest;
+        self.conditions.retain(|id, _| dest.contains(id));
+    }
+
+    /// Condition of every link in link order, empty if the link is unconditional
+    fn link_conditions(&self) -> Vec<String> {
+        self.dest
+            .iter()
+            .map(|dest| self.condition(dest).unwrap_or_default().to_string())
+            .collect()
+    }
+}
+
 impl EncodeBinary for Node {
     fn get_byte_size(&self) -> usize {
-      self.dest.get_byte_size()
+      self.dest.get_byte_size() + self.link_conditions().get_byte_size()
     }
 
     fn write_byte(&self, buf: &mut Vec<u8>) {
       self.dest.write_byte(buf);
+      // v5: one condition per link, empty if the transition is unconditional
+      self.link_conditions().write_byte(buf);
     }
 }
@@ -2,7 +2,7 @@ use std::mem::size_of;
 use serde::{Deserialize, Serialize};
 use crate::project::serialize::EncodeBinary;
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct Offset {
     pub x: f32,
     pub y: f32,
@@ -10,6 +10,85 @@ pub struct Offset {
     pub r: f32,
 }
 
+impl Offset {
+    pub fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite() && self.z.is_finite() && self.r.is_finite()
+    }
+
+    /// This offset with every component limited to the range given by min and max
+    pub fn clamped(&self, min: &Offset, max: &Offset) -> Offset {
+        Offset {
+            x: self.x.clamp(min.x, max.x),
+            y: self.y.clamp(min.y, max.y),
+            z: self.z.clamp(min.z, max.z),
+            r: self.r.clamp(min.r, max.r),
+        }
+    }
+
+    /// This offset mirrored along the x axis, the rotation flipped to face the other way
+    pub fn mirrored(&self) -> Offset {
+        Offset {
+            x: -self.x,
+            y: self.y,
+            z: self.z,
+            r: (360.0 - self.r).rem_euclid(360.0),
+        }
+    }
+
+    /// Round every component to the given number of decimals, returns the number of components changed.
+    /// More than 6 decimals are beyond what an f32 holds and are treated as 6
+    pub fn round_to(&mut self, decimals: u32) -> usize {
+        let factor = 10f32.powi(decimals.min(6) as i32);
+        let mut changed = 0;
+        for value in [&mut self.x, &mut self.y, &mut self.z, &mut self.r] {
+            let rounded = (*value * factor).round() / factor;
+            if value.is_finite() && rounded != *value {
+                *value = rounded;
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    pub fn approx_eq(&self, other: &Offset, tolerance: f32) -> bool {
+        (self.x - other.x).abs() <= tolerance
+            && (self.y - other.y).abs() <= tolerance
+            && (self.z - other.z).abs() <= tolerance
+            && (self.r - other.r).abs() <= tolerance
+    }
+}
+
+/// Unit system an offset is expressed in
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OffsetUnit {
+    /// SexLab's own units, the only ones stored in the project and written on export
+    Game,
+    /// The user's preferred display units, see `DisplayUnits` in the settings
+    Display,
+}
+
+/// Convert the position of an offset between unit systems, `display_per_game` being the number of display
+/// units per game unit. The rotation is an angle and stays as is
+pub fn convert_offset_units(
+    offset: &Offset,
+    from: OffsetUnit,
+    to: OffsetUnit,
+    display_per_game: f32,
+) -> Offset {
+    let factor = match (from, to) {
+        (OffsetUnit::Game, OffsetUnit::Display) => display_per_game,
+        (OffsetUnit::Display, OffsetUnit::Game) => 1.0 / display_per_game,
+        _ => 1.0,
+    };
+    Offset {
+        x: offset.x * factor,
+        y: offset.y * factor,
+        z: offset.z * factor,
+        r: offset.r,
+    }
+}
+
 impl EncodeBinary for Offset {
     fn get_byte_size(&self) -> usize {
         size_of::<Offset>()
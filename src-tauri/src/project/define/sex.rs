@@ -1,5 +1,6 @@
 use crate::project::serialize::EncodeBinary;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Sex {
@@ -32,3 +33,17 @@ impl Default for Sex {
         }
     }
 }
+
+impl fmt::Display for Sex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list: Vec<&str> = [
+            (self.male, "Male"),
+            (self.female, "Female"),
+            (self.futa, "Futa"),
+        ]
+        .iter()
+        .filter_map(|&(set, name)| set.then_some(name))
+        .collect();
+        write!(f, "{}", list.join("/"))
+    }
+}
@@ -0,0 +1,778 @@
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use log::{info, warn};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+use tauri_plugin_dialog::DialogExt;
+
+use super::{package::Package, scene::Scene, NanoID};
+
+const IMAGE_DIR: &str = "images";
+
+const STORYBOARD_COLUMNS: usize = 4;
+const TILE_WIDTH: usize = 256;
+const TILE_HEIGHT: usize = 144;
+const TILE_GAP: usize = 16;
+const LABEL_HEIGHT: usize = 40;
+const TITLE_HEIGHT: usize = 48;
+
+impl Package {
+    pub fn export_documentation(&self, app: &tauri::AppHandle) -> Result<(), String> {
+        let path = app
+            .dialog()
+            .file()
+            .set_title("Export Documentation")
+            .blocking_pick_folder()
+            .ok_or("No path to export documentation to".to_string())?
+            .into_path()
+            .map_err(|e| e.to_string())?;
+        self.export_docs(&path)?;
+        self.export_docs_html(&path)
+    }
+
+    /// Write a Markdown catalog of all scenes into the given directory, copying thumbnails alongside
+    pub fn export_docs(&self, path: &Path) -> Result<(), String> {
+        fs::create_dir_all(path).map_err(|e| e.to_string())?;
+        let images = self.copy_doc_images(path)?;
+        let mut doc = String::new();
+        let _ = writeln!(doc, "# {}\n", md_escape(&self.doc_title()));
+        let _ = writeln!(doc, "Author: {}\n", md_escape(&self.pack_author));
+        if !self.pack_description.is_empty() {
+            let _ = writeln!(doc, "{}\n", self.pack_description);
+        }
+        let _ = writeln!(doc, "Scenes: {}\n", self.scenes.len());
+        let _ = writeln!(
+            doc,
+            "Total Estimated Duration: {}\n",
+            playtime_text(self.stats().estimated_duration)
+        );
+        for scene in self.scenes_by_name() {
+            let _ = writeln!(doc, "## {}\n", md_escape(&scene_title(scene)));
+            if let Some(image) = images.get(&scene.id) {
+                let _ = writeln!(doc, "![{}]({})\n", md_escape(&scene.name), image);
+            }
+            if !scene.tags.is_empty() {
+                let _ = writeln!(doc, "**Tags:** {}\n", md_escape(&scene.tags.join(", ")));
+            }
+            let _ = writeln!(
+                doc,
+                "**Furniture:** {}\n",
+                md_escape(&scene.furniture.furni_types.join(", "))
+            );
+            let _ = writeln!(
+                doc,
+                "**Estimated Duration:** {}\n",
+                playtime_text(scene.estimated_duration())
+            );
+            if !scene.notes.trim().is_empty() {
+                let _ = writeln!(doc, "{}\n", scene.notes.trim());
+            }
+            let _ = writeln!(doc, "### Positions\n");
+            let _ = writeln!(doc, "| # | Race | Sex | Scale | Submissive | Vampire | Dead |");
+            let _ = writeln!(doc, "|---|------|-----|-------|------------|---------|------|");
+            for (i, info) in scene.positions.iter().enumerate() {
+                let _ = writeln!(
+                    doc,
+                    "| {} | {} | {} | {} | {} | {} | {} |",
+                    i + 1,
+                    md_escape(&info.race),
+                    info.sex,
+                    info.scale,
+                    yes_no(info.submissive),
+                    yes_no(info.vampire),
+                    yes_no(info.dead)
+                );
+            }
+            let _ = writeln!(doc, "\n### Stages\n");
+            let _ = writeln!(doc, "| # | Name | Duration | Navigation | Tags | Events |");
+            let _ = writeln!(doc, "|---|------|----------|------------|------|--------|");
+            for (i, stage) in scene.stages.iter().enumerate() {
+                let _ = writeln!(
+                    doc,
+                    "| {}{} | {} | {} | {} | {} | {} |",
+                    i + 1,
+                    if scene.root == stage.id { " (start)" } else { "" },
+                    md_escape(&stage_title(&stage.name)),
+                    duration_text(stage.extra.export_len()),
+                    md_escape(&stage.extra.nav_text),
+                    md_escape(&stage.tags.join(", ")),
+                    md_escape(&stage_events(scene, i).join(", "))
+                );
+            }
+            let _ = writeln!(doc);
+        }
+        let file = path.join(format!("{}.md", self.doc_file_name()));
+        fs::write(&file, doc).map_err(|e| e.to_string())?;
+        info!("Wrote documentation to {}", file.display());
+        Ok(())
+    }
+
+    /// Write the same catalog as `export_docs` as a single HTML page
+    pub fn export_docs_html(&self, path: &Path) -> Result<(), String> {
+        fs::create_dir_all(path).map_err(|e| e.to_string())?;
+        let images = self.copy_doc_images(path)?;
+        let title = html_escape(&self.doc_title());
+        let mut doc = String::new();
+        let _ = writeln!(
+            doc,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>",
+            title
+        );
+        let _ = writeln!(doc, "<h1>{}</h1>", title);
+        let _ = writeln!(doc, "<p>Author: {}</p>", html_escape(&self.pack_author));
+        if !self.pack_description.is_empty() {
+            let _ = writeln!(doc, "<p>{}</p>", html_escape(&self.pack_description));
+        }
+        let _ = writeln!(doc, "<p>Scenes: {}</p>", self.scenes.len());
+        let _ = writeln!(
+            doc,
+            "<p>Total Estimated Duration: {}</p>",
+            playtime_text(self.stats().estimated_duration)
+        );
+        for scene in self.scenes_by_name() {
+            let _ = writeln!(doc, "<h2>{}</h2>", html_escape(&scene_title(scene)));
+            if let Some(image) = images.get(&scene.id) {
+                let _ = writeln!(
+                    doc,
+                    "<img src=\"{}\" alt=\"{}\" style=\"max-width: 640px\">",
+                    html_escape(image),
+                    html_escape(&scene.name)
+                );
+            }
+            if !scene.tags.is_empty() {
+                let _ = writeln!(doc, "<p><b>Tags:</b> {}</p>", html_escape(&scene.tags.join(", ")));
+            }
+            let _ = writeln!(
+                doc,
+                "<p><b>Furniture:</b> {}</p>",
+                html_escape(&scene.furniture.furni_types.join(", "))
+            );
+            let _ = writeln!(
+                doc,
+                "<p><b>Estimated Duration:</b> {}</p>",
+                playtime_text(scene.estimated_duration())
+            );
+            if !scene.notes.trim().is_empty() {
+                let _ = writeln!(doc, "<p>{}</p>", html_escape(scene.notes.trim()));
+            }
+            let _ = writeln!(doc, "<h3>Positions</h3>\n<table border=\"1\">");
+            let _ = writeln!(doc, "<tr><th>#</th><th>Race</th><th>Sex</th><th>Scale</th><th>Submissive</th><th>Vampire</th><th>Dead</th></tr>");
+            for (i, info) in scene.positions.iter().enumerate() {
+                let _ = writeln!(
+                    doc,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    i + 1,
+                    html_escape(&info.race),
+                    info.sex,
+                    info.scale,
+                    yes_no(info.submissive),
+                    yes_no(info.vampire),
+                    yes_no(info.dead)
+                );
+            }
+            let _ = writeln!(doc, "</table>\n<h3>Stages</h3>\n<table border=\"1\">");
+            let _ = writeln!(doc, "<tr><th>#</th><th>Name</th><th>Duration</th><th>Navigation</th><th>Tags</th><th>Events</th></tr>");
+            for (i, stage) in scene.stages.iter().enumerate() {
+                let _ = writeln!(
+                    doc,
+                    "<tr><td>{}{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    i + 1,
+                    if scene.root == stage.id { " (start)" } else { "" },
+                    html_escape(&stage_title(&stage.name)),
+                    duration_text(stage.extra.export_len()),
+                    html_escape(&stage.extra.nav_text),
+                    html_escape(&stage.tags.join(", ")),
+                    html_escape(&stage_events(scene, i).join(", "))
+                );
+            }
+            let _ = writeln!(doc, "</table>");
+        }
+        let _ = writeln!(doc, "</body>\n</html>");
+        let file = path.join(format!("{}.html", self.doc_file_name()));
+        fs::write(&file, doc).map_err(|e| e.to_string())?;
+        info!("Wrote documentation to {}", file.display());
+        Ok(())
+    }
+
+    pub fn export_csv(&self, app: &tauri::AppHandle) -> Result<(), String> {
+        let path = app
+            .dialog()
+            .file()
+            .set_title("Export Positions")
+            .set_file_name(&format!("{}.csv", self.doc_file_name()))
+            .add_filter("CSV", &["csv"])
+            .blocking_save_file()
+            .ok_or("No path to export positions to".to_string())?
+            .into_path()
+            .map_err(|e| e.to_string())?;
+        self.export_positions_csv(&path)
+    }
+
+    /// Write one row per position of every stage, for bulk review in a spreadsheet
+    pub fn export_positions_csv(&self, path: &Path) -> Result<(), String> {
+        let mut doc = String::new();
+        let _ = writeln!(
+            doc,
+            "scene_id,scene,stage_id,stage,position,race,sex,x,y,z,r,event"
+        );
+        for scene in self.scenes_by_name() {
+            for stage in &scene.stages {
+                for (i, position) in stage.positions.iter().enumerate() {
+                    let (race, sex) = scene
+                        .positions
+                        .get(i)
+                        .map(|info| (info.race.clone(), info.sex.to_string()))
+                        .unwrap_or_default();
+                    let row = [
+                        scene.id.0.clone(),
+                        scene.name.clone(),
+                        stage.id.0.clone(),
+                        stage.name.clone(),
+                        (i + 1).to_string(),
+                        race,
+                        sex,
+                        position.offset.x.to_string(),
+                        position.offset.y.to_string(),
+                        position.offset.z.to_string(),
+                        position.offset.r.to_string(),
+                        position.event.join(" "),
+                    ];
+                    let _ = writeln!(
+                        doc,
+                        "{}",
+                        row.iter()
+                            .map(|field| csv_escape(field))
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    );
+                }
+            }
+        }
+        fs::write(path, doc).map_err(|e| e.to_string())?;
+        info!("Wrote positions to {}", path.display());
+        Ok(())
+    }
+
+    /// Write a contact sheet of the scene's stages, using the stage's thumbnail or else the scene's.
+    /// The sheet is an SVG with the thumbnails inlined rather than a PNG, as labelling a PNG would need
+    /// an image and font rendering stack the builder does not ship. Browsers and image viewers open it alike
+    pub fn export_storyboard(&self, scene_id: &NanoID, path: &Path) -> Result<(), String> {
+        let scene = self
+            .get_scene(scene_id)
+            .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?;
+        let columns = scene.stages.len().clamp(1, STORYBOARD_COLUMNS);
+        let rows = (scene.stages.len() + columns - 1) / columns;
+        let cell_w = TILE_WIDTH + TILE_GAP;
+        let cell_h = TILE_HEIGHT + LABEL_HEIGHT + TILE_GAP;
+        let width = columns * cell_w + TILE_GAP;
+        let height = rows.max(1) * cell_h + TITLE_HEIGHT + TILE_GAP;
+
+        let mut doc = String::new();
+        let _ = writeln!(
+            doc,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" font-family=\"sans-serif\">",
+            width, height
+        );
+        let _ = writeln!(doc, "<rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>");
+        let _ = writeln!(
+            doc,
+            "<text x=\"{}\" y=\"{}\" font-size=\"20\" font-weight=\"bold\">{}</text>",
+            TILE_GAP,
+            TITLE_HEIGHT - 12,
+            html_escape(&scene_title(scene))
+        );
+        for (i, stage) in scene.stages.iter().enumerate() {
+            let x = TILE_GAP + (i % columns) * cell_w;
+            let y = TITLE_HEIGHT + (i / columns) * cell_h;
+            let image = stage.image.as_ref().or(scene.image.as_ref());
+            match image.and_then(|image| storyboard_image(image)) {
+                Some(href) => {
+                    let _ = writeln!(
+                        doc,
+                        "<image x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" preserveAspectRatio=\"xMidYMid slice\" href=\"{}\"/>",
+                        x, y, TILE_WIDTH, TILE_HEIGHT, href
+                    );
+                }
+                None => {
+                    let _ = writeln!(
+                        doc,
+                        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#e0e0e0\"/>",
+                        x, y, TILE_WIDTH, TILE_HEIGHT
+                    );
+                    let _ = writeln!(
+                        doc,
+                        "<text x=\"{}\" y=\"{}\" font-size=\"14\" fill=\"#888888\" text-anchor=\"middle\">No Image</text>",
+                        x + TILE_WIDTH / 2,
+                        y + TILE_HEIGHT / 2
+                    );
+                }
+            }
+            let _ = writeln!(
+                doc,
+                "<text x=\"{}\" y=\"{}\" font-size=\"14\">{}. {}</text>",
+                x,
+                y + TILE_HEIGHT + 18,
+                i + 1,
+                html_escape(&stage_title(&stage.name))
+            );
+            let _ = writeln!(
+                doc,
+                "<text x=\"{}\" y=\"{}\" font-size=\"12\" fill=\"#555555\">{}</text>",
+                x,
+                y + TILE_HEIGHT + 34,
+                duration_text(stage.extra.export_len())
+            );
+        }
+        let _ = writeln!(doc, "</svg>");
+
+        fs::write(path, doc).map_err(|e| e.to_string())?;
+        info!("Wrote storyboard of Scene {} to {}", scene.id.0, path.display());
+        Ok(())
+    }
+
+    /// Write a table of every stage's position offsets for the animator, as CSV if the path ends in .csv, otherwise Markdown
+    pub fn export_offset_sheet(&self, scene_id: &NanoID, path: &Path) -> Result<(), String> {
+        let scene = self
+            .get_scene(scene_id)
+            .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?;
+        let as_csv = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        let header = ["Stage", "Position", "Race", "Role", "X", "Y", "Z", "Angle", "Scale"];
+        let mut doc = String::new();
+        if as_csv {
+            let _ = writeln!(doc, "Pack,{}", csv_escape(&self.doc_title()));
+            let _ = writeln!(doc, "Scene,{}", csv_escape(&scene_title(scene)));
+            let _ = writeln!(doc, "{}", header.join(","));
+        } else {
+            let _ = writeln!(doc, "# {}\n", md_escape(&scene_title(scene)));
+            let _ = writeln!(doc, "Pack: {}\n", md_escape(&self.doc_title()));
+            let _ = writeln!(doc, "| {} |", header.join(" | "));
+            let _ = writeln!(doc, "|{}", "---|".repeat(header.len()));
+        }
+        for stage in &scene.stages {
+            for (i, position) in stage.positions.iter().enumerate() {
+                let (race, role, scale) = scene
+                    .positions
+                    .get(i)
+                    .map(|info| {
+                        let role = if info.submissive { "Submissive" } else { "Dominant" };
+                        (info.race.clone(), role.to_string(), info.scale.to_string())
+                    })
+                    .unwrap_or_default();
+                let row = [
+                    stage_title(&stage.name),
+                    (i + 1).to_string(),
+                    race,
+                    role,
+                    position.offset.x.to_string(),
+                    position.offset.y.to_string(),
+                    position.offset.z.to_string(),
+                    position.offset.r.to_string(),
+                    scale,
+                ];
+                if as_csv {
+                    let row: Vec<_> = row.iter().map(|field| csv_escape(field)).collect();
+                    let _ = writeln!(doc, "{}", row.join(","));
+                } else {
+                    let row: Vec<_> = row.iter().map(|field| md_escape(field)).collect();
+                    let _ = writeln!(doc, "| {} |", row.join(" | "));
+                }
+            }
+        }
+        fs::write(path, doc).map_err(|e| e.to_string())?;
+        info!("Wrote offset sheet of Scene {} to {}", scene.id.0, path.display());
+        Ok(())
+    }
+
+    /// Write a Markdown checklist of every animation file the project references, grouped by scene, to compare
+    /// against the animations folder. Positions without an event are flagged. Returns the number of unique events
+    pub fn export_animation_checklist(&self, path: &Path) -> Result<usize, String> {
+        let mut all_events: HashSet<&str> = HashSet::new();
+        let mut sections = String::new();
+        let mut empty = 0;
+        for scene in self.scenes_by_name() {
+            let mut events: Vec<&str> = vec![];
+            let mut missing: Vec<String> = vec![];
+            for stage in &scene.stages {
+                for (i, position) in stage.positions.iter().enumerate() {
+                    if position.is_empty() {
+                        missing.push(format!("{} Position {}", stage_title(&stage.name), i + 1));
+                    }
+                    for event in position.event.iter().map(|event| event.trim()) {
+                        if !event.is_empty() && !events.contains(&event) {
+                            events.push(event);
+                        }
+                    }
+                }
+            }
+            all_events.extend(events.iter().copied());
+            empty += missing.len();
+            let _ = writeln!(
+                sections,
+                "## {} ({} events)\n",
+                md_escape(&scene_title(scene)),
+                events.len()
+            );
+            for event in events {
+                let _ = writeln!(sections, "- [ ] {}.hkx", md_escape(event));
+            }
+            for position in missing {
+                let _ = writeln!(sections, "- **No animation event:** {}", md_escape(&position));
+            }
+            let _ = writeln!(sections);
+        }
+        let mut doc = String::new();
+        let _ = writeln!(doc, "# {} Animation Files\n", md_escape(&self.doc_title()));
+        let _ = writeln!(doc, "Unique animation events: {}\n", all_events.len());
+        if empty > 0 {
+            let _ = writeln!(doc, "Positions without an animation event: {}\n", empty);
+        }
+        doc += &sections;
+        fs::write(path, doc).map_err(|e| e.to_string())?;
+        info!(
+            "Wrote checklist of {} animation events to {}",
+            all_events.len(),
+            path.display()
+        );
+        Ok(all_events.len())
+    }
+
+    /// YAML front matter describing a scene, for embedding in wiki pages. Quoting is left to the YAML serializer
+    pub fn export_scene_frontmatter(&self, id: &NanoID) -> Result<String, String> {
+        let scene = self
+            .get_scene(id)
+            .ok_or_else(|| format!("Invalid Scene ID: {}", id.0))?;
+        let frontmatter = SceneFrontmatter {
+            name: &scene.name,
+            author: &self.pack_author,
+            tags: &scene.tags,
+            notes: &scene.notes,
+            stages: scene.stages.len(),
+        };
+        let yaml = serde_yaml::to_string(&frontmatter).map_err(|e| e.to_string())?;
+        // serde_yaml may or may not open the document with a marker, front matter needs exactly one on each side
+        let body = yaml.trim_start_matches("---").trim_start_matches('\n');
+        Ok(format!("---\n{}---\n", body))
+    }
+
+    /// Markdown release notes listing the scenes added, removed and modified since the old version of this pack
+    pub fn changelog(&self, old: &Package) -> String {
+        let mut doc = String::new();
+        let _ = writeln!(doc, "# {} Changelog\n", md_escape(&self.doc_title()));
+        let added: Vec<&Scene> = self
+            .scenes_by_name()
+            .into_iter()
+            .filter(|scene| !old.scenes.contains_key(&scene.id))
+            .collect();
+        let removed: Vec<&Scene> = old
+            .scenes_by_name()
+            .into_iter()
+            .filter(|scene| !self.scenes.contains_key(&scene.id))
+            .collect();
+        let modified: Vec<(&Scene, Vec<String>)> = self
+            .scenes_by_name()
+            .into_iter()
+            .filter_map(|scene| {
+                let changes = scene_changes(old.get_scene(&scene.id)?, scene);
+                (!changes.is_empty()).then_some((scene, changes))
+            })
+            .collect();
+        if added.is_empty() && removed.is_empty() && modified.is_empty() {
+            let _ = writeln!(doc, "No changes.");
+            return doc;
+        }
+        if !added.is_empty() {
+            let _ = writeln!(doc, "## Added\n");
+            for scene in &added {
+                let _ = writeln!(
+                    doc,
+                    "- {} ({} stages, {} positions)",
+                    md_escape(&scene.name),
+                    scene.stages.len(),
+                    scene.positions.len()
+                );
+            }
+            let _ = writeln!(doc);
+        }
+        if !removed.is_empty() {
+            let _ = writeln!(doc, "## Removed\n");
+            for scene in &removed {
+                let _ = writeln!(doc, "- {}", md_escape(&scene.name));
+            }
+            let _ = writeln!(doc);
+        }
+        if !modified.is_empty() {
+            let _ = writeln!(doc, "## Modified\n");
+            for (scene, changes) in &modified {
+                let _ = writeln!(doc, "- {}", md_escape(&scene.name));
+                for change in changes {
+                    let _ = writeln!(doc, "  - {}", md_escape(change));
+                }
+            }
+            let _ = writeln!(doc);
+        }
+        doc
+    }
+
+    fn doc_title(&self) -> String {
+        if self.pack_name.is_empty() {
+            "Untitled Pack".into()
+        } else {
+            self.pack_name.clone()
+        }
+    }
+
+    fn doc_file_name(&self) -> String {
+        if self.pack_name.is_empty() {
+            self.prefix_hash.0.clone()
+        } else {
+            self.pack_name.clone()
+        }
+    }
+
+    /// Copy the thumbnail of every scene into the given directory, named after the scene, returns the number copied
+    pub fn export_thumbnails(&self, dir: &Path) -> Result<usize, String> {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        let mut exported = 0;
+        for scene in self.scenes_by_name() {
+            let image = match &scene.image {
+                Some(image) => image,
+                None => continue,
+            };
+            if !image.is_file() {
+                warn!(
+                    "Thumbnail {} of Scene {} does not exist, skipping",
+                    image.display(),
+                    scene.id.0
+                );
+                continue;
+            }
+            let file_name = match image.extension().and_then(|ext| ext.to_str()) {
+                Some(ext) => format!("{}.{}", scene.file_name(), ext.to_lowercase()),
+                None => scene.file_name(),
+            };
+            fs::copy(image, dir.join(file_name)).map_err(|e| e.to_string())?;
+            exported += 1;
+        }
+        info!("Exported {} thumbnails to {}", exported, dir.display());
+        Ok(exported)
+    }
+
+    /// Scenes whose thumbnail no longer exists on disk, ordered by scene name. The docs leave these scenes without an image
+    pub fn check_thumbnails(&self) -> Vec<(NanoID, PathBuf)> {
+        self.scenes_by_name()
+            .into_iter()
+            .filter_map(|scene| match &scene.image {
+                Some(image) if !image.is_file() => Some((scene.id.clone(), image.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Copy every existing scene thumbnail into the image folder, returning their relative paths
+    fn copy_doc_images(&self, path: &Path) -> Result<HashMap<NanoID, String>, String> {
+        let mut ret = HashMap::new();
+        for scene in self.scenes.values() {
+            let image = match &scene.image {
+                Some(image) => image,
+                None => continue,
+            };
+            if !image.is_file() {
+                warn!(
+                    "Thumbnail {} of Scene {} does not exist, skipping",
+                    image.display(),
+                    scene.id.0
+                );
+                continue;
+            }
+            let file_name = match image.extension().and_then(|ext| ext.to_str()) {
+                Some(ext) => format!("{}.{}", scene.id.0, ext),
+                None => scene.id.0.clone(),
+            };
+            let target_dir = path.join(IMAGE_DIR);
+            fs::create_dir_all(&target_dir).map_err(|e| e.to_string())?;
+            fs::copy(image, target_dir.join(&file_name)).map_err(|e| e.to_string())?;
+            ret.insert(scene.id.clone(), format!("{}/{}", IMAGE_DIR, file_name));
+        }
+        Ok(ret)
+    }
+}
+
+/// Inline an image as data url, so the storyboard stays a single self contained file
+fn storyboard_image(image: &Path) -> Option<String> {
+    let mime = match image
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .as_deref()
+    {
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("bmp") => "image/bmp",
+        Some("webp") => "image/webp",
+        _ => {
+            warn!("Unsupported thumbnail {}, using a placeholder", image.display());
+            return None;
+        }
+    };
+    match fs::read(image) {
+        Ok(bytes) => Some(format!("data:{};base64,{}", mime, BASE64_STANDARD.encode(bytes))),
+        Err(e) => {
+            warn!("Unable to read thumbnail {}: {}", image.display(), e);
+            None
+        }
+    }
+}
+
+fn scene_title(scene: &Scene) -> String {
+    if scene.name.is_empty() {
+        format!("Untitled ({})", scene.id.0)
+    } else {
+        scene.name.clone()
+    }
+}
+
+fn stage_title(name: &str) -> String {
+    if name.is_empty() {
+        "Untitled".into()
+    } else {
+        name.into()
+    }
+}
+
+fn stage_events(scene: &Scene, stage_idx: usize) -> Vec<String> {
+    scene.stages[stage_idx]
+        .positions
+        .iter()
+        .map(|position| position.event.first().cloned().unwrap_or_default())
+        .collect()
+}
+
+fn duration_text(fixed_len: f32) -> String {
+    if fixed_len > 0.0 {
+        format!("{} ms", fixed_len)
+    } else {
+        "Looping".into()
+    }
+}
+
+fn playtime_text(seconds: f32) -> String {
+    let seconds = seconds.round() as u64;
+    format!("{}:{:02} min", seconds / 60, seconds % 60)
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "Yes"
+    } else {
+        "No"
+    }
+}
+
+/// Human readable differences between two versions of a scene
+fn scene_changes(old: &Scene, new: &Scene) -> Vec<String> {
+    let mut ret = vec![];
+    if old.name != new.name {
+        ret.push(format!("Renamed from {}", old.name));
+    }
+    if old.positions.len() != new.positions.len() {
+        ret.push(format!(
+            "Positions changed from {} to {}",
+            old.positions.len(),
+            new.positions.len()
+        ));
+    }
+    if old.private != new.private {
+        ret.push(if new.private { "Now private" } else { "No longer private" }.into());
+    }
+    if old.furniture.furni_types != new.furniture.furni_types {
+        ret.push(format!("Furniture set to {}", new.furniture.furni_types.join(", ")));
+    }
+    let tags_added: Vec<&str> = new
+        .tags
+        .iter()
+        .filter(|tag| !old.tags.contains(tag))
+        .map(|tag| tag.as_str())
+        .collect();
+    if !tags_added.is_empty() {
+        ret.push(format!("Added tags {}", tags_added.join(", ")));
+    }
+    let tags_removed: Vec<&str> = old
+        .tags
+        .iter()
+        .filter(|tag| !new.tags.contains(tag))
+        .map(|tag| tag.as_str())
+        .collect();
+    if !tags_removed.is_empty() {
+        ret.push(format!("Removed tags {}", tags_removed.join(", ")));
+    }
+    for stage in &new.stages {
+        match old.get_stage(&stage.id) {
+            None => ret.push(format!("Added stage {}", stage.name)),
+            Some(before) => {
+                if before.name != stage.name {
+                    ret.push(format!("Renamed stage {} to {}", before.name, stage.name));
+                }
+                let changed = serde_json::to_value(before).ok() != serde_json::to_value(stage).ok();
+                if changed && before.name == stage.name {
+                    ret.push(format!("Changed stage {}", stage.name));
+                }
+            }
+        }
+    }
+    for stage in old.stages.iter().filter(|stage| new.get_stage(&stage.id).is_none()) {
+        ret.push(format!("Removed stage {}", stage.name));
+    }
+    if old.root != new.root {
+        if let Some(root) = new.get_stage(&new.root) {
+            ret.push(format!("Starts at stage {}", root.name));
+        }
+    }
+    let links = |scene: &Scene| -> Vec<(NanoID, NanoID)> {
+        let mut ret: Vec<(NanoID, NanoID)> = scene
+            .graph
+            .iter()
+            .flat_map(|(id, node)| node.dest.iter().map(move |dest| (id.clone(), dest.clone())))
+            .collect();
+        ret.sort_by(|a, b| (&a.0 .0, &a.1 .0).cmp(&(&b.0 .0, &b.1 .0)));
+        ret
+    };
+    if links(old) != links(new) {
+        ret.push("Changed stage transitions".into());
+    }
+    ret
+}
+
+#[derive(Serialize)]
+struct SceneFrontmatter<'a> {
+    name: &'a str,
+    author: &'a str,
+    tags: &'a [String],
+    notes: &'a str,
+    stages: usize,
+}
+
+fn md_escape(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn csv_escape(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.into()
+    }
+}
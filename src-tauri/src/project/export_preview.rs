@@ -0,0 +1,157 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use super::{
+    define::{CameraHint, Offset, Sex, Stripping},
+    package::Package,
+    scene::Scene,
+    NanoID,
+};
+use crate::racekeys::get_race_key_bytes;
+
+/// The fields of a scene the registry file holds, in the order they are written and with the values the
+/// export writes, e.g. only the first event of a position and whitespace free lowercase stage tags
+#[derive(Debug, Serialize)]
+struct ScenePreview<'a> {
+    id: &'a NanoID,
+    name: &'a str,
+    positions: Vec<PositionInfoPreview<'a>>,
+    stages: Vec<StagePreview<'a>>,
+    graph: BTreeMap<&'a str, Vec<LinkPreview<'a>>>,
+    furniture: FurniturePreview<'a>,
+    private: bool,
+    required_tags: &'a [String],
+}
+
+#[derive(Debug, Serialize)]
+struct PositionInfoPreview<'a> {
+    race: &'a str,
+    race_key: Option<u8>,
+    sex: &'a Sex,
+    scale: f32,
+    submissive: bool,
+    vampire: bool,
+    dead: bool,
+    skeleton: &'a str,
+    voice: &'a str,
+    start_offset: f32,
+}
+
+#[derive(Debug, Serialize)]
+struct StagePreview<'a> {
+    id: &'a NanoID,
+    positions: Vec<PositionPreview<'a>>,
+    fixed_len: f32,
+    nav_text: &'a str,
+    tags: Vec<String>,
+    camera: Option<&'a CameraHint>,
+}
+
+#[derive(Debug, Serialize)]
+struct LinkPreview<'a> {
+    dest: &'a NanoID,
+    condition: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct PositionPreview<'a> {
+    event: &'a str,
+    climax: bool,
+    offset: &'a Offset,
+    strip_data: &'a Stripping,
+    tags: &'a [String],
+}
+
+#[derive(Debug, Serialize)]
+struct FurniturePreview<'a> {
+    types: &'a [String],
+    bits: u32,
+    allow_bed: bool,
+    offset: &'a Offset,
+}
+
+impl Package {
+    /// JSON rendering of what the registry file holds for a scene, disabled stages and positions dropped like
+    /// on export. The registry itself is binary, this is meant for reading
+    pub fn preview_scene_export(&self, id: &NanoID) -> Result<String, String> {
+        let scene = self
+            .get_scene(id)
+            .ok_or_else(|| format!("Invalid Scene ID: {}", id.0))?;
+        if scene.has_warnings || scene.stages.is_empty() {
+            return Err(format!(
+                "Scene {} has warnings or no stages and is left out of the export",
+                scene.name
+            ));
+        }
+        let exported = scene.for_export();
+        serde_json::to_string_pretty(&scene_preview(&exported)).map_err(|e| e.to_string())
+    }
+}
+
+pub(super) fn scene_preview(scene: &Scene) -> ScenePreview<'_> {
+    ScenePreview {
+        id: &scene.id,
+        name: &scene.name,
+        positions: scene
+            .positions
+            .iter()
+            .map(|info| PositionInfoPreview {
+                race: &info.race,
+                race_key: get_race_key_bytes(&info.race),
+                sex: &info.sex,
+                scale: info.scale,
+                submissive: info.submissive,
+                vampire: info.vampire,
+                dead: info.dead,
+                skeleton: info.skeleton.as_deref().unwrap_or_default(),
+                voice: info.voice.as_deref().unwrap_or_default(),
+                start_offset: info.start_offset,
+            })
+            .collect(),
+        stages: scene
+            .stages
+            .iter()
+            .map(|stage| StagePreview {
+                id: &stage.id,
+                positions: stage
+                    .positions
+                    .iter()
+                    .map(|position| PositionPreview {
+                        event: position.event.first().map(|event| event.as_str()).unwrap_or_default(),
+                        climax: position.climax,
+                        offset: &position.offset,
+                        strip_data: &position.strip_data,
+                        tags: &position.tags,
+                    })
+                    .collect(),
+                fixed_len: stage.extra.export_len(),
+                nav_text: &stage.extra.nav_text,
+                tags: stage.export_tags(),
+                camera: stage.camera.as_ref(),
+            })
+            .collect(),
+        graph: scene
+            .graph
+            .iter()
+            .map(|(id, node)| {
+                let links = node
+                    .dest
+                    .iter()
+                    .map(|dest| LinkPreview {
+                        dest,
+                        condition: node.condition(dest).unwrap_or_default(),
+                    })
+                    .collect();
+                (id.0.as_str(), links)
+            })
+            .collect(),
+        furniture: FurniturePreview {
+            types: &scene.furniture.furni_types,
+            bits: crate::furniture::as_furnitre(&scene.furniture.furni_types).bits(),
+            allow_bed: scene.furniture.allow_bed,
+            offset: &scene.furniture.offset,
+        },
+        private: scene.private,
+        required_tags: &scene.required_tags,
+    }
+}
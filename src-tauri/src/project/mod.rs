@@ -17,6 +17,30 @@ pub mod position_info;
 
 pub mod define;
 
+// Human readable documentation of a project, separate from the game export
+mod docs;
+
+// Distributable zip archive of the game export
+mod archive;
+
+// Example projects for trying out the builder
+mod sample;
+
+// Builder only metadata written next to the game export, for round-tripping published packs
+pub mod sidecar;
+
+// Consistency checks run before exporting a project
+pub mod validation;
+
+// Features of a project an older registry version can't represent
+mod compatibility;
+
+// Readable rendering of a scene's registry data
+mod export_preview;
+
+// Translatable texts of a project, exported for translators and applied back
+pub mod strings;
+
 mod serialize;
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -38,6 +62,12 @@ impl NanoID {
         Self::new(Self::NANOID_LENGTH)
     }
 
+    /// Whether this id could have been generated by `new_nanoid`
+    pub fn is_well_formed(&self) -> bool {
+        self.0.len() == Self::NANOID_LENGTH
+            && self.0.chars().all(|c| Self::NANOID_ALPHABET.contains(&c))
+    }
+
     fn new(len: usize) -> Self {
         assert!(
             len == Self::NANOID_LENGTH || len == Self::PREFIX_HASH_LEN,
@@ -64,3 +94,22 @@ impl EncodeBinary for NanoID {
         buf.extend_from_slice(self.0.as_bytes());
     }
 }
+
+/// Longest file name stem the export produces, leaving room for suffixes and deep install paths
+const MAX_FILE_NAME_LEN: usize = 64;
+
+/// Portable file name stem for the given name. Only characters windows doesn't allow in paths are replaced,
+/// so the stem still matches the folders animation files were placed in. Surrounding spaces and dots are
+/// trimmed as windows drops them
+pub fn sanitize_filename(name: &str) -> String {
+    let ret: String = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(MAX_FILE_NAME_LEN)
+        .collect();
+    ret.trim_matches([' ', '.']).to_string()
+}
@@ -1,29 +1,37 @@
-use log::info;
+use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fs,
     io::{BufReader, BufWriter, ErrorKind, Write},
     mem::size_of,
-    path::PathBuf,
+    path::{Path, PathBuf},
+    time::SystemTime,
     vec,
 };
-use tauri_plugin_dialog::DialogExt;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
 
 use crate::{
     project::{
-        define::{Node, Sex},
+        define::{Node, Offset, Sex},
         position::Position,
         serialize::{make_fnis_lines, map_race_to_folder},
     },
-    racekeys::map_legacy_to_racekey,
+    racekeys::{get_canonical_race_key, map_legacy_to_racekey, resolve_race_key_with},
+    settings::{ExportLayout, ExportSettings, ValidationSettings, SETTINGS},
 };
 
-use super::{scene::Scene, serialize::EncodeBinary, stage::Stage, NanoID};
+use super::validation::{log_issues, Severity};
 
-const VERSION: u8 = 4; // current version
+use super::{sanitize_filename, scene::Scene, serialize::EncodeBinary, stage::Stage, NanoID};
 
-#[derive(Debug, Serialize, Deserialize)]
+// current version, shared by the project file and the exported registry file
+// v5: packs carry a description, scenes a list of required actor tags, navigation links an optional
+//     transition condition, stages an optional camera hint and positions an optional skeleton
+//     requirement, voice set and start offset
+pub(super) const VERSION: u8 = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Package {
     #[serde(default)]
     pub version: u8,
@@ -32,8 +40,83 @@ pub struct Package {
 
     pub pack_name: String,
     pub pack_author: String,
+    #[serde(default)] // addition 2.1
+    pub pack_description: String,
+    // Load order hint for SexLab, packs with a higher priority are loaded later
+    #[serde(default)] // addition 2.1
+    pub priority: i32,
     pub prefix_hash: NanoID,
     pub scenes: HashMap<NanoID, Scene>,
+    // Content hash of every scene at the time it was last exported, see `build_changed`
+    #[serde(default)] // addition 2.1
+    pub export_hashes: HashMap<NanoID, String>,
+    // Order the builder lists scenes in, scenes missing from it follow sorted by name
+    #[serde(default)] // addition 2.1
+    pub scene_order: Vec<NanoID>,
+}
+
+/// Summary of a project's size and metadata
+#[derive(Debug, Serialize)]
+pub struct ProjectStats {
+    pub version: u8,
+    pub name: String,
+    pub author: String,
+    pub description: String,
+    pub scenes: usize,
+    pub stages: usize,
+    pub positions: usize,
+    pub private_scenes: usize,
+    // Sum of the estimated playtime of all scenes, in seconds
+    pub estimated_duration: f32,
+    // (stage count, number of scenes) pairs, see `Package::stage_count_histogram`
+    pub stage_counts: Vec<(usize, usize)>,
+}
+
+/// Order in which scenes can be listed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortKey {
+    Name,
+    StageCount,
+    // Alphabetically first tag of each scene, scenes without tags sort after tagged ones
+    Tag,
+    Modified,
+}
+
+/// What a file of the export holds
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportFileKind {
+    Registry,
+    Index,
+    FnisList,
+    Sidecar,
+}
+
+/// A file the export would write, relative to the export directory
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportFile {
+    pub relative_path: String,
+    pub kind: ExportFileKind,
+}
+
+/// Registry index listing the scenes of an exported pack
+#[derive(Debug, Serialize)]
+struct RegistryIndex {
+    pack: String,
+    author: String,
+    version: u8,
+    priority: i32,
+    scenes: Vec<IndexEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct IndexEntry {
+    id: NanoID,
+    name: String,
+    tags: Vec<String>,
+    // registry file holding the scene, relative to the registry folder
+    file: String,
 }
 
 impl Package {
@@ -43,24 +126,54 @@ impl Package {
             pack_path: Default::default(),
             pack_name: Default::default(),
             pack_author: "Unknown".into(),
+            pack_description: Default::default(),
+            priority: 0,
             prefix_hash: NanoID::new_prefix(),
             scenes: HashMap::new(),
+            export_hashes: HashMap::new(),
+            scene_order: vec![],
         }
     }
 
     pub fn from_file(file: std::fs::File) -> Result<Package, String> {
-        serde_json::from_reader(BufReader::new(file))
+        Self::from_file_with_progress(file, |_| {})
+    }
+
+    /// Parse a project file, reporting the percentage of the file read so far whenever it grows
+    pub fn from_file_with_progress(
+        file: std::fs::File,
+        on_progress: impl FnMut(u8),
+    ) -> Result<Package, String> {
+        let total = file.metadata().map(|m| m.len()).unwrap_or_default();
+        let reader = ProgressReader {
+            inner: BufReader::new(file),
+            read: 0,
+            total,
+            percent: 0,
+            on_progress,
+        };
+        serde_json::from_reader(reader)
             .map_err(|e| e.to_string())
             .and_then(|mut package: Package| {
                 if package.version < VERSION {
-                    package.update_to_latest_version()?;
+                    package.migrate()?;
+                } else if package.is_newer_than_supported() {
+                    warn!(
+                        "Project {} uses format version {}, newer than the supported version {}",
+                        package.pack_name, package.version, VERSION
+                    );
                 }
                 info!("Loaded project {}", package.pack_name);
                 Ok(package)
             })
     }
 
-    fn update_to_latest_version(&mut self) -> Result<(), String> {
+    /// Upgrade a project stored in an older format version to the current schema
+    pub fn migrate(&mut self) -> Result<(), String> {
+        info!(
+            "Migrating project {} from version {} to {}",
+            self.pack_name, self.version, VERSION
+        );
         for (_, scene) in &mut self.scenes {
             if let Err(e) = scene.update_to_latest_version(self.version) {
                 return Err(format!("Failed to update scene {}: {}", scene.id.0, e));
@@ -70,18 +183,178 @@ impl Package {
         Ok(())
     }
 
+    pub fn is_newer_than_supported(&self) -> bool {
+        self.version > VERSION
+    }
+
+    pub fn stats(&self) -> ProjectStats {
+        let stages = self.scenes.values().flat_map(|scene| scene.stages.iter());
+        ProjectStats {
+            version: self.version,
+            name: self.pack_name.clone(),
+            author: self.pack_author.clone(),
+            description: self.pack_description.clone(),
+            scenes: self.scenes.len(),
+            stages: stages.clone().count(),
+            positions: stages.map(|stage| stage.positions.len()).sum(),
+            private_scenes: self.scenes.values().filter(|scene| scene.private).count(),
+            estimated_duration: self.scenes.values().map(|scene| scene.estimated_duration()).sum(),
+            stage_counts: self.stage_count_histogram(),
+        }
+    }
+
+    /// Number of scenes per stage count, as (stage count, number of scenes) pairs sorted by stage count
+    pub fn stage_count_histogram(&self) -> Vec<(usize, usize)> {
+        let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+        for scene in self.scenes.values() {
+            *counts.entry(scene.stages.len()).or_default() += 1;
+        }
+        counts.into_iter().collect()
+    }
+
     pub fn reset(&mut self) -> &Self {
         *self = Self::new();
         self
     }
 
-    pub fn save_scene(&mut self, scene: Scene) -> &Scene {
+    pub fn save_scene(&mut self, mut scene: Scene) -> &Scene {
+        scene.modified = SystemTime::now();
         let id = scene.id.clone();
         info!("Saving or inserting Scene: {} / {}", id.0, scene.name);
         self.scenes.insert(id.clone(), scene);
         self.scenes.get(&id).unwrap()
     }
 
+    /// Trim the position infos of a scene to the actors its stages use, returns the number of entries removed
+    pub fn compact_positions(&mut self, scene_id: &NanoID) -> usize {
+        self.get_scene_mut(scene_id)
+            .map_or(0, |scene| scene.compact_positions())
+    }
+
+    /// Names of other scenes within a small edit distance of the given name, ignoring case
+    pub fn similar_scene_names(&self, id: &NanoID, name: &str) -> Vec<String> {
+        let name = name.trim().to_lowercase();
+        if name.is_empty() {
+            return vec![];
+        }
+        let threshold = if name.chars().count() < 6 { 1 } else { 2 };
+        let mut ret: Vec<String> = self
+            .scenes
+            .values()
+            .filter(|scene| scene.id != *id)
+            .filter(|scene| levenshtein(&name, &scene.name.trim().to_lowercase()) <= threshold)
+            .map(|scene| scene.name.clone())
+            .collect();
+        ret.sort();
+        ret
+    }
+
+    /// Assemble a new scene from copies of the referenced (scene, stage) pairs, linked in the given order.
+    /// Positions and furniture are taken from the scene of the first stage
+    pub fn scene_from_stages(&mut self, stage_refs: &[(NanoID, NanoID)]) -> Result<&Scene, String> {
+        let mut stages: Vec<(&Scene, &Stage)> = vec![];
+        for (scene_id, stage_id) in stage_refs {
+            let scene = self
+                .get_scene(scene_id)
+                .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?;
+            let stage = scene
+                .get_stage(stage_id)
+                .ok_or_else(|| format!("Scene {} has no stage with id {}", scene.name, stage_id.0))?;
+            stages.push((scene, stage));
+        }
+        let (origin, _) = *stages.first().ok_or("No stages selected".to_string())?;
+        let mismatches: Vec<String> = stages
+            .iter()
+            .filter(|(_, stage)| stage.positions.len() != origin.positions.len())
+            .map(|(scene, stage)| {
+                format!("{} / {} ({} positions)", scene.name, stage.name, stage.positions.len())
+            })
+            .collect();
+        if !mismatches.is_empty() {
+            return Err(format!(
+                "Expected {} positions, but these stages differ: {}",
+                origin.positions.len(),
+                mismatches.join(", ")
+            ));
+        }
+
+        let mut scene = Scene {
+            name: format!("{} (Assembled)", origin.name),
+            positions: origin.positions.clone(),
+            furniture: origin.furniture.clone(),
+            tags: origin.tags.clone(),
+            ..Default::default()
+        };
+        scene.stages = stages
+            .iter()
+            .map(|(_, stage)| Stage {
+                id: NanoID::new_nanoid(),
+                ..(*stage).clone()
+            })
+            .collect();
+        for (i, stage) in scene.stages.iter().enumerate() {
+            let node = Node {
+                dest: scene.stages.get(i + 1).map(|next| vec![next.id.clone()]).unwrap_or_default(),
+                x: 40.0 + 200.0 * i as f32,
+                ..Default::default()
+            };
+            scene.graph.insert(stage.id.clone(), node);
+        }
+        scene.root = scene.stages[0].id.clone();
+        info!(
+            "Assembled Scene {} from {} stages",
+            scene.name,
+            scene.stages.len()
+        );
+        Ok(self.save_scene(scene))
+    }
+
+    /// Regenerate the ids of every scene and stage, see `Scene::reassign_ids`. Returns the number of ids replaced.
+    /// Export hashes refer to the old ids, so every scene counts as changed afterwards
+    pub fn reassign_all_ids(&mut self) -> usize {
+        let mut reassigned = 0;
+        let mut ids: HashMap<NanoID, NanoID> = HashMap::new();
+        let scenes = std::mem::take(&mut self.scenes);
+        for (old_id, mut scene) in scenes {
+            reassigned += scene.reassign_ids();
+            ids.insert(old_id, scene.id.clone());
+            self.scenes.insert(scene.id.clone(), scene);
+        }
+        self.scene_order = self
+            .scene_order
+            .iter()
+            .filter_map(|id| ids.get(id).cloned())
+            .collect();
+        self.export_hashes.clear();
+        reassigned
+    }
+
+    /// Round all offsets, including furniture offsets, to the given number of decimals to drop float noise
+    /// picked up by text round trips. Returns the number of values adjusted
+    pub fn normalize_offset_precision(&mut self, decimals: u32) -> usize {
+        let mut adjusted = 0;
+        for scene in self.scenes.values_mut() {
+            adjusted += scene.furniture.offset.round_to(decimals);
+            for position in scene.stages.iter_mut().flat_map(|stage| stage.positions.iter_mut()) {
+                adjusted += position.offset.round_to(decimals);
+                for offset in position.furniture_offsets.values_mut() {
+                    adjusted += offset.round_to(decimals);
+                }
+            }
+        }
+        if adjusted > 0 {
+            info!("Rounded {} offset values to {} decimals", adjusted, decimals);
+        }
+        adjusted
+    }
+
+    pub fn repair_nav_links(&mut self) -> usize {
+        self.scenes
+            .values_mut()
+            .map(|scene| scene.repair_nav_links())
+            .sum()
+    }
+
     pub fn discard_scene(&mut self, id: &NanoID) -> Option<Scene> {
         self.scenes.remove(id).map(|s| {
             info!("Deleting Scene: {} / {}", id.0, s.name);
@@ -89,6 +362,104 @@ impl Package {
         })
     }
 
+    /// Scenes in the order set through `reorder_scenes`, followed by the remaining scenes by name
+    pub fn ordered_scenes(&self) -> Vec<&Scene> {
+        let mut ret: Vec<&Scene> = self.scene_order.iter().filter_map(|id| self.get_scene(id)).collect();
+        let listed: HashSet<&NanoID> = ret.iter().map(|scene| &scene.id).collect();
+        let remaining: Vec<&Scene> = self
+            .scenes_by_name()
+            .into_iter()
+            .filter(|scene| !listed.contains(&scene.id))
+            .collect();
+        ret.extend(remaining);
+        ret
+    }
+
+    /// Set the order scenes are listed in. Unknown ids are ignored and scenes missing from
+    /// the list keep their relative order at the end
+    pub fn reorder_scenes(&mut self, ordered_ids: &[NanoID]) -> () {
+        let mut order: Vec<NanoID> = vec![];
+        for id in ordered_ids {
+            if self.get_scene(id).is_none() {
+                warn!("Project has no scene with id {}", id.0);
+            } else if !order.contains(id) {
+                order.push(id.clone());
+            }
+        }
+        let remaining: Vec<NanoID> = self
+            .ordered_scenes()
+            .into_iter()
+            .map(|scene| scene.id.clone())
+            .filter(|id| !order.contains(id))
+            .collect();
+        order.extend(remaining);
+        self.scene_order = order;
+    }
+
+    /// Reorder scenes from a text file listing one scene name or id per line, see `reorder_scenes`.
+    /// Empty lines and lines starting with `#` are skipped, names match ignoring case
+    pub fn import_scene_order(&mut self, path: &Path) -> Result<SceneOrderImportSummary, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let mut summary = SceneOrderImportSummary::default();
+        let mut ordered_ids: Vec<NanoID> = vec![];
+        for line in text.lines().map(|line| line.trim()) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let scene = self.get_scene(&NanoID(line.to_string())).or_else(|| {
+                self.scenes_by_name()
+                    .into_iter()
+                    .find(|scene| scene.name.trim().eq_ignore_ascii_case(line))
+            });
+            match scene {
+                Some(scene) if !ordered_ids.contains(&scene.id) => {
+                    ordered_ids.push(scene.id.clone());
+                    summary.matched += 1;
+                }
+                Some(_) => {}
+                None => summary.unmatched.push(line.to_string()),
+            }
+        }
+        self.reorder_scenes(&ordered_ids);
+        info!(
+            "Imported scene order of {} scenes from {}, {} lines unmatched",
+            summary.matched,
+            path.display(),
+            summary.unmatched.len()
+        );
+        Ok(summary)
+    }
+
+    /// Scenes sorted by name, for output which should be stable between runs
+    pub fn scenes_by_name(&self) -> Vec<&Scene> {
+        let mut scenes: Vec<&Scene> = self.scenes.values().collect();
+        scenes.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.0.cmp(&b.id.0)));
+        scenes
+    }
+
+    /// All scenes ordered by the given key, ties are ordered by name
+    pub fn sorted_scenes(&self, key: SortKey, ascending: bool) -> Vec<&Scene> {
+        let first_tag = |scene: &Scene| scene.tags.iter().map(|tag| tag.to_lowercase()).min();
+        let mut scenes = self.scenes_by_name();
+        scenes.sort_by(|a, b| {
+            let order = match key {
+                SortKey::Name => a.name.cmp(&b.name),
+                SortKey::StageCount => a.stages.len().cmp(&b.stages.len()),
+                SortKey::Tag => match (first_tag(a), first_tag(b)) {
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    (a, b) => a.is_none().cmp(&b.is_none()),
+                },
+                SortKey::Modified => a.modified.cmp(&b.modified),
+            };
+            if ascending {
+                order
+            } else {
+                order.reverse()
+            }
+        });
+        scenes
+    }
+
     pub fn get_scene(&self, id: &NanoID) -> Option<&Scene> {
         self.scenes.get(id)
     }
@@ -97,6 +468,428 @@ impl Package {
         self.scenes.get_mut(id)
     }
 
+    pub fn set_scene_image(&mut self, id: &NanoID, image: Option<PathBuf>) -> Result<&Scene, String> {
+        if let Some(path) = &image {
+            if !path.is_file() {
+                return Err(format!("Thumbnail {} does not exist", path.display()));
+            }
+        }
+        let scene = self
+            .get_scene_mut(id)
+            .ok_or(format!("Invalid Scene ID: {}", id.0))?;
+        info!("Setting thumbnail of Scene {} to {:?}", id.0, image);
+        scene.image = image;
+        Ok(&*scene)
+    }
+
+    /// Thumbnail of a single stage, shown on storyboards instead of the scene's
+    pub fn set_stage_image(
+        &mut self,
+        scene_id: &NanoID,
+        stage_id: &NanoID,
+        image: Option<PathBuf>,
+    ) -> Result<&Scene, String> {
+        if let Some(path) = &image {
+            if !path.is_file() {
+                return Err(format!("Thumbnail {} does not exist", path.display()));
+            }
+        }
+        let scene = self
+            .get_scene_mut(scene_id)
+            .ok_or(format!("Invalid Scene ID: {}", scene_id.0))?;
+        let stage = scene
+            .get_stage_mut(stage_id)
+            .ok_or(format!("Invalid Stage ID: {}", stage_id.0))?;
+        info!("Setting thumbnail of Stage {} to {:?}", stage_id.0, image);
+        stage.image = image;
+        Ok(&*scene)
+    }
+
+    /// Rewrite all race keys to their canonical spelling, returning the number of keys changed
+    pub fn canonicalize_race_keys(&mut self) -> usize {
+        let mut changed = 0;
+        for (_, scene) in &mut self.scenes {
+            for (i, info) in scene.positions.iter_mut().enumerate() {
+                match get_canonical_race_key(&info.race) {
+                    Some(canonical) if canonical != info.race => {
+                        info!(
+                            "Scene {} Position {}: Renaming race key {} to {}",
+                            scene.id.0, i, info.race, canonical
+                        );
+                        info.race = canonical;
+                        changed += 1;
+                    }
+                    Some(_) => {}
+                    None => warn!(
+                        "Scene {} Position {}: Unknown race key {}",
+                        scene.id.0, i, info.race
+                    ),
+                }
+            }
+        }
+        changed
+    }
+
+    /// Replace race key aliases with the keys they stand for, see `racekeys::resolve_race_key`.
+    /// Returns the number of positions changed
+    pub fn resolve_race_aliases(&mut self) -> usize {
+        let aliases = SETTINGS.lock().unwrap().race_key_aliases.clone();
+        let mut changed = 0;
+        for scene in self.scenes.values_mut() {
+            for (i, info) in scene.positions.iter_mut().enumerate() {
+                let resolved = resolve_race_key_with(&aliases, &info.race);
+                if resolved != info.race {
+                    info!(
+                        "Scene {} Position {}: Resolved race key {} to {}",
+                        scene.id.0, i, info.race, resolved
+                    );
+                    info.race = resolved;
+                    changed += 1;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Number of animation events across all stages matching the given event name
+    pub fn count_event(&self, event: &str) -> usize {
+        self.scenes
+            .values()
+            .flat_map(|scene| scene.stages.iter())
+            .flat_map(|stage| stage.positions.iter())
+            .flat_map(|position| position.event.iter())
+            .filter(|e| e.as_str() == event)
+            .count()
+    }
+
+    /// Scene and stage ids of every stage with a position playing the event. Matching is case-sensitive,
+    /// like the file systems the game loads animations from
+    pub fn stages_using_event(&self, event: &str) -> Vec<(NanoID, NanoID)> {
+        self.scenes_by_name()
+            .into_iter()
+            .flat_map(|scene| scene.stages.iter().map(move |stage| (scene, stage)))
+            .filter(|(_, stage)| {
+                stage
+                    .positions
+                    .iter()
+                    .any(|position| position.event.iter().any(|e| e == event))
+            })
+            .map(|(scene, stage)| (scene.id.clone(), stage.id.clone()))
+            .collect()
+    }
+
+    /// Scene and stage ids of every enabled stage of a multi-stage scene which ends playback without a climax,
+    /// see `Scene::is_dead_end`
+    pub fn dead_end_stages(&self) -> Vec<(NanoID, NanoID)> {
+        self.scenes_by_name()
+            .into_iter()
+            .filter(|scene| scene.stages.len() > 1)
+            .flat_map(|scene| scene.stages.iter().map(move |stage| (scene, stage)))
+            .filter(|(scene, stage)| stage.enabled && scene.is_dead_end(stage))
+            .map(|(scene, stage)| (scene.id.clone(), stage.id.clone()))
+            .collect()
+    }
+
+    /// Rename an animation event across all stages, returning the number of events changed
+    pub fn replace_event(&mut self, old: &str, new: &str) -> usize {
+        if old == new {
+            return 0;
+        }
+        let mut changed = 0;
+        for (_, scene) in &mut self.scenes {
+            for stage in &mut scene.stages {
+                for (i, position) in stage.positions.iter_mut().enumerate() {
+                    for event in position.event.iter_mut().filter(|e| e.as_str() == old) {
+                        info!(
+                            "Stage {} Position {}: Renaming event {} to {}",
+                            stage.id.0, i, old, new
+                        );
+                        *event = new.to_string();
+                        changed += 1;
+                    }
+                }
+            }
+        }
+        changed
+    }
+
+    /// Rename a tag on every scene and stage, ignoring case. Lists already holding the new tag keep a
+    /// single copy of it. Returns the number of tag lists changed
+    pub fn rename_tag(&mut self, old: &str, new: &str) -> usize {
+        let mut changed = 0;
+        for (_, scene) in &mut self.scenes {
+            if rename_tag_in(&mut scene.tags, old, new) {
+                info!("Scene {}: Renamed tag {} to {}", scene.id.0, old, new);
+                changed += 1;
+            }
+            for stage in &mut scene.stages {
+                if rename_tag_in(&mut stage.tags, old, new) {
+                    info!("Stage {}: Renamed tag {} to {}", stage.id.0, old, new);
+                    changed += 1;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Empty or whitespace only scene names, stage names and tags of scenes and stages
+    pub fn find_text_issues(&self) -> Vec<TextIssue> {
+        let mut ret = vec![];
+        for scene in self.scenes_by_name() {
+            let issue = |stage: Option<&Stage>, message: String| TextIssue {
+                scene: scene.id.clone(),
+                stage: stage.map(|stage| stage.id.clone()),
+                message,
+            };
+            if scene.name.trim().is_empty() {
+                ret.push(issue(None, format!("Scene {} has no name", scene.id.0)));
+            }
+            let empty_tags = scene.tags.iter().filter(|tag| tag.trim().is_empty()).count();
+            if empty_tags > 0 {
+                ret.push(issue(None, format!("Scene {}: Has {} empty tags", scene.name, empty_tags)));
+            }
+            for stage in &scene.stages {
+                if stage.name.trim().is_empty() {
+                    ret.push(issue(
+                        Some(stage),
+                        format!("Scene {}: Stage {} has no name", scene.name, stage.id.0),
+                    ));
+                }
+                let empty_tags = stage.tags.iter().filter(|tag| tag.trim().is_empty()).count();
+                if empty_tags > 0 {
+                    ret.push(issue(
+                        Some(stage),
+                        format!("Scene {} Stage {}: Has {} empty tags", scene.name, stage.name, empty_tags),
+                    ));
+                }
+            }
+        }
+        ret
+    }
+
+    /// Trim whitespace around scene names, stage names and tags and drop empty tags.
+    /// Returns the number of names and tags fixed
+    pub fn trim_all_text(&mut self) -> usize {
+        let trim = |text: &mut String| {
+            let trimmed = text.trim();
+            if trimmed.len() == text.len() {
+                return false;
+            }
+            *text = trimmed.to_string();
+            true
+        };
+        let trim_tags = |tags: &mut Vec<String>| {
+            let before = tags.len();
+            tags.retain(|tag| !tag.trim().is_empty());
+            let mut fixed = before - tags.len();
+            for tag in tags.iter_mut() {
+                fixed += trim(tag) as usize;
+            }
+            fixed
+        };
+        let mut fixed = 0;
+        for scene in self.scenes.values_mut() {
+            fixed += trim(&mut scene.name) as usize + trim_tags(&mut scene.tags);
+            for stage in &mut scene.stages {
+                fixed += trim(&mut stage.name) as usize + trim_tags(&mut stage.tags);
+            }
+        }
+        if fixed > 0 {
+            info!("Trimmed {} names and tags", fixed);
+        }
+        fixed
+    }
+
+    /// Number of position offsets which lie outside the given bounds
+    pub fn count_offsets_outside(&self, min: &Offset, max: &Offset) -> Result<usize, String> {
+        check_offset_bounds(min, max)?;
+        Ok(self
+            .scenes
+            .values()
+            .flat_map(|scene| scene.stages.iter())
+            .flat_map(|stage| stage.positions.iter())
+            .filter(|position| position.offset.clamped(min, max) != position.offset)
+            .count())
+    }
+
+    /// Clamp every position offset into the given bounds, returns the number of offsets changed
+    pub fn clamp_all_offsets(&mut self, min: &Offset, max: &Offset) -> Result<usize, String> {
+        check_offset_bounds(min, max)?;
+        let mut changed = 0;
+        for position in self
+            .scenes
+            .values_mut()
+            .flat_map(|scene| scene.stages.iter_mut())
+            .flat_map(|stage| stage.positions.iter_mut())
+        {
+            let clamped = position.offset.clamped(min, max);
+            if clamped != position.offset {
+                position.offset = clamped;
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Store one offset per actor slot as the furniture offset of every scene using that furniture. Scenes
+    /// using only this furniture also take the offsets as their active offsets. Scenes with stages whose
+    /// actor count differs from the profile are left untouched and reported
+    pub fn apply_furniture_profile(
+        &mut self,
+        furniture: &str,
+        offsets: &[Offset],
+    ) -> Result<FurnitureProfileSummary, String> {
+        if offsets.is_empty() {
+            return Err("Furniture profile has no offsets".into());
+        }
+        if let Some(i) = offsets.iter().position(|offset| !offset.is_finite()) {
+            return Err(format!("Offset {} of the furniture profile is invalid", i + 1));
+        }
+        let mut summary = FurnitureProfileSummary::default();
+        for scene in self.scenes.values_mut() {
+            if !scene.furniture.furni_types.iter().any(|furni| furni == furniture) {
+                continue;
+            }
+            if let Some(stage) = scene
+                .stages
+                .iter()
+                .find(|stage| stage.positions.len() != offsets.len())
+            {
+                summary.mismatched.push(format!(
+                    "{} (Stage {} has {} positions)",
+                    scene.name,
+                    stage.name,
+                    stage.positions.len()
+                ));
+                continue;
+            }
+            let only = scene.furniture.furni_types.len() == 1;
+            for stage in &mut scene.stages {
+                for (position, offset) in stage.positions.iter_mut().zip(offsets) {
+                    position.furniture_offsets.insert(furniture.to_string(), offset.clone());
+                    if only {
+                        position.offset = offset.clone();
+                    }
+                    summary.positions += 1;
+                }
+            }
+            summary.scenes += 1;
+        }
+        summary.mismatched.sort();
+        Ok(summary)
+    }
+
+    /// Animation events out of the given ones which no position plays, events compare case-insensitively
+    pub fn missing_events(&self, available: Vec<String>) -> Vec<String> {
+        let used: HashSet<String> = self
+            .scenes
+            .values()
+            .flat_map(|scene| scene.stages.iter())
+            .flat_map(|stage| stage.positions.iter())
+            .flat_map(|position| position.event.iter())
+            .map(|event| event.to_lowercase())
+            .collect();
+        let mut seen = HashSet::new();
+        available
+            .into_iter()
+            .filter(|event| {
+                let key = event.to_lowercase();
+                !used.contains(&key) && seen.insert(key)
+            })
+            .collect()
+    }
+
+    /// Sorted list of all race keys in use
+    pub fn used_race_keys(&self) -> Vec<String> {
+        self.race_key_usage().into_keys().collect()
+    }
+
+    /// Number of positions using each race key
+    pub fn race_key_usage(&self) -> BTreeMap<String, usize> {
+        let mut ret = BTreeMap::new();
+        for info in self.scenes.values().flat_map(|scene| scene.positions.iter()) {
+            *ret.entry(info.race.clone()).or_insert(0) += 1;
+        }
+        ret
+    }
+
+    /// Sorted list of race keys in use which are not part of the known race keys
+    pub fn unknown_race_keys(&self) -> Vec<String> {
+        let mut ret: Vec<String> = self
+            .scenes
+            .values()
+            .flat_map(|scene| scene.positions.iter())
+            .filter(|info| get_canonical_race_key(&info.race).is_none())
+            .map(|info| info.race.clone())
+            .collect();
+        ret.sort();
+        ret.dedup();
+        ret
+    }
+
+    /// Sorted distinct race keys per actor slot, the outer index being the position index across all scenes
+    pub fn race_key_matrix(&self) -> Vec<Vec<String>> {
+        let mut ret: Vec<Vec<String>> = vec![];
+        for scene in self.scenes.values() {
+            for (i, info) in scene.positions.iter().enumerate() {
+                if ret.len() <= i {
+                    ret.resize(i + 1, vec![]);
+                }
+                if !ret[i].contains(&info.race) {
+                    ret[i].push(info.race.clone());
+                }
+            }
+        }
+        for keys in &mut ret {
+            keys.sort();
+        }
+        ret
+    }
+
+    /// Scenes whose climax stages cannot be reached from their start stage
+    pub fn scenes_with_unreachable_climax(&self) -> Vec<NanoID> {
+        let mut ret: Vec<NanoID> = self
+            .scenes
+            .values()
+            .filter(|scene| scene.has_unreachable_climax())
+            .map(|scene| scene.id.clone())
+            .collect();
+        ret.sort_by(|a, b| a.0.cmp(&b.0));
+        ret
+    }
+
+    /// Scenes whose stages don't all use the same number of actors
+    pub fn scenes_with_mismatched_counts(&self) -> Vec<NanoID> {
+        let mut ret: Vec<NanoID> = self
+            .scenes
+            .values()
+            .filter(|scene| scene.stage_actor_counts().len() > 1)
+            .map(|scene| scene.id.clone())
+            .collect();
+        ret.sort_by(|a, b| a.0.cmp(&b.0));
+        ret
+    }
+
+    /// Scenes which look abandoned: without stages, with empty stages, or without a proper name
+    pub fn find_incomplete_scenes(&self) -> Vec<NanoID> {
+        let is_placeholder = |name: &str| {
+            let name = name.trim();
+            name.is_empty() || name.eq_ignore_ascii_case("untitled")
+        };
+        let mut ret: Vec<NanoID> = self
+            .scenes
+            .values()
+            .filter(|scene| {
+                is_placeholder(&scene.name)
+                    || scene.stages.is_empty()
+                    || scene.stages.iter().any(|stage| stage.positions.is_empty())
+            })
+            .map(|scene| scene.id.clone())
+            .collect();
+        ret.sort_by(|a, b| a.0.cmp(&b.0));
+        ret
+    }
+
     pub fn get_stage(&self, id: &NanoID) -> Option<&Stage> {
         for (_, scene) in &self.scenes {
             let stage = scene.get_stage(id);
@@ -107,19 +900,106 @@ impl Package {
         None
     }
 
-    pub fn load_project(&mut self, app: &tauri::AppHandle) -> Result<(), String> {
-        let path = app
-            .dialog()
+    pub fn pick_project_file(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+        app.dialog()
             .file()
             .add_filter("SexLab Project", &["slsb.json"])
             .blocking_pick_file()
             .ok_or("No path to load project from".to_string())?
             .into_path()
-            .map_err(|e| e.to_string())?;
-        *self = Package::from_file(fs::File::open(&path).map_err(|e| e.to_string())?)?;
+            .map_err(|e| e.to_string())
+    }
+
+    /// Read a project file for use in the editor, offering recovery copies of a damaged file and
+    /// asking before loading a newer format. The current project is left untouched, so nothing
+    /// refers to a partially loaded project
+    pub fn read_project(
+        app: &tauri::AppHandle,
+        path: PathBuf,
+        on_progress: impl FnMut(u8),
+    ) -> Result<Package, String> {
+        let mut package = match fs::File::open(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| Package::from_file_with_progress(file, on_progress))
+        {
+            Ok(package) => package,
+            Err(e) => {
+                let size = fs::metadata(&path).map(|m| m.len()).unwrap_or_default();
+                error!(
+                    "Project file {} is damaged ({} bytes): {}",
+                    path.display(),
+                    size,
+                    e
+                );
+                let (recovery_path, package) = find_recovery(&path)
+                    .ok_or(format!("Failed to load project {}: {}", path.display(), e))?;
+                let recover = app
+                    .dialog()
+                    .message(format!(
+                        "The project file could not be read, it may have been damaged during an interrupted save:\n{}\n\nA recovery copy was found at {}. Load it instead?\nSave the project afterwards to restore the original file.",
+                        e,
+                        recovery_path.display()
+                    ))
+                    .title("Open Project")
+                    .buttons(MessageDialogButtons::YesNo)
+                    .kind(MessageDialogKind::Warning)
+                    .blocking_show();
+                if !recover {
+                    return Err(format!("Failed to load project {}: {}", path.display(), e));
+                }
+                info!("Recovered project from {}", recovery_path.display());
+                package
+            }
+        };
+        if package.is_newer_than_supported() {
+            let proceed = app
+                .dialog()
+                .message(format!(
+                    "This project was saved by a newer version of the Scene Builder (format version {}, supported up to {}). Data this version does not know about will be lost when saving.\nContinue loading?",
+                    package.version, VERSION
+                ))
+                .title("Open Project")
+                .buttons(MessageDialogButtons::YesNo)
+                .kind(MessageDialogKind::Warning)
+                .blocking_show();
+            if !proceed {
+                return Err("Loading cancelled, project version is not supported".into());
+            }
+            package.version = VERSION;
+        }
+        package.finish_loading(path);
+        Ok(package)
+    }
+
+    /// Read a project file passed on the command line at startup. There is no window to ask from yet, so
+    /// damaged files and newer formats are refused instead of offering recovery copies or loading anyway
+    pub fn read_startup_project(path: PathBuf) -> Result<Package, String> {
+        if !path.is_file() {
+            return Err(format!("Project file {} does not exist", path.display()));
+        }
+        let mut package = fs::File::open(&path)
+            .map_err(|e| e.to_string())
+            .and_then(Package::from_file)
+            .map_err(|e| format!("Failed to load project {}: {}", path.display(), e))?;
+        if package.is_newer_than_supported() {
+            return Err(format!(
+                "Project {} was saved by a newer version of the Scene Builder (format version {}, supported up to {})",
+                path.display(),
+                package.version,
+                VERSION
+            ));
+        }
+        package.finish_loading(path);
+        Ok(package)
+    }
+
+    fn finish_loading(&mut self, path: PathBuf) -> () {
+        let repaired = self.repair_nav_links();
+        if repaired > 0 {
+            warn!("Removed {} broken nav links while loading the project", repaired);
+        }
         self.set_project_name_from_path(&path);
-        self.pack_path = path.into();
-        Ok(())
+        self.pack_path = path;
     }
 
     pub fn save_project(&mut self, save_as: bool, app: &tauri::AppHandle) -> Result<(), String> {
@@ -136,18 +1016,146 @@ impl Package {
         } else {
             self.pack_path.clone()
         };
+        if save_as && path.exists() && SETTINGS.lock().unwrap().confirm_overwrite {
+            let overwrite = app
+                .dialog()
+                .message(format!(
+                    "{} already exists.\nDo you want to replace it?",
+                    path.display()
+                ))
+                .title("Save Project")
+                .buttons(MessageDialogButtons::YesNo)
+                .kind(MessageDialogKind::Warning)
+                .blocking_show();
+            if !overwrite {
+                return Err(format!("Save cancelled, {} already exists", path.display()));
+            }
+        }
 
         self.set_project_name_from_path(&path);
         self.write(path)
     }
 
+    /// Ask for a file and write a copy of the project into it, named after the file like Save As
+    pub fn duplicate_project_as(&self, app: &tauri::AppHandle) -> Result<PathBuf, String> {
+        let path = app
+            .dialog()
+            .file()
+            .set_title("Duplicate Project As")
+            .set_file_name(format!("{} Copy", self.pack_name))
+            .add_filter("SexLab Project", &["slsb.json"])
+            .blocking_save_file()
+            .ok_or("No path to duplicate project to".to_string())?
+            .into_path()
+            .map_err(|e| e.to_string())?;
+        let reassign_ids = app
+            .dialog()
+            .message("Give the scenes and stages of the copy new ids?\nThis is required to load both packs side by side, but exports of the copy will no longer update scenes exported from this project.")
+            .title("Duplicate Project As")
+            .buttons(MessageDialogButtons::YesNo)
+            .kind(MessageDialogKind::Info)
+            .blocking_show();
+        self.duplicate_project(&project_name_from_path(&path), &path, reassign_ids)
+    }
+
+    /// Write a copy of the project under a new pack name, optionally with new scene and stage ids.
+    /// The current project is left untouched, including its path
+    pub fn duplicate_project(&self, new_name: &str, path: &Path, reassign_ids: bool) -> Result<PathBuf, String> {
+        let new_name = new_name.trim();
+        if new_name.is_empty() || sanitize_filename(new_name).is_empty() {
+            return Err(format!("Invalid pack name: {}", new_name));
+        }
+        if new_name == self.pack_name {
+            return Err("The copy needs a pack name different from this project".into());
+        }
+        if path.is_dir() || path.parent().is_some_and(|dir| !dir.as_os_str().is_empty() && !dir.is_dir()) {
+            return Err(format!("Invalid project path: {}", path.display()));
+        }
+        if path == self.pack_path {
+            return Err("The copy must not replace the current project file".into());
+        }
+        let mut project = Package {
+            pack_name: new_name.to_string(),
+            pack_path: path.to_path_buf(),
+            // The copy has never been exported under its new name
+            export_hashes: HashMap::new(),
+            ..self.clone()
+        };
+        if reassign_ids {
+            project.reassign_all_ids();
+        }
+        project.write(path.to_path_buf())?;
+        info!("Duplicated project {} as {} at {}", self.pack_name, new_name, path.display());
+        Ok(path.to_path_buf())
+    }
+
+    /// Save the project, in the canonical format if the settings ask for it, see `save_canonical`
     pub fn write(&mut self, path: PathBuf) -> Result<(), String> {
-        let file = fs::File::create(&path).map_err(|e| e.to_string())?;
-        serde_json::to_writer(file, self).map_err(|e| e.to_string())?;
+        let canonical = SETTINGS.lock().unwrap().canonical_saves;
+        self.write_project_file(&path, canonical)
+    }
+
+    /// Save the project with object keys sorted and indented, so saving an unchanged project rewrites the
+    /// same file and diffs between versions only show actual changes. The file loads like any other project
+    pub fn save_canonical(&self, path: &Path) -> Result<(), String> {
+        self.write_project_file(path, true)
+    }
+
+    fn write_project_file(&self, path: &Path, canonical: bool) -> Result<(), String> {
+        // Write into a temporary file first, so an interrupted save never truncates the project
+        let temp = temp_path(path);
+        let file = fs::File::create(&temp).map_err(|e| e.to_string())?;
+        let mut writer = BufWriter::new(file);
+        if canonical {
+            // Maps of a json value are sorted by key, this orders the scene and node maps by id
+            let value = serde_json::to_value(self).map_err(|e| e.to_string())?;
+            serde_json::to_writer_pretty(&mut writer, &value).map_err(|e| e.to_string())?;
+            writer.write_all(b"\n").map_err(|e| e.to_string())?;
+        } else {
+            serde_json::to_writer(&mut writer, self).map_err(|e| e.to_string())?;
+        }
+        writer
+            .into_inner()
+            .map_err(|e| e.to_string())?
+            .sync_all()
+            .map_err(|e| e.to_string())?;
+        if path.is_file() {
+            fs::copy(path, backup_path(path)).map_err(|e| e.to_string())?;
+        }
+        fs::rename(&temp, path).map_err(|e| e.to_string())?;
         println!("Saved project {}", self.pack_name);
         Ok(())
     }
 
+    /// Save the given scenes as a new project file, keeping their ids and the
+    /// pack metadata. The current project is left untouched
+    pub fn export_as_project(&self, ids: &[NanoID], path: &Path) -> Result<PathBuf, String> {
+        if ids.is_empty() {
+            return Err("No scenes selected".into());
+        }
+        let mut scenes = HashMap::new();
+        for id in ids {
+            let scene = self
+                .get_scene(id)
+                .ok_or_else(|| format!("Invalid Scene ID: {}", id.0))?;
+            scenes.insert(id.clone(), scene.clone());
+        }
+        let mut project = Package {
+            pack_path: path.to_path_buf(),
+            export_hashes: self
+                .export_hashes
+                .iter()
+                .filter(|(id, _)| scenes.contains_key(*id))
+                .map(|(id, hash)| (id.clone(), hash.clone()))
+                .collect(),
+            scenes,
+            ..self.clone()
+        };
+        project.write(path.to_path_buf())?;
+        info!("Exported {} scenes as project {}", ids.len(), path.display());
+        Ok(path.to_path_buf())
+    }
+
     pub fn load_slal(&mut self, app: &tauri::AppHandle) -> Result<(), String> {
         let path = app
             .dialog()
@@ -302,28 +1310,172 @@ impl Package {
             prjct.scenes.len(),
             path.to_str().unwrap_or_default()
         );
-        prjct.update_to_latest_version()?;
+        prjct.migrate()?;
+        let repaired = prjct.repair_nav_links();
+        if repaired > 0 {
+            warn!("Removed {} broken nav links while converting", repaired);
+        }
+        let resolve_aliases = SETTINGS.lock().unwrap().import.resolve_race_aliases;
+        if resolve_aliases {
+            prjct.resolve_race_aliases();
+        }
         Ok(prjct)
     }
 
-    pub fn export(&self, app: &tauri::AppHandle) -> Result<(), std::io::Error> {
+    pub fn export(
+        &mut self,
+        app: &tauri::AppHandle,
+        validation: &ValidationSettings,
+        export: &ExportSettings,
+    ) -> Result<(), std::io::Error> {
+        let title = match self.estimate_export_size(validation, export) {
+            Ok(size) => format!("Export Project (about {})", format_size(size)),
+            Err(_) => "Export Project".into(),
+        };
         let path = app
             .dialog()
             .file()
-            .set_title("Export Project")
+            .set_title(title)
             .set_file_name(&self.pack_name)
             .blocking_pick_folder()
             .ok_or(std::io::Error::from(ErrorKind::NotFound))?
             .into_path()
             .map_err(|e| std::io::Error::new(ErrorKind::Other, e))?;
 
-        self.build(path)
+        // An invalid project is rejected by the build, don't bother looking for stale files
+        let valid = !self
+            .validate(validation)
+            .iter()
+            .any(|issue| issue.severity == Severity::Error);
+        let stale = if valid {
+            self.stale_export_files(&path)
+                .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?
+        } else {
+            vec![]
+        };
+        let clean = if export.clean || stale.is_empty() {
+            export.clean
+        } else {
+            app
+                .dialog()
+                .message(format!(
+                    "The export folder contains files of this pack which the current project no longer produces:\n{}\n\nDelete them?",
+                    stale
+                        .iter()
+                        .map(|file| file.strip_prefix(&path).unwrap_or(file).display().to_string())
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ))
+                .title("Export Project")
+                .buttons(MessageDialogButtons::YesNo)
+                .kind(MessageDialogKind::Warning)
+                .blocking_show()
+        };
+        let export = ExportSettings {
+            clean,
+            ..export.clone()
+        };
+        self.build(path, validation, &export)?;
+        let exported: Vec<NanoID> = self
+            .scenes
+            .values()
+            .filter(|scene| !scene.has_warnings && !scene.stages.is_empty())
+            .map(|scene| scene.id.clone())
+            .collect();
+        self.remember_export(&exported);
+        Ok(())
+    }
+
+    /// Export the scenes matching the tag query as their own pack, see `scenes_matching_tags`. The export
+    /// hashes of the exported scenes are remembered like on a full export. Returns the number of scenes exported
+    pub fn export_by_tags(
+        &mut self,
+        tags: &[String],
+        match_all: bool,
+        app: &tauri::AppHandle,
+        validation: &ValidationSettings,
+        export: &ExportSettings,
+    ) -> Result<usize, std::io::Error> {
+        let mut selected = self.scenes_matching_tags(tags, match_all)?;
+        selected.export(app, validation, export)?;
+        for (id, hash) in selected.export_hashes {
+            if selected.scenes.contains_key(&id) {
+                self.export_hashes.insert(id, hash);
+            }
+        }
+        Ok(selected.scenes.len())
     }
 
-    pub fn build(&self, root_dir: PathBuf) -> Result<(), std::io::Error> {
+    /// Copy of this project holding only the scenes tagged with all or any of the given tags, ignoring case
+    pub fn scenes_matching_tags(&self, tags: &[String], match_all: bool) -> Result<Package, std::io::Error> {
+        let query: Vec<&str> = tags
+            .iter()
+            .map(|tag| tag.trim())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        if query.is_empty() {
+            return Err(std::io::Error::new(ErrorKind::InvalidInput, "No tags to filter scenes by"));
+        }
+        let matches = |scene: &Scene| {
+            let has_tag = |tag: &&str| scene.tags.iter().any(|t| t.eq_ignore_ascii_case(tag));
+            if match_all {
+                query.iter().all(has_tag)
+            } else {
+                query.iter().any(has_tag)
+            }
+        };
+        let selected = Package {
+            scenes: self
+                .scenes
+                .iter()
+                .filter(|(_, scene)| matches(scene))
+                .map(|(id, scene)| (id.clone(), scene.clone()))
+                .collect(),
+            ..self.clone()
+        };
+        if selected.scenes.is_empty() {
+            return Err(std::io::Error::new(
+                ErrorKind::NotFound,
+                format!("No scenes match the tags {}", query.join(", ")),
+            ));
+        }
+        info!(
+            "{} of {} scenes match the tags {}",
+            selected.scenes.len(),
+            self.scenes.len(),
+            query.join(", ")
+        );
+        Ok(selected)
+    }
+
+    pub fn build(
+        &self,
+        root_dir: PathBuf,
+        validation: &ValidationSettings,
+        export: &ExportSettings,
+    ) -> Result<(), std::io::Error> {
         println!("Compiling project {}", self.pack_name);
-        self.write_binary_file(&root_dir)?;
-        self.write_fnis_files(&root_dir)?;
+        self.validate_for_build(validation)?;
+        if self.export_name() != self.pack_name {
+            warn!("Pack {} is exported as {}", self.pack_name, self.export_name());
+        }
+        let ext = export
+            .registry_extension()
+            .map_err(|e| std::io::Error::new(ErrorKind::InvalidInput, e))?;
+        let exported = self.for_export();
+        match export.layout {
+            ExportLayout::Combined => exported.write_binary_file(&root_dir, ext)?,
+            ExportLayout::PerScene => exported.write_scene_binary_files(&root_dir, ext)?,
+        }
+        exported.write_index_file(&root_dir, export)?;
+        exported.write_fnis_files(&root_dir)?;
+        if export.clean {
+            self.remove_stale_export_files(&root_dir)?;
+        }
+        if export.builder_sidecar {
+            self.write_builder_sidecar(&root_dir, export.minify)
+                .map_err(|e| std::io::Error::new(ErrorKind::Other, e))?;
+        }
         info!(
             "Successfully compiled {}",
             root_dir.to_str().unwrap_or_default()
@@ -331,7 +1483,271 @@ impl Package {
         Ok(())
     }
 
-    pub fn import_offset(&mut self, app: &tauri::AppHandle) -> Result<(), String> {
+    /// Files an export with the given settings would write, without touching the disk. Paths use forward slashes.
+    /// Fails for projects the build would reject
+    pub fn export_plan(
+        &self,
+        validation: &ValidationSettings,
+        export: &ExportSettings,
+    ) -> Result<Vec<ExportFile>, String> {
+        self.validate_for_build(validation).map_err(|e| e.to_string())?;
+        let ext = export.registry_extension()?;
+        let exported = self.for_export();
+        let name = exported.export_name();
+        let file = |relative_path: String, kind| ExportFile { relative_path, kind };
+        let mut ret = vec![];
+        match export.layout {
+            ExportLayout::Combined => ret.push(file(
+                format!("SKSE/SexLab/Registry/{}.{}", name, ext),
+                ExportFileKind::Registry,
+            )),
+            ExportLayout::PerScene => {
+                for scene in exported.scenes_by_name() {
+                    if scene.has_warnings || scene.stages.is_empty() {
+                        continue;
+                    }
+                    ret.push(file(
+                        format!("SKSE/SexLab/Registry/{}/{}.{}", name, scene.file_name(), ext),
+                        ExportFileKind::Registry,
+                    ));
+                }
+            }
+        }
+        ret.push(file(
+            format!("SKSE/SexLab/Registry/{}.index.json", name),
+            ExportFileKind::Index,
+        ));
+        let mut fnis = vec![];
+        for racekey in exported.collect_fnis_events()?.into_keys() {
+            let path = exported.fnis_list_path(Path::new(""), racekey)?;
+            fnis.push(path.to_string_lossy().replace('\\', "/"));
+        }
+        fnis.sort();
+        ret.extend(fnis.into_iter().map(|path| file(path, ExportFileKind::FnisList)));
+        if export.builder_sidecar {
+            ret.push(file(format!("{}.builder.json", name), ExportFileKind::Sidecar));
+        }
+        Ok(ret)
+    }
+
+    /// Approximate number of bytes an export with the given settings writes. Registry files are sized
+    /// exactly, json files are serialized in memory and FNIS lists assume single byte line endings.
+    /// Fails for projects the build would reject, as those cannot be sized
+    pub fn estimate_export_size(
+        &self,
+        validation: &ValidationSettings,
+        export: &ExportSettings,
+    ) -> Result<u64, String> {
+        let errors = self
+            .validate(validation)
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .count();
+        if errors > 0 {
+            return Err(format!("Validation failed with {} errors", errors));
+        }
+        let exported = self.for_export();
+        let registry = match export.layout {
+            ExportLayout::Combined => exported.get_byte_size(),
+            ExportLayout::PerScene => exported
+                .scenes
+                .values()
+                .filter(|scene| !scene.has_warnings && !scene.stages.is_empty())
+                .map(|scene| {
+                    Package {
+                        scenes: HashMap::from([(scene.id.clone(), scene.clone())]),
+                        ..exported.clone()
+                    }
+                    .get_byte_size()
+                })
+                .sum(),
+        };
+        let index = exported.registry_index(export)?;
+        let index = if export.minify {
+            serde_json::to_vec(&index)
+        } else {
+            serde_json::to_vec_pretty(&index)
+        }
+        .map_err(|e| e.to_string())?
+        .len();
+        let fnis: usize = exported
+            .collect_fnis_events()?
+            .values()
+            .flat_map(|events| events.iter())
+            .map(|event| event.len() + 1)
+            .sum();
+        let sidecar = if export.builder_sidecar {
+            let metadata = self.builder_metadata();
+            if export.minify {
+                serde_json::to_vec(&metadata)
+            } else {
+                serde_json::to_vec_pretty(&metadata)
+            }
+            .map_err(|e| e.to_string())?
+            .len()
+        } else {
+            0
+        };
+        Ok((registry + index + fnis + sidecar) as u64)
+    }
+
+    fn validate_for_build(&self, validation: &ValidationSettings) -> Result<(), std::io::Error> {
+        let issues = self.validate(validation);
+        log_issues(&issues);
+        let errors = issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .count();
+        if errors > 0 {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Validation failed with {} errors", errors),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn export_changed(
+        &mut self,
+        app: &tauri::AppHandle,
+        validation: &ValidationSettings,
+        export: &ExportSettings,
+    ) -> Result<Vec<NanoID>, String> {
+        let path = app
+            .dialog()
+            .file()
+            .set_title("Export Changed Scenes")
+            .set_file_name(&self.pack_name)
+            .blocking_pick_folder()
+            .ok_or("No path to export to".to_string())?
+            .into_path()
+            .map_err(|e| e.to_string())?;
+        self.build_changed(path, validation, export)
+    }
+
+    /// Hash of the exported content of the whole project, identical content always hashes the same.
+    /// Combines the pack header with the content hash of every exported scene in id order
+    pub fn content_hash(&self) -> String {
+        let mut scenes: Vec<&Scene> = self
+            .scenes
+            .values()
+            .filter(|scene| !scene.has_warnings && !scene.stages.is_empty())
+            .collect();
+        scenes.sort_by(|a, b| a.id.0.cmp(&b.id.0));
+        let mut content = format!(
+            "{}\n{}\n{}\n{}\n",
+            self.pack_name, self.pack_author, self.pack_description, self.prefix_hash.0
+        );
+        for scene in scenes {
+            content += &format!("{}:{}\n", scene.id.0, scene.content_hash());
+        }
+        format!("{:016x}", fnv1a(content.as_bytes()))
+    }
+
+    /// Scenes whose content differs from the last export, including scenes never exported
+    pub fn scenes_changed_since_export(&self) -> Vec<NanoID> {
+        let mut ret: Vec<NanoID> = self
+            .scenes
+            .values()
+            .filter(|scene| self.export_hashes.get(&scene.id) != Some(&scene.content_hash()))
+            .map(|scene| scene.id.clone())
+            .collect();
+        ret.sort_by(|a, b| a.0.cmp(&b.0));
+        ret
+    }
+
+    /// Exported scenes whose content differs from the last export
+    pub fn changed_scenes(&self) -> Vec<NanoID> {
+        self.scenes_changed_since_export()
+            .into_iter()
+            .filter(|id| {
+                self.get_scene(id)
+                    .is_some_and(|scene| !scene.has_warnings && !scene.stages.is_empty())
+            })
+            .collect()
+    }
+
+    /// Export only the scenes changed since the last export and remember their hashes, returns the
+    /// exported scenes. The combined registry file holds every scene, so with that layout any
+    /// change rewrites all of it. FNIS lists and the index always cover the whole pack
+    pub fn build_changed(
+        &mut self,
+        root_dir: PathBuf,
+        validation: &ValidationSettings,
+        export: &ExportSettings,
+    ) -> Result<Vec<NanoID>, String> {
+        let changed = self.changed_scenes();
+        if changed.is_empty() {
+            info!("No scenes changed since the last export");
+            return Ok(changed);
+        }
+        match export.layout {
+            ExportLayout::Combined => self.build(root_dir, validation, export),
+            ExportLayout::PerScene => self.build_scenes(&root_dir, &changed, validation, export),
+        }
+        .map_err(|e| e.to_string())?;
+        self.remember_export(&changed);
+        info!("Exported {} changed scenes", changed.len());
+        Ok(changed)
+    }
+
+    /// Remember the content of the given scenes as exported, forgetting scenes which no longer exist
+    fn remember_export(&mut self, ids: &[NanoID]) -> () {
+        self.export_hashes
+            .retain(|id, _| self.scenes.contains_key(id));
+        for id in ids {
+            if let Some(scene) = self.scenes.get(id) {
+                self.export_hashes.insert(id.clone(), scene.content_hash());
+            }
+        }
+    }
+
+    fn build_scenes(
+        &self,
+        root_dir: &PathBuf,
+        ids: &[NanoID],
+        validation: &ValidationSettings,
+        export: &ExportSettings,
+    ) -> Result<(), std::io::Error> {
+        println!("Compiling {} scenes of project {}", ids.len(), self.pack_name);
+        self.validate_for_build(validation)?;
+        let exported = self.for_export();
+        let selected = Package {
+            scenes: exported
+                .scenes
+                .iter()
+                .filter(|(id, _)| ids.contains(id))
+                .map(|(id, scene)| (id.clone(), scene.clone()))
+                .collect(),
+            ..exported.clone()
+        };
+        let ext = export
+            .registry_extension()
+            .map_err(|e| std::io::Error::new(ErrorKind::InvalidInput, e))?;
+        selected.write_scene_binary_files(root_dir, ext)?;
+        exported.write_index_file(root_dir, export)?;
+        exported.write_fnis_files(root_dir)?;
+        if export.builder_sidecar {
+            self.write_builder_sidecar(root_dir, export.minify)
+                .map_err(|e| std::io::Error::new(ErrorKind::Other, e))?;
+        }
+        Ok(())
+    }
+
+    /// Copy of this project as it is exported, see `Scene::for_export`
+    fn for_export(&self) -> Package {
+        let mut ret = self.clone();
+        for scene in ret.scenes.values_mut() {
+            *scene = scene.for_export();
+        }
+        ret
+    }
+
+    pub fn import_offset(
+        &mut self,
+        app: &tauri::AppHandle,
+        round_to: Option<u32>,
+    ) -> Result<(), String> {
         let path = app
             .dialog()
             .file()
@@ -341,10 +1757,15 @@ impl Package {
             .ok_or("No path to load offsets from".to_string())?
             .into_path()
             .map_err(|e| e.to_string())?;
-        let file = fs::File::open(&path).map_err(|e| e.to_string())?;
-        let offsetfile: serde_yaml::Mapping =
-            serde_yaml::from_reader(BufReader::new(file)).map_err(|e| e.to_string())?;
+        let offsetfile = read_offset_file(&path)?;
+        self.import_offset_mapping(offsetfile, round_to)
+    }
 
+    pub fn import_offset_mapping(
+        &mut self,
+        offsetfile: serde_yaml::Mapping,
+        round_to: Option<u32>,
+    ) -> Result<(), String> {
         for (scene_id_v, stages_v) in offsetfile {
             if !stages_v.is_mapping() {
                 continue;
@@ -358,6 +1779,7 @@ impl Package {
                     stages_v
                         .as_mapping()
                         .ok_or(format!("Expected mapping in scene {}", scene_id))?,
+                    round_to,
                 )?;
             }
         }
@@ -365,28 +1787,155 @@ impl Package {
         Ok(())
     }
 
+    /// Import offsets into a single scene. The mapping may use the full offset format keyed by
+    /// scene id or only list the stages of the scene
+    pub fn import_scene_offset_mapping(
+        &mut self,
+        scene_id: &NanoID,
+        mapping: serde_yaml::Mapping,
+        round_to: Option<u32>,
+    ) -> Result<usize, String> {
+        let stages = match mapping.get(&scene_id.0.as_str().into()) {
+            Some(value) => value
+                .as_mapping()
+                .cloned()
+                .ok_or(format!("Expected mapping in scene {}", scene_id.0))?,
+            None => mapping,
+        };
+        self.get_scene_mut(scene_id)
+            .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?
+            .import_offset(&stages, round_to)
+    }
+
+    /// Import offsets for one scene from YAML text, e.g. pasted from the clipboard
+    pub fn import_offset_string(&mut self, scene_id: &NanoID, yaml: &str) -> Result<usize, String> {
+        let value: serde_yaml::Value =
+            serde_yaml::from_str(yaml).map_err(|e| format!("Invalid offset YAML: {}", e))?;
+        let mapping = match value {
+            serde_yaml::Value::Mapping(mapping) => mapping,
+            _ => return Err("Invalid offset YAML, expected a mapping of stage ids".into()),
+        };
+        self.import_scene_offset_mapping(scene_id, mapping, None)
+    }
+
+    /// Import offsets for one scene from YAML text keyed by stage name rather than stage id
+    pub fn import_offset_string_by_stage_name(
+        &mut self,
+        scene_id: &NanoID,
+        yaml: &str,
+    ) -> Result<StageOffsetImportSummary, String> {
+        let value: serde_yaml::Value =
+            serde_yaml::from_str(yaml).map_err(|e| format!("Invalid offset YAML: {}", e))?;
+        let mapping = match value {
+            serde_yaml::Value::Mapping(mapping) => mapping,
+            _ => return Err("Invalid offset YAML, expected a mapping of stage names".into()),
+        };
+        self.get_scene_mut(scene_id)
+            .ok_or_else(|| format!("Invalid Scene ID: {}", scene_id.0))?
+            .import_offset_by_stage_name(&mapping)
+    }
+
+    pub fn import_offset_folder(
+        &mut self,
+        app: &tauri::AppHandle,
+        round_to: Option<u32>,
+    ) -> Result<OffsetImportSummary, String> {
+        let path = app
+            .dialog()
+            .file()
+            .set_title("Import Offset Folder")
+            .blocking_pick_folder()
+            .ok_or("No folder to load offsets from".to_string())?
+            .into_path()
+            .map_err(|e| e.to_string())?;
+        self.import_offset_dir(&path, round_to)
+    }
+
+    /// Import a folder of offset files, each named after the scene it applies to
+    pub fn import_offset_dir(
+        &mut self,
+        dir: &Path,
+        round_to: Option<u32>,
+    ) -> Result<OffsetImportSummary, String> {
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)
+            .map_err(|e| e.to_string())?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .extension()
+                        .map_or(false, |ext| ext == "yaml" || ext == "yml")
+            })
+            .collect();
+        files.sort();
+
+        let mut summary = OffsetImportSummary::default();
+        for path in files {
+            let file_name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let stem = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default();
+            let scene_id = match self.find_scene_by_file_name(stem) {
+                Some(id) => id,
+                None => {
+                    warn!("No scene matches offset file {}", file_name);
+                    summary.unmatched.push(file_name);
+                    continue;
+                }
+            };
+            let res = read_offset_file(&path)
+                .and_then(|mapping| self.import_scene_offset_mapping(&scene_id, mapping, round_to));
+            match res {
+                Ok(_) => {
+                    info!("Imported offsets from {} into Scene {}", file_name, scene_id.0);
+                    summary.matched.push(file_name);
+                }
+                Err(e) => {
+                    error!("Failed to import offsets from {}: {}", file_name, e);
+                    summary.failed.push(format!("{}: {}", file_name, e));
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Match a file name against scene ids and names, optionally prefixed with the pack name
+    fn find_scene_by_file_name(&self, stem: &str) -> Option<NanoID> {
+        let stem = stem.to_lowercase();
+        let unprefixed = stem
+            .strip_prefix(&format!("{}_", self.pack_name.to_lowercase()))
+            .filter(|_| !self.pack_name.is_empty())
+            .unwrap_or(&stem);
+        self.scenes
+            .values()
+            .find(|scene| {
+                let name = scene.name.to_lowercase();
+                scene.id.0 == stem || (!name.is_empty() && (name == stem || name == unprefixed))
+            })
+            .map(|scene| scene.id.clone())
+    }
+
     fn set_project_name_from_path(&mut self, path: &PathBuf) -> () {
-        self.pack_name = String::from(
-            path.file_name() // ...\\{project.slsb.json}
-                .and_then(|name| name.to_str())
-                .and_then(|str| {
-                    let ret = &str[0..str.find(".slsb.json").unwrap_or(str.len())];
-                    Some(ret)
-                })
-                .unwrap_or_default(),
-        );
+        self.pack_name = project_name_from_path(path);
+    }
+
+    /// Name of the pack's files and folders in the export, the sanitized pack name or the prefix if there is none
+    pub(super) fn export_name(&self) -> String {
+        let name = sanitize_filename(&self.pack_name);
+        if name.is_empty() {
+            self.prefix_hash.0.clone()
+        } else {
+            name
+        }
     }
 
-    fn write_binary_file(&self, root_dir: &PathBuf) -> Result<(), std::io::Error> {
+    fn write_binary_file(&self, root_dir: &PathBuf, ext: &str) -> Result<(), std::io::Error> {
         let target_dir = root_dir.join("SKSE\\SexLab\\Registry\\");
-        let project_name = format!(
-            "{}.slr",
-            if self.pack_name.is_empty() {
-                &self.prefix_hash.0
-            } else {
-                &self.pack_name
-            }
-        );
+        let project_name = format!("{}.{}", self.export_name(), ext);
         let mut buf: Vec<u8> = Vec::new();
         buf.reserve(self.get_byte_size());
         info!(
@@ -401,27 +1950,35 @@ impl Package {
         Ok(())
     }
 
-    fn write_fnis_files(&self, root_dir: &PathBuf) -> Result<(), std::io::Error> {
+    /// FNIS list lines of every exported animation event, grouped by race key
+    fn collect_fnis_events(&self) -> Result<HashMap<&str, Vec<String>>, String> {
         let mut events: HashMap<&str, Vec<String>> = HashMap::new(); // map<RaceKey, Lines[]>
         let mut control: HashSet<&str> = HashSet::from(["__BLANK__", "__DEFAULT__"]);
         for (_, scene) in &self.scenes {
             if scene.has_warnings {
                 continue;
             }
-            assert_eq!(
-                scene
-                    .stages
-                    .first()
-                    .expect(&format!("Scene {} has 0 Stages", scene.id.0))
-                    .positions
-                    .len(),
-                scene.positions.len()
-            );
+            if scene.stages.is_empty() {
+                return Err(format!("Scene {} has 0 Stages", scene.id.0));
+            }
             for stage in &scene.stages {
+                if stage.positions.len() != scene.positions.len() {
+                    return Err(format!(
+                        "Stage {} of Scene {} has {} positions, expected {}",
+                        stage.id.0,
+                        scene.id.0,
+                        stage.positions.len(),
+                        scene.positions.len()
+                    ));
+                }
                 for i in 0..stage.positions.len() {
                     let stage_position = &stage.positions[i];
                     let scene_position = &scene.positions[i];
-                    let event = &stage_position.event[0];
+                    let event = stage_position.event.first().ok_or(format!(
+                        "Position {} of Stage {} has no event",
+                        i + 1,
+                        stage.id.0
+                    ))?;
                     if control.contains(event.as_str()) {
                         continue;
                     }
@@ -429,7 +1986,7 @@ impl Package {
                     let lines = make_fnis_lines(
                         &stage_position.event,
                         &self.prefix_hash.0,
-                        stage.extra.fixed_len > 0.0,
+                        stage.extra.export_len() > 0.0,
                         &stage_position
                             .anim_obj
                             .split(',')
@@ -465,50 +2022,319 @@ impl Package {
                 }
             }
         }
-        info!("---------------------------------------------------------");
-        for (racekey, anim_events) in events {
-            let target_folder = map_race_to_folder(racekey)
-                .expect(format!("Cannot find folder for RaceKey {}", racekey).as_str());
-            let path = root_dir.join(format!(
-                "meshes\\actors\\{}\\animations\\{}",
-                target_folder, self.pack_name
-            ));
-            let crt = &target_folder[target_folder
-                .find('\\')
-                .and_then(|w| Some(w + 1))
-                .unwrap_or(0)..];
-            fs::create_dir_all(&path)?;
-
-            let create = |file_path: PathBuf| -> Result<(), std::io::Error> {
-                let name = file_path.to_str().unwrap_or("NONE".into()).to_string();
-                let file = fs::File::create(file_path)?;
-                let mut file = BufWriter::new(file);
-                info!(
-                    "Adding {} lines to race {} |||||| file: {}",
-                    anim_events.len(),
-                    racekey,
-                    name
-                );
-                for anim_event in anim_events {
-                    writeln!(file, "{}", anim_event)?;
-                }
-                Ok(())
+        Ok(events)
+    }
+
+    /// Location of the FNIS list for the given race key
+    fn fnis_list_path(&self, root_dir: &Path, racekey: &str) -> Result<PathBuf, String> {
+        let pack_name = self.export_name();
+        let target_folder = map_race_to_folder(racekey)
+            .ok_or(format!("Cannot find folder for RaceKey {}", racekey))?;
+        let path = root_dir.join(format!(
+            "meshes\\actors\\{}\\animations\\{}",
+            target_folder, pack_name
+        ));
+        let crt = &target_folder[target_folder
+            .find('\\')
+            .and_then(|w| Some(w + 1))
+            .unwrap_or(0)..];
+        Ok(match crt {
+            "character" => path.join(format!("FNIS_{}_List.txt", pack_name)),
+            "canine" => match racekey {
+                "Canine" => path.join(format!("FNIS_{}_canine_List.txt", pack_name)),
+                "Dog" => path.join(format!("FNIS_{}_dog_List.txt", pack_name)),
+                _ => path.join(format!("FNIS_{}_wolf_List.txt", pack_name)),
+            },
+            _ => path.join(format!("FNIS_{}_{}_List.txt", pack_name, crt)),
+        })
+    }
+
+    /// Write every scene into its own registry file, each with the pack header
+    fn write_scene_binary_files(&self, root_dir: &PathBuf, ext: &str) -> Result<(), std::io::Error> {
+        let name = self.export_name();
+        let target_dir = root_dir.join(format!("SKSE\\SexLab\\Registry\\{}\\", name));
+        fs::create_dir_all(&target_dir)?;
+        for scene in self.scenes.values() {
+            if scene.has_warnings || scene.stages.is_empty() {
+                continue;
+            }
+            let single = Package {
+                scenes: HashMap::from([(scene.id.clone(), scene.clone())]),
+                ..self.clone()
             };
-            match crt {
-                "character" => create(path.join(format!("FNIS_{}_List.txt", self.pack_name))),
-                "canine" => match racekey {
-                    "Canine" => {
-                        create(path.join(format!("FNIS_{}_canine_List.txt", self.pack_name)))
-                    }
-                    "Dog" => create(path.join(format!("FNIS_{}_dog_List.txt", self.pack_name))),
-                    _ => create(path.join(format!("FNIS_{}_wolf_List.txt", self.pack_name))),
+            let mut buf: Vec<u8> = Vec::new();
+            buf.reserve(single.get_byte_size());
+            single.write_byte(&mut buf);
+            let file_name = scene.file_name();
+            if !file_name.starts_with(&format!("{}_", scene.name)) {
+                warn!("Scene {} ({}) is exported as {}.{}", scene.name, scene.id.0, file_name, ext);
+            }
+            let path = target_dir.join(format!("{}.{}", file_name, ext));
+            info!(
+                "Writing binary file for Scene {} with size {} at {}",
+                scene.id.0,
+                buf.len(),
+                path.display()
+            );
+            fs::File::create(path)?.write(&buf)?;
+        }
+        Ok(())
+    }
+
+    /// Index of the exported scenes next to the registry file, mapping scene ids to their name and tags
+    fn write_index_file(&self, root_dir: &PathBuf, export: &ExportSettings) -> Result<(), std::io::Error> {
+        let index = self
+            .registry_index(export)
+            .map_err(|e| std::io::Error::new(ErrorKind::InvalidInput, e))?;
+        let target_dir = root_dir.join("SKSE\\SexLab\\Registry\\");
+        fs::create_dir_all(&target_dir)?;
+        let path = target_dir.join(format!("{}.index.json", index.pack));
+        let writer = BufWriter::new(fs::File::create(&path)?);
+        if export.minify {
+            serde_json::to_writer(writer, &index)
+        } else {
+            serde_json::to_writer_pretty(writer, &index)
+        }
+        .map_err(|e| std::io::Error::new(ErrorKind::Other, e))?;
+        info!("Wrote index of {} scenes to {}", index.scenes.len(), path.display());
+        Ok(())
+    }
+
+    fn registry_index(&self, export: &ExportSettings) -> Result<RegistryIndex, String> {
+        let name = &self.export_name();
+        let ext = export.registry_extension()?;
+        let scenes: Vec<IndexEntry> = self
+            .scenes_by_name()
+            .into_iter()
+            .filter(|scene| !scene.has_warnings && !scene.stages.is_empty())
+            .map(|scene| IndexEntry {
+                id: scene.id.clone(),
+                name: scene.name.clone(),
+                tags: scene.tags.clone(),
+                file: match export.layout {
+                    ExportLayout::Combined => format!("{}.{}", name, ext),
+                    ExportLayout::PerScene => format!("{}/{}.{}", name, scene.file_name(), ext),
                 },
-                _ => create(path.join(format!("FNIS_{}_{}_List.txt", self.pack_name, crt))),
-            }?;
+            })
+            .collect();
+        Ok(RegistryIndex {
+            pack: name.clone(),
+            author: self.pack_author.clone(),
+            version: self.version,
+            priority: self.priority,
+            scenes,
+        })
+    }
+
+    fn write_fnis_files(&self, root_dir: &PathBuf) -> Result<(), std::io::Error> {
+        let invalid = |e| std::io::Error::new(ErrorKind::InvalidData, e);
+        let events = self.collect_fnis_events().map_err(invalid)?;
+        info!("---------------------------------------------------------");
+        for (racekey, anim_events) in events {
+            let file_path = self.fnis_list_path(root_dir, racekey).map_err(invalid)?;
+            if let Some(dir) = file_path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let name = file_path.to_str().unwrap_or("NONE".into()).to_string();
+            let file = fs::File::create(&file_path)?;
+            let mut file = BufWriter::new(file);
+            info!(
+                "Adding {} lines to race {} |||||| file: {}",
+                anim_events.len(),
+                racekey,
+                name
+            );
+            for anim_event in anim_events {
+                writeln!(file, "{}", anim_event)?;
+            }
         }
         info!("---------------------------------------------------------");
         Ok(())
     }
+
+    /// FNIS lists of this pack in an export directory which the current project would not write,
+    /// left over from removed scenes or races
+    pub fn stale_export_files(&self, root_dir: &Path) -> Result<Vec<PathBuf>, String> {
+        let exported = self.for_export();
+        let mut expected: HashSet<String> = HashSet::new();
+        for racekey in exported.collect_fnis_events()?.into_keys() {
+            expected.insert(normalize_path(&exported.fnis_list_path(root_dir, racekey)?));
+        }
+        let pack_name = self.export_name();
+        let prefix = format!("FNIS_{}_", pack_name).to_lowercase();
+        let mut ret = vec![];
+        let mut open = vec![root_dir.join("meshes").join("actors")];
+        while let Some(dir) = open.pop() {
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+                if path.is_dir() {
+                    open.push(path);
+                    continue;
+                }
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                let in_pack_folder = path
+                    .parent()
+                    .and_then(|dir| dir.file_name())
+                    .is_some_and(|dir| dir.to_string_lossy().eq_ignore_ascii_case(&pack_name));
+                if in_pack_folder
+                    && name.starts_with(&prefix)
+                    && name.ends_with("_list.txt")
+                    && !expected.contains(&normalize_path(&path))
+                {
+                    ret.push(path);
+                }
+            }
+        }
+        ret.sort();
+        Ok(ret)
+    }
+
+    fn remove_stale_export_files(&self, root_dir: &Path) -> Result<(), std::io::Error> {
+        let stale = self
+            .stale_export_files(root_dir)
+            .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?;
+        for path in stale {
+            info!("Removing stale export file {}", path.display());
+            fs::remove_file(&path)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct OffsetImportSummary {
+    pub matched: Vec<String>,
+    pub unmatched: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+/// Outcome of importing offsets keyed by stage name into a scene
+#[derive(Debug, Default, Serialize)]
+pub struct StageOffsetImportSummary {
+    /// Number of positions updated
+    pub applied: usize,
+    /// Stage names in the file without a matching stage
+    pub unmatched: Vec<String>,
+    /// Stages of the scene which received no offsets
+    pub missing: Vec<String>,
+}
+
+/// Name or tag of a scene or stage which is empty or only whitespace
+#[derive(Debug, Serialize)]
+pub struct TextIssue {
+    pub scene: NanoID,
+    pub stage: Option<NanoID>,
+    pub message: String,
+}
+
+/// Outcome of importing a scene order from a text file
+#[derive(Debug, Default, Serialize)]
+pub struct SceneOrderImportSummary {
+    /// Number of scenes placed by the file
+    pub matched: usize,
+    /// Lines of the file without a matching scene
+    pub unmatched: Vec<String>,
+}
+
+/// Outcome of applying a furniture offset profile to a project
+#[derive(Debug, Default, Serialize)]
+pub struct FurnitureProfileSummary {
+    pub scenes: usize,
+    pub positions: usize,
+    /// Scenes skipped as their actor count differs from the profile
+    pub mismatched: Vec<String>,
+}
+
+/// Replace the old tag with the new one, dropping duplicates this creates. Returns whether the tag was present
+fn rename_tag_in(tags: &mut Vec<String>, old: &str, new: &str) -> bool {
+    if !tags.iter().any(|tag| tag.eq_ignore_ascii_case(old)) {
+        return false;
+    }
+    let mut seen: HashSet<String> = HashSet::new();
+    *tags = tags
+        .drain(..)
+        .map(|tag| if tag.eq_ignore_ascii_case(old) { new.to_string() } else { tag })
+        .filter(|tag| seen.insert(tag.to_lowercase()))
+        .collect();
+    true
+}
+
+/// Comparable form of a path, export paths are built with windows separators
+fn normalize_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/").to_lowercase()
+}
+
+fn check_offset_bounds(min: &Offset, max: &Offset) -> Result<(), String> {
+    if min.x > max.x || min.y > max.y || min.z > max.z || min.r > max.r {
+        return Err("Lower offset bounds must not exceed the upper bounds".into());
+    }
+    Ok(())
+}
+
+/// File a project is written into before replacing the actual project file
+fn temp_path(path: &Path) -> PathBuf {
+    let mut ret = path.as_os_str().to_owned();
+    ret.push(".tmp");
+    ret.into()
+}
+
+/// Copy of the previously saved project, kept when saving over it
+fn backup_path(path: &Path) -> PathBuf {
+    let mut ret = path.as_os_str().to_owned();
+    ret.push(".bak");
+    ret.into()
+}
+
+/// Reader forwarding the percentage of bytes consumed to a callback
+struct ProgressReader<R, F> {
+    inner: R,
+    read: u64,
+    total: u64,
+    percent: u8,
+    on_progress: F,
+}
+
+impl<R: std::io::Read, F: FnMut(u8)> std::io::Read for ProgressReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n as u64;
+        if self.total > 0 {
+            let percent = (self.read * 100 / self.total).min(100) as u8;
+            if percent > self.percent {
+                self.percent = percent;
+                (self.on_progress)(percent);
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Find a readable copy of a damaged project, preferring an unfinished save over the backup
+fn find_recovery(path: &Path) -> Option<(PathBuf, Package)> {
+    for candidate in [temp_path(path), backup_path(path)] {
+        if !candidate.is_file() {
+            continue;
+        }
+        match fs::File::open(&candidate)
+            .map_err(|e| e.to_string())
+            .and_then(Package::from_file)
+        {
+            Ok(package) => return Some((candidate, package)),
+            Err(e) => warn!("Recovery file {} is not usable: {}", candidate.display(), e),
+        }
+    }
+    None
+}
+
+fn read_offset_file(path: &Path) -> Result<serde_yaml::Mapping, String> {
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    serde_yaml::from_reader(BufReader::new(file)).map_err(|e| e.to_string())
 }
 
 impl EncodeBinary for Package {
@@ -516,6 +2342,7 @@ impl EncodeBinary for Package {
         self.version.get_byte_size()
             + self.pack_name.get_byte_size()
             + self.pack_author.get_byte_size()
+            + self.pack_description.get_byte_size()
             + self.prefix_hash.get_byte_size()
             + self
                 .scenes
@@ -530,6 +2357,8 @@ impl EncodeBinary for Package {
         self.version.write_byte(buf);
         self.pack_name.write_byte(buf);
         self.pack_author.write_byte(buf);
+        // v5
+        self.pack_description.write_byte(buf);
         self.prefix_hash.write_byte(buf);
         buf.extend_from_slice(&(self.scenes.len() as u64).to_be_bytes());
         self.scenes
@@ -538,3 +2367,52 @@ impl EncodeBinary for Package {
             .for_each(|(_, scene)| scene.write_byte(buf));
     }
 }
+
+fn project_name_from_path(path: &Path) -> String {
+    String::from(
+        path.file_name() // ...\\{project.slsb.json}
+            .and_then(|name| name.to_str())
+            .and_then(|str| {
+                let ret = &str[0..str.find(".slsb.json").unwrap_or(str.len())];
+                Some(ret)
+            })
+            .unwrap_or_default(),
+    )
+}
+
+/// Byte count in the largest unit keeping it above one, e.g. `1.5 MB`
+fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "KB", "MB"] {
+        if size < 1024.0 {
+            return match unit {
+                "B" => format!("{} B", bytes),
+                _ => format!("{:.1} {}", size, unit),
+            };
+        }
+        size /= 1024.0;
+    }
+    format!("{:.1} GB", size)
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { prev } else { prev + 1 };
+            prev = row[j + 1];
+            row[j + 1] = cost.min(row[j] + 1).min(prev + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// 64 bit FNV-1a, stable across builds unlike the std hasher
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 use super::serialize::{deserialize_vec_or_string, EncodeBinary};
 use crate::project::{
@@ -17,6 +18,9 @@ pub struct Position {
     pub climax: bool,
     #[serde(default)] // addition 2.0
     pub tags: Vec<String>,
+    // Builder only, offsets to switch to when the scene is set to the furniture type
+    #[serde(default)]
+    pub furniture_offsets: HashMap<String, Offset>,
 
     // Unused fields, but kept for compatibility
     #[serde(skip_serializing, default)]
@@ -40,6 +44,7 @@ impl Position {
             strip_data: reference.map_or_else(|| Stripping::default(), |p| p.strip_data.clone()),
             climax: false,
             tags: Default::default(),
+            furniture_offsets: reference.map_or_else(HashMap::new, |p| p.furniture_offsets.clone()),
             // Unused fields
             sex: Default::default(),
             race: "Human".into(),
@@ -49,6 +54,11 @@ impl Position {
         }
     }
 
+    /// A slot without any animation event assigned yet
+    pub fn is_empty(&self) -> bool {
+        self.event.iter().all(|event| event.trim().is_empty())
+    }
+
     pub fn import_offset(&mut self, yaml_obj: &serde_yaml::Mapping) -> Result<(), String> {
         let loc = yaml_obj[&"Location".into()]
             .as_sequence()
@@ -90,6 +100,11 @@ impl Position {
             submissive: self.extra.submissive,
             vampire: self.extra.vampire,
             dead: self.extra.dead,
+            skeleton: None,
+            voice: None,
+            start_offset: 0.0,
+            allow_sex_mismatch: false,
+            enabled: true,
         }
     }
 }
@@ -12,6 +12,25 @@ pub struct PositionInfo {
     pub submissive: bool,
     pub vampire: bool,
     pub dead: bool,
+    // Skeleton or behavior project a creature needs to play this position
+    #[serde(default)] // addition 2.1
+    pub skeleton: Option<String>,
+    // Voice set SexLab should use for the actor in this position
+    #[serde(default)] // addition 2.1
+    pub voice: Option<String>,
+    // Seconds this actor starts after the others when a stage begins
+    #[serde(default)] // addition 2.1
+    pub start_offset: f32,
+    // Silences the warning about a sex untypical for the race
+    #[serde(default)] // addition 2.1
+    pub allow_sex_mismatch: bool,
+    // Disabled positions are left out of the export in every stage
+    #[serde(default = "enabled_default")] // addition 2.1
+    pub enabled: bool,
+}
+
+fn enabled_default() -> bool {
+    true
 }
 
 impl EncodeBinary for PositionInfo {
@@ -19,7 +38,10 @@ impl EncodeBinary for PositionInfo {
         self.sex.get_byte_size() +
         self.race.get_byte_size() +
         size_of::<bool>() * 3 + // submissive, vampire, dead
-        size_of::<f32>() // scale
+        size_of::<f32>() + // scale
+        self.skeleton.clone().unwrap_or_default().get_byte_size() +
+        self.voice.clone().unwrap_or_default().get_byte_size() +
+        size_of::<f32>() // start_offset
     }
 
     fn write_byte(&self, buf: &mut Vec<u8>) -> () {
@@ -31,6 +53,12 @@ impl EncodeBinary for PositionInfo {
                 + (1 << 1) * self.vampire as u8
                 + (1 << 2) * self.dead as u8,
         );
+        // v5: empty if the position plays on any skeleton
+        self.skeleton.clone().unwrap_or_default().write_byte(buf);
+        // v5: empty to let SexLab pick a voice
+        self.voice.clone().unwrap_or_default().write_byte(buf);
+        // v5: delay before the actor starts animating
+        self.start_offset.write_byte(buf);
     }
 }
 
@@ -43,6 +71,11 @@ impl Default for PositionInfo {
             submissive: false,
             vampire: false,
             dead: false,
+            skeleton: None,
+            voice: None,
+            start_offset: 0.0,
+            allow_sex_mismatch: false,
+            enabled: true,
         }
     }
 }
@@ -0,0 +1,163 @@
+use log::info;
+use std::collections::HashMap;
+
+use super::{
+    define::{Node, Offset, Sex},
+    package::Package,
+    position::Position,
+    position_info::PositionInfo,
+    scene::Scene,
+    stage::{Extra, Stage},
+    NanoID,
+};
+
+const SAMPLE_PACK_NAME: &str = "Example Project";
+
+/// Actor of a sample scene and where it stands relative to the scene origin
+struct SampleActor {
+    race: &'static str,
+    sex: Sex,
+    submissive: bool,
+    offset: Offset,
+}
+
+impl Package {
+    /// Fill the package with fully formed example scenes. Known kinds are `basic`, two humanoid scenes,
+    /// and `creature`, a scene with a wolf. The project has no path, so saving it asks for a new file
+    pub fn create_sample_project(&mut self, kind: &str) -> Result<(), String> {
+        let scenes = match kind {
+            "basic" => vec![
+                sample_scene(
+                    "Example Duo",
+                    &["Sample", "Standing"],
+                    &[
+                        actor("Human", female(), true, 0.0, 0.0),
+                        actor("Human", male(), false, 0.0, -35.0),
+                    ],
+                    &["Intro", "Build Up", "Climax"],
+                ),
+                sample_scene(
+                    "Example Trio",
+                    &["Sample", "Kneeling"],
+                    &[
+                        actor("Human", female(), true, 0.0, 0.0),
+                        actor("Human", male(), false, 0.0, -40.0),
+                        actor("Human", male(), false, 0.0, 40.0),
+                    ],
+                    &["Intro", "Climax"],
+                ),
+            ],
+            "creature" => vec![sample_scene(
+                "Example Creature",
+                &["Sample", "Doggy"],
+                &[
+                    actor("Human", female(), true, 0.0, 0.0),
+                    actor("Wolf", male(), false, 0.0, -50.0),
+                ],
+                &["Intro", "Build Up", "Climax"],
+            )],
+            _ => return Err(format!("Unknown sample project {}, expected basic or creature", kind)),
+        };
+        *self = Package::new();
+        self.pack_name = SAMPLE_PACK_NAME.into();
+        self.pack_author = "Scene Builder".into();
+        self.pack_description =
+            "Example scenes shipped with the Scene Builder, save them under a new name before editing.".into();
+        for scene in scenes {
+            self.scenes.insert(scene.id.clone(), scene);
+        }
+        info!("Created {} sample project with {} scenes", kind, self.scenes.len());
+        Ok(())
+    }
+}
+
+/// Scene with one stage per name, played in order. The last stage is a timed climax
+fn sample_scene(name: &str, tags: &[&str], actors: &[SampleActor], stage_names: &[&str]) -> Scene {
+    let mut scene = Scene {
+        name: name.into(),
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        positions: actors
+            .iter()
+            .map(|actor| PositionInfo {
+                sex: actor.sex.clone(),
+                race: actor.race.into(),
+                submissive: actor.submissive,
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    };
+    let event_prefix: String = name.chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+    let last = stage_names.len() - 1;
+    scene.stages = stage_names
+        .iter()
+        .enumerate()
+        .map(|(i, stage_name)| Stage {
+            id: NanoID::new_nanoid(),
+            name: stage_name.to_string(),
+            positions: actors
+                .iter()
+                .enumerate()
+                .map(|(a, actor)| Position {
+                    event: vec![format!("SLSB_{}_A{}_S{}", event_prefix, a + 1, i + 1)],
+                    offset: actor.offset.clone(),
+                    climax: i == last,
+                    ..Position::new(None)
+                })
+                .collect(),
+            tags: scene.tags.clone(),
+            extra: Extra {
+                fixed_len: if i == last { 8000.0 } else { 0.0 },
+                nav_text: stage_name.to_string(),
+                looping: false,
+            },
+            enabled: true,
+            camera: None,
+            image: None,
+            notes: Default::default(),
+        })
+        .collect();
+    let mut graph = HashMap::new();
+    for (i, stage) in scene.stages.iter().enumerate() {
+        let node = Node {
+            dest: scene.stages.get(i + 1).map(|next| vec![next.id.clone()]).unwrap_or_default(),
+            x: 40.0 + 200.0 * i as f32,
+            ..Default::default()
+        };
+        graph.insert(stage.id.clone(), node);
+    }
+    scene.graph = graph;
+    scene.root = scene.stages[0].id.clone();
+    scene
+}
+
+fn actor(race: &'static str, sex: Sex, submissive: bool, x: f32, y: f32) -> SampleActor {
+    SampleActor {
+        race,
+        sex,
+        submissive,
+        offset: Offset {
+            x,
+            y,
+            z: 0.0,
+            // Actors in front of the origin turn around to face it
+            r: if y > 0.0 { 180.0 } else { 0.0 },
+        },
+    }
+}
+
+fn male() -> Sex {
+    Sex {
+        male: true,
+        female: false,
+        futa: false,
+    }
+}
+
+fn female() -> Sex {
+    Sex {
+        male: false,
+        female: true,
+        futa: false,
+    }
+}
@@ -1,15 +1,27 @@
 use log::warn;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, vec};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    time::SystemTime,
+    vec,
+};
 
 use super::{
-    define::{FurnitureData, Node},
+    define::{FurnitureData, Node, Offset, Sex},
+    export_preview::scene_preview,
+    package::{fnv1a, StageOffsetImportSummary},
+    position::Position,
     position_info::PositionInfo,
+    sanitize_filename,
     serialize::EncodeBinary,
     stage::Stage,
     NanoID,
 };
 
+/// Typical time SexLab spends in a looping stage before advancing
+pub const LOOPING_STAGE_SECONDS: f32 = 15.0;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Scene {
     pub id: NanoID,
@@ -27,6 +39,67 @@ pub struct Scene {
     pub positions: Vec<PositionInfo>,
     #[serde(default)] // addition 1.1
     pub has_warnings: bool,
+    // Silences the warning about all actors sharing one creature race
+    #[serde(default)] // addition 2.1
+    pub allow_same_race: bool,
+    // Tags every actor needs to be cast into this scene, e.g. "submissive"
+    #[serde(default)] // addition 2.1
+    pub required_tags: Vec<String>,
+
+    // Builder only metadata, not part of the SexLab export
+    #[serde(default)]
+    pub image: Option<PathBuf>,
+    #[serde(default)]
+    pub notes: String,
+    // Time of the last save, scenes from before this field existed count as never modified
+    #[serde(default = "modified_default")]
+    pub modified: SystemTime,
+}
+
+fn modified_default() -> SystemTime {
+    SystemTime::UNIX_EPOCH
+}
+
+/// Navigation graph in the nodes and edges shape force-directed graph libraries consume
+#[derive(Debug, Serialize)]
+struct NavJson<'a> {
+    name: &'a str,
+    nodes: Vec<NavJsonNode<'a>>,
+    edges: Vec<NavJsonEdge<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct NavJsonNode<'a> {
+    id: &'a str,
+    name: String,
+    root: bool,
+    climax: bool,
+    /// Layered layout, stages one link further from the root are one column further right
+    x: f32,
+    y: f32,
+}
+
+#[derive(Debug, Serialize)]
+struct NavJsonEdge<'a> {
+    source: &'a str,
+    target: &'a str,
+    /// SexLab picks branches evenly, so all links weigh the same
+    weight: f32,
+    label: &'a str,
+    condition: Option<&'a str>,
+}
+
+const NAV_LAYOUT_COLUMN: f32 = 200.0;
+const NAV_LAYOUT_ROW: f32 = 100.0;
+
+/// Health of a scene's navigation graph, stage ids are listed in stage order
+#[derive(Debug, Default, Serialize)]
+pub struct NavAnalysis {
+    /// Stages playback starting at the root never enters
+    pub unreachable: Vec<NanoID>,
+    /// Stages without outgoing links which have no climax to end the scene on
+    pub dead_ends: Vec<NanoID>,
+    pub cycles: Vec<Vec<NanoID>>,
 }
 
 impl Scene {
@@ -50,8 +123,976 @@ impl Scene {
         None
     }
 
-    pub fn import_offset(&mut self, yaml_obj: &serde_yaml::Mapping) -> Result<(), String> {
+    /// Set the stage the scene starts with, None clears it
+    pub fn set_start_stage(&mut self, stage_id: Option<NanoID>) -> Result<(), String> {
+        match stage_id {
+            Some(id) => {
+                if self.get_stage(&id).is_none() {
+                    return Err(format!("Scene {} has no stage with id {}", self.name, id.0));
+                }
+                self.root = id;
+            }
+            None => self.root = NanoID::default(),
+        }
+        Ok(())
+    }
+
+    /// Reorder stages to match the given ids. Unknown ids are ignored and stages
+    /// missing from the list keep their relative order at the end
+    pub fn reorder_stages(&mut self, ordered_ids: &[NanoID]) -> () {
+        let mut remaining = std::mem::take(&mut self.stages);
+        for id in ordered_ids {
+            if let Some(i) = remaining.iter().position(|stage| &stage.id == id) {
+                self.stages.push(remaining.remove(i));
+            } else {
+                warn!("Scene {} has no stage with id {}", self.id.0, id.0);
+            }
+        }
+        self.stages.append(&mut remaining);
+    }
+
+    /// Merge the stage `remove` into `keep`: nav links into `remove` lead to `keep` instead, links out of `remove`
+    /// are added to `keep` and `remove` is deleted. Both need the same number of positions.
+    /// Returns the number of nav links redirected
+    pub fn merge_stages(&mut self, keep: &NanoID, remove: &NanoID) -> Result<usize, String> {
+        if keep == remove {
+            return Err("Can't merge a stage with itself".into());
+        }
+        let keep_stage = self
+            .get_stage(keep)
+            .ok_or_else(|| format!("Scene {} has no stage with id {}", self.name, keep.0))?;
+        let remove_stage = self
+            .get_stage(remove)
+            .ok_or_else(|| format!("Scene {} has no stage with id {}", self.name, remove.0))?;
+        if keep_stage.positions.len() != remove_stage.positions.len() {
+            return Err(format!(
+                "Stage {} has {} positions, but Stage {} has {}",
+                keep_stage.name,
+                keep_stage.positions.len(),
+                remove_stage.name,
+                remove_stage.positions.len()
+            ));
+        }
+        let outgoing = self.graph.remove(remove).unwrap_or_default();
+        let mut redirected = 0;
+        for node in self.graph.values_mut() {
+            for dest in node.dest.iter_mut().filter(|dest| *dest == remove) {
+                *dest = keep.clone();
+                redirected += 1;
+            }
+            if let Some(condition) = node.conditions.remove(remove) {
+                node.conditions.entry(keep.clone()).or_insert(condition);
+            }
+        }
+        let node = self.graph.entry(keep.clone()).or_default();
+        node.dest.extend(outgoing.dest.into_iter().filter(|dest| dest != remove));
+        for (dest, condition) in outgoing.conditions {
+            node.conditions.entry(dest).or_insert(condition);
+        }
+        // Links between the two stages would now loop back onto the merged stage
+        let mut seen: HashSet<NanoID> = HashSet::new();
+        node.dest.retain(|dest| dest != keep && seen.insert(dest.clone()));
+        node.prune_conditions();
+        if &self.root == remove {
+            self.root = keep.clone();
+        }
+        self.stages.retain(|stage| &stage.id != remove);
+        Ok(redirected)
+    }
+
+    /// Rename all stages from a pattern, `{n}` is replaced by the 1-based stage index and
+    /// `{scene}` by the scene name
+    pub fn rename_stages(&mut self, pattern: &str) -> () {
+        for (i, stage) in self.stages.iter_mut().enumerate() {
+            stage.name = pattern
+                .replace("{n}", &(i + 1).to_string())
+                .replace("{scene}", &self.name);
+        }
+    }
+
+    /// Stage names used by more than one stage, ignoring case and untitled stages
+    pub fn duplicate_stage_names(&self) -> Vec<String> {
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut ret: Vec<String> = vec![];
+        for stage in &self.stages {
+            let name = stage.name.trim();
+            if name.is_empty() {
+                continue;
+            }
+            if !seen.insert(name.to_lowercase())
+                && !ret.iter().any(|other| other.eq_ignore_ascii_case(name))
+            {
+                ret.push(name.to_string());
+            }
+        }
+        ret
+    }
+
+    /// Append a number to every repeated stage name, the first stage keeps its name. Returns the number of stages renamed
+    pub fn dedupe_stage_names(&mut self) -> usize {
+        let mut used: HashSet<String> = self
+            .stages
+            .iter()
+            .map(|stage| stage.name.trim().to_lowercase())
+            .collect();
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut renamed = 0;
+        for stage in &mut self.stages {
+            let name = stage.name.trim().to_string();
+            if name.is_empty() || seen.insert(name.to_lowercase()) {
+                continue;
+            }
+            let mut n = 2;
+            while used.contains(&format!("{} {}", name, n).to_lowercase()) {
+                n += 1;
+            }
+            stage.name = format!("{} {}", name, n);
+            used.insert(stage.name.to_lowercase());
+            seen.insert(stage.name.to_lowercase());
+            renamed += 1;
+        }
+        renamed
+    }
+
+    /// Groups of two or more stages with matching positions, see `Stage::has_same_positions`
+    pub fn find_identical_stages(&self, tolerance: f32) -> Vec<Vec<NanoID>> {
+        let mut groups: Vec<Vec<&Stage>> = vec![];
+        for stage in &self.stages {
+            match groups
+                .iter_mut()
+                .find(|group| group[0].has_same_positions(stage, tolerance))
+            {
+                Some(group) => group.push(stage),
+                None => groups.push(vec![stage]),
+            }
+        }
+        groups
+            .into_iter()
+            .filter(|group| group.len() > 1)
+            .map(|group| group.iter().map(|stage| stage.id.clone()).collect())
+            .collect()
+    }
+
+    /// Switch the scene to a single furniture type. If `apply_offsets` is set, positions with
+    /// stored offsets for that type use them. Returns whether any such offsets exist
+    pub fn set_furniture(&mut self, furniture: &str, apply_offsets: bool) -> bool {
+        self.furniture.furni_types = vec![furniture.to_string()];
+        let mut has_offsets = false;
+        for position in self.stages.iter_mut().flat_map(|stage| stage.positions.iter_mut()) {
+            if let Some(offset) = position.furniture_offsets.get(furniture) {
+                has_offsets = true;
+                if apply_offsets {
+                    position.offset = offset.clone();
+                }
+            }
+        }
+        has_offsets
+    }
+
+    /// Reset the offset of one position in one stage, scale is part of the scene wide position info
+    pub fn reset_position_offset(
+        &mut self,
+        stage_id: &NanoID,
+        position: usize,
+        include_scale: bool,
+    ) -> Result<(), String> {
+        let scene_id = self.id.0.clone();
+        let stage = self
+            .get_stage_mut(stage_id)
+            .ok_or_else(|| format!("Scene {} has no stage with id {}", scene_id, stage_id.0))?;
+        let count = stage.positions.len();
+        stage
+            .positions
+            .get_mut(position)
+            .ok_or_else(|| {
+                format!(
+                    "Position index {} out of range, Stage {} has {} positions",
+                    position, stage.name, count
+                )
+            })?
+            .offset = Offset::default();
+        if include_scale {
+            if let Some(info) = self.positions.get_mut(position) {
+                info.scale = 1.0;
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the offset of the target position to the mirrored offset of the source position
+    pub fn mirror_position(&mut self, stage_id: &NanoID, source: usize, target: usize) -> Result<(), String> {
+        let scene_id = self.id.0.clone();
+        let stage = self
+            .get_stage_mut(stage_id)
+            .ok_or_else(|| format!("Scene {} has no stage with id {}", scene_id, stage_id.0))?;
+        let count = stage.positions.len();
+        if source >= count || target >= count {
+            return Err(format!(
+                "Position index out of range, Stage {} has {} positions",
+                stage.name, count
+            ));
+        }
+        if source == target {
+            return Err("Source and target position must differ".into());
+        }
+        stage.positions[target].offset = stage.positions[source].offset.mirrored();
+        Ok(())
+    }
+
+    pub fn reset_all_offsets(&mut self, include_scale: bool) -> () {
+        for stage in &mut self.stages {
+            for position in &mut stage.positions {
+                position.offset = Offset::default();
+            }
+        }
+        if include_scale {
+            for info in &mut self.positions {
+                info.scale = 1.0;
+            }
+        }
+    }
+
+    /// Swap two actor slots, in the scene's position info and in every stage
+    pub fn swap_positions(&mut self, a: usize, b: usize) -> Result<(), String> {
+        let count = self.positions.len();
+        if a >= count || b >= count {
+            return Err(format!(
+                "Position index out of range, Scene {} has {} positions",
+                self.name, count
+            ));
+        }
+        if let Some(stage) = self.stages.iter().find(|stage| stage.positions.len() != count) {
+            return Err(format!(
+                "Stage {} has {} positions, expected {}",
+                stage.name,
+                stage.positions.len(),
+                count
+            ));
+        }
+        self.positions.swap(a, b);
+        for stage in &mut self.stages {
+            stage.positions.swap(a, b);
+        }
+        Ok(())
+    }
+
+    /// "Stage N" with N one past the highest stage number in use, suffixed if the name is taken
+    pub fn next_stage_name(&self) -> String {
+        let highest = self
+            .stages
+            .iter()
+            .filter_map(|stage| stage.name.strip_prefix("Stage ")?.trim().parse::<usize>().ok())
+            .max()
+            .unwrap_or(0);
+        let name = format!("Stage {}", highest.max(self.stages.len()) + 1);
+        let taken = |name: &str| self.stages.iter().any(|stage| stage.name == name);
+        if !taken(&name) {
+            return name;
+        }
+        (2..)
+            .map(|i| format!("{} ({})", name, i))
+            .find(|it| !taken(it))
+            .unwrap()
+    }
+
+    /// Estimated playtime in seconds along the path of `simulate_playback`, every stage counted once.
+    /// Looping stages play for as long as SexLab's stage timer, assumed to be `LOOPING_STAGE_SECONDS`
+    pub fn estimated_duration(&self) -> f32 {
+        self.simulate_playback(self.stages.len())
+            .iter()
+            .filter_map(|id| self.get_stage(id))
+            .map(|stage| {
+                if stage.extra.export_len() > 0.0 {
+                    stage.extra.export_len() / 1000.0
+                } else {
+                    LOOPING_STAGE_SECONDS
+                }
+            })
+            .sum()
+    }
+
+    /// Set the looping flag of stages, optionally leaving climax stages alone. Their fixed lengths are kept, a
+    /// stage without one keeps looping on SexLab's timer after the flag is cleared. Returns the number of stages changed
+    pub fn set_looping(&mut self, only_non_climax: bool, looping: bool) -> usize {
+        let mut changed = 0;
+        for stage in &mut self.stages {
+            if only_non_climax && stage.positions.iter().any(|position| position.climax) {
+                continue;
+            }
+            if stage.extra.looping == looping {
+                continue;
+            }
+            stage.extra.looping = looping;
+            changed += 1;
+        }
+        changed
+    }
+
+    /// Stages played when starting at the root and always taking the first link into an enabled stage,
+    /// ends at a stage without links, when a stage would repeat or after max_steps stages
+    pub fn simulate_playback(&self, max_steps: usize) -> Vec<NanoID> {
+        let mut ret = vec![];
+        let mut current = match self.get_stage(&self.root) {
+            Some(stage) if stage.enabled => &stage.id,
+            _ => return ret,
+        };
+        while ret.len() < max_steps && !ret.contains(current) {
+            ret.push(current.clone());
+            let next = self.graph.get(current).and_then(|node| {
+                node.dest
+                    .iter()
+                    .find(|dest| self.get_stage(dest).is_some_and(|stage| stage.enabled))
+            });
+            current = match next {
+                Some(next) => next,
+                None => break,
+            };
+        }
+        ret
+    }
+
+    /// Assign the sex of every position slot at once, shared by all stages of the scene
+    pub fn set_sexes(&mut self, sexes: Vec<Sex>) -> Result<(), String> {
+        if sexes.len() != self.positions.len() {
+            return Err(format!(
+                "Expected {} sexes for Scene {}, got {}",
+                self.positions.len(),
+                self.name,
+                sexes.len()
+            ));
+        }
+        for (i, (info, sex)) in self.positions.iter().zip(&sexes).enumerate() {
+            if !sex.male && !sex.female && !sex.futa {
+                return Err(format!("Position {} needs at least one sex", i + 1));
+            }
+            if sex.futa && info.race != "Human" {
+                return Err(format!("Position {}: Only humans can be futa", i + 1));
+            }
+        }
+        for (info, sex) in self.positions.iter_mut().zip(sexes) {
+            info.sex = sex;
+        }
+        Ok(())
+    }
+
+    /// Replace the actor tag requirements, tags are trimmed and duplicates dropped ignoring case
+    pub fn set_required_tags(&mut self, tags: Vec<String>) -> Result<(), String> {
+        let mut ret: Vec<String> = vec![];
+        for tag in tags {
+            let tag = tag.trim();
+            if tag.is_empty() {
+                return Err("Required tags must not be empty".into());
+            }
+            if !ret.iter().any(|other| other.eq_ignore_ascii_case(tag)) {
+                ret.push(tag.to_string());
+            }
+        }
+        self.required_tags = ret;
+        Ok(())
+    }
+
+    /// Remove nav links and graph nodes referring to stages which no longer exist, returns the number of links removed
+    pub fn repair_nav_links(&mut self) -> usize {
+        let stages: HashSet<NanoID> = self.stages.iter().map(|stage| stage.id.clone()).collect();
+        let mut removed = 0;
+        self.graph.retain(|id, node| {
+            if stages.contains(id) {
+                return true;
+            }
+            warn!(
+                "Scene {}: Removed graph node of missing Stage {} with {} links",
+                self.name,
+                id.0,
+                node.dest.len()
+            );
+            removed += node.dest.len();
+            false
+        });
+        for (id, node) in self.graph.iter_mut() {
+            node.dest.retain(|dest| {
+                if stages.contains(dest) {
+                    return true;
+                }
+                warn!(
+                    "Scene {}: Removed link from Stage {} to missing Stage {}",
+                    self.name, id.0, dest.0
+                );
+                removed += 1;
+                false
+            });
+            node.prune_conditions();
+        }
+        removed
+    }
+
+    /// Copy the positions of one stage onto another, keeping non-empty slots of the target unless overwriting
+    /// Returns the number of positions copied
+    pub fn copy_positions(&mut self, from: &NanoID, to: &NanoID, overwrite: bool) -> Result<usize, String> {
+        if from == to {
+            return Err("Source and target stage must differ".into());
+        }
+        let source = self
+            .get_stage(from)
+            .ok_or_else(|| format!("Invalid Stage ID: {}", from.0))?
+            .positions
+            .clone();
+        let target = self
+            .get_stage_mut(to)
+            .ok_or_else(|| format!("Invalid Stage ID: {}", to.0))?;
+        if source.len() != target.positions.len() {
+            return Err(format!(
+                "Stage {} has {} positions, expected {}",
+                target.name,
+                target.positions.len(),
+                source.len()
+            ));
+        }
+        let mut copied = 0;
+        for (position, copy) in target.positions.iter_mut().zip(source) {
+            if overwrite || position.is_empty() {
+                *position = copy;
+                copied += 1;
+            }
+        }
+        Ok(copied)
+    }
+
+    /// Stamp a position template onto the scene. Overwriting replaces the scene's position infos with the
+    /// template, filling only adds the template's positions the scene doesn't have yet. The actors are shared
+    /// by all stages, so every stage is resized to match. Returns the number of position infos taken from the template
+    pub fn apply_position_template(&mut self, template: &[PositionInfo], overwrite: bool) -> Result<usize, String> {
+        if template.is_empty() {
+            return Err("Position template is empty".into());
+        }
+        for stage in &mut self.stages {
+            if overwrite {
+                stage.positions.truncate(template.len());
+            }
+            while stage.positions.len() < template.len() {
+                let position = Position::new(stage.positions.last());
+                stage.positions.push(position);
+            }
+        }
+        let mut applied = 0;
+        for (i, info) in template.iter().enumerate() {
+            if i >= self.positions.len() {
+                self.positions.push(info.clone());
+            } else if overwrite {
+                self.positions[i] = info.clone();
+            } else {
+                continue;
+            }
+            applied += 1;
+        }
+        if overwrite {
+            self.compact_positions();
+        }
+        Ok(applied)
+    }
+
+    /// Add an actor of the given race and sex, appending a position with a default offset to every stage.
+    /// The race is expected to be a known race key. Fails if the scene would have more active actors than
+    /// `max_positions`. Returns the new actor count
+    pub fn add_actor(&mut self, race: String, sex: Sex, max_positions: usize) -> Result<usize, String> {
+        let active = self.positions.iter().filter(|info| info.enabled).count() + 1;
+        if active > max_positions {
+            return Err(format!(
+                "Scene {} would have {} active actors, at most {} are supported",
+                self.name, active, max_positions
+            ));
+        }
+        self.positions.push(PositionInfo {
+            sex,
+            race,
+            ..Default::default()
+        });
+        for stage in &mut self.stages {
+            stage.positions.push(Position::new(None));
+        }
+        Ok(self.positions.len())
+    }
+
+    /// Remove the actor at the given index from the scene and every stage, along with its offsets, events
+    /// and furniture offsets. The last actor can't be removed. Returns the new actor count
+    pub fn remove_actor(&mut self, index: usize) -> Result<usize, String> {
+        if index >= self.positions.len() {
+            return Err(format!(
+                "Invalid position {}, Scene {} has {} actors",
+                index,
+                self.name,
+                self.positions.len()
+            ));
+        }
+        if self.positions.len() == 1 {
+            return Err(format!("Scene {} needs at least one actor", self.name));
+        }
+        self.positions.remove(index);
+        for stage in &mut self.stages {
+            if index < stage.positions.len() {
+                stage.positions.remove(index);
+            }
+        }
+        Ok(self.positions.len())
+    }
+
+    /// Stages of a two actor scene whose second offset is not the mirror image of the first, see `Offset::mirrored`.
+    /// Rotations are compared as angles, so 0 and 360 degrees match
+    pub fn check_offset_symmetry(&self, tolerance: f32) -> Result<Vec<NanoID>, String> {
+        if self.positions.len() != 2 {
+            return Err(format!(
+                "Scene {} has {} actors, symmetry is only checked for two",
+                self.name,
+                self.positions.len()
+            ));
+        }
+        let ret = self
+            .stages
+            .iter()
+            .filter(|stage| match stage.positions.as_slice() {
+                [a, b] => {
+                    let mirrored = a.offset.mirrored();
+                    let angle = (mirrored.r - b.offset.r).rem_euclid(360.0);
+                    !Offset { r: b.offset.r, ..mirrored }.approx_eq(&b.offset, tolerance)
+                        || angle.min(360.0 - angle) > tolerance
+                }
+                _ => false,
+            })
+            .map(|stage| stage.id.clone())
+            .collect();
+        Ok(ret)
+    }
+
+    /// Stages and position indices with a NaN or infinite offset, including furniture specific offsets
+    pub fn non_finite_offsets(&self) -> Vec<(&Stage, usize)> {
+        let mut ret = vec![];
+        for stage in &self.stages {
+            for (i, position) in stage.positions.iter().enumerate() {
+                if !position.offset.is_finite()
+                    || !position.furniture_offsets.values().all(|offset| offset.is_finite())
+                {
+                    ret.push((stage, i));
+                }
+            }
+        }
+        ret
+    }
+
+    /// Hash of the exported content of this scene, the registry data followed by the events and animation
+    /// objects the FNIS lists are made of. Editor only data such as node positions or notes does not count
+    pub fn content_hash(&self) -> String {
+        let scene = self.for_export();
+        let fnis: Vec<(&[String], &str)> = scene
+            .stages
+            .iter()
+            .flat_map(|stage| stage.positions.iter())
+            .map(|position| (position.event.as_slice(), position.anim_obj.as_str()))
+            .collect();
+        let content = format!(
+            "{}\n{}",
+            serde_json::to_string(&scene_preview(&scene)).unwrap_or_default(),
+            serde_json::to_string(&fnis).unwrap_or_default()
+        );
+        format!("{:016x}", fnv1a(content.as_bytes()))
+    }
+
+    /// Whether both scenes hold the same data, ignoring the time they were last saved
+    pub fn same_content(&self, other: &Scene) -> bool {
+        let content = |scene: &Scene| {
+            let mut value = serde_json::to_value(scene).unwrap_or_default();
+            if let Some(map) = value.as_object_mut() {
+                map.remove("modified");
+            }
+            value
+        };
+        content(self) == content(other)
+    }
+
+    /// File name stem unique to this scene, the sanitized name followed by the id. The id keeps
+    /// the stem unique even when several names sanitize to the same text
+    pub fn file_name(&self) -> String {
+        let name = sanitize_filename(&self.name);
+        format!("{}_{}", name, self.id.0)
+    }
+
+    /// Copy of this scene as it is exported, disabled stages and positions are dropped
+    pub fn for_export(&self) -> Scene {
+        let mut ret = self.without_disabled_stages();
+        if ret.positions.iter().all(|info| info.enabled) {
+            return ret;
+        }
+        let enabled: Vec<bool> = ret.positions.iter().map(|info| info.enabled).collect();
+        let keep = |i: usize| enabled.get(i).copied().unwrap_or(true);
+        ret.positions.retain(|info| info.enabled);
+        for stage in &mut ret.stages {
+            let mut i = 0;
+            stage.positions.retain(|_| {
+                i += 1;
+                keep(i - 1)
+            });
+        }
+        ret
+    }
+
+    /// Copy of this scene without disabled stages or links into them
+    pub fn without_disabled_stages(&self) -> Scene {
+        let disabled: HashSet<&NanoID> = self
+            .stages
+            .iter()
+            .filter(|stage| !stage.enabled)
+            .map(|stage| &stage.id)
+            .collect();
+        let mut ret = self.clone();
+        if disabled.is_empty() {
+            return ret;
+        }
+        ret.stages.retain(|stage| stage.enabled);
+        ret.graph.retain(|id, _| !disabled.contains(id));
+        for node in ret.graph.values_mut() {
+            node.dest.retain(|dest| !disabled.contains(dest));
+            node.prune_conditions();
+        }
+        ret
+    }
+
+    /// Cycles in the navigation graph as sequences of stage ids, found through DFS back edges
+    pub fn find_nav_cycles(&self) -> Vec<Vec<NanoID>> {
+        fn visit<'a>(
+            scene: &'a Scene,
+            id: &'a NanoID,
+            path: &mut Vec<&'a NanoID>,
+            done: &mut HashSet<&'a NanoID>,
+            cycles: &mut Vec<Vec<NanoID>>,
+        ) {
+            done.insert(id);
+            path.push(id);
+            let dests = scene.graph.get(id).map(|node| node.dest.iter());
+            for dest in dests.into_iter().flatten() {
+                if let Some(start) = path.iter().position(|it| *it == dest) {
+                    cycles.push(path[start..].iter().map(|it| (*it).clone()).collect());
+                } else if !done.contains(dest) && scene.get_stage(dest).is_some() {
+                    visit(scene, dest, path, done, cycles);
+                }
+            }
+            path.pop();
+        }
+
+        let mut cycles = vec![];
+        let mut done = HashSet::new();
+        for stage in &self.stages {
+            if !done.contains(&stage.id) {
+                visit(self, &stage.id, &mut vec![], &mut done, &mut cycles);
+            }
+        }
+        cycles
+    }
+
+    /// Unreachable stages, dead ends and cycles of the navigation graph in one pass
+    pub fn analyze_nav_graph(&self) -> NavAnalysis {
+        let reachable = self.reachable_stages(false);
+        let mut ret = NavAnalysis {
+            cycles: self.find_nav_cycles(),
+            ..Default::default()
+        };
+        for stage in &self.stages {
+            if !reachable.contains(&stage.id) {
+                ret.unreachable.push(stage.id.clone());
+            }
+            if self.is_dead_end(stage) {
+                ret.dead_ends.push(stage.id.clone());
+            }
+        }
+        ret
+    }
+
+    /// Whether playback stops at the stage without a climax, as it has no links to other stages
+    pub fn is_dead_end(&self, stage: &Stage) -> bool {
+        let has_exit = self
+            .graph
+            .get(&stage.id)
+            .is_some_and(|node| node.dest.iter().any(|dest| self.get_stage(dest).is_some()));
+        !has_exit && !stage.positions.iter().any(|position| position.climax)
+    }
+
+    /// Ids of all stages reachable from the start stage by following navigation links
+    pub fn reachable_stages(&self, skip_disabled: bool) -> HashSet<NanoID> {
+        let is_walkable = |id: &NanoID| {
+            self.get_stage(id)
+                .is_some_and(|stage| stage.enabled || !skip_disabled)
+        };
+        let mut ret = HashSet::new();
+        let mut open = vec![self.root.clone()];
+        while let Some(id) = open.pop() {
+            if !is_walkable(&id) || !ret.insert(id.clone()) {
+                continue;
+            }
+            if let Some(node) = self.graph.get(&id) {
+                open.extend(node.dest.iter().cloned());
+            }
+        }
+        ret
+    }
+
+    /// Names of the stages using each number of actors, ordered by count. More than one entry means
+    /// the stages disagree on the actors of the scene
+    pub fn stage_actor_counts(&self) -> Vec<(usize, Vec<&str>)> {
+        let mut ret: Vec<(usize, Vec<&str>)> = vec![];
+        for stage in &self.stages {
+            let count = stage.positions.len();
+            match ret.iter_mut().find(|(other, _)| *other == count) {
+                Some((_, names)) => names.push(&stage.name),
+                None => ret.push((count, vec![&stage.name])),
+            }
+        }
+        ret.sort_by_key(|(count, _)| *count);
+        ret
+    }
+
+    /// Whether the scene has climax stages but playback starting at the root can reach none of them
+    pub fn has_unreachable_climax(&self) -> bool {
+        let climaxes: Vec<&NanoID> = self
+            .stages
+            .iter()
+            .filter(|stage| stage.enabled && stage.positions.iter().any(|position| position.climax))
+            .map(|stage| &stage.id)
+            .collect();
+        if climaxes.is_empty() {
+            return false;
+        }
+        let reachable = self.reachable_stages(true);
+        !climaxes.iter().any(|id| reachable.contains(*id))
+    }
+
+    /// Give the scene and all its stages fresh ids, updating the start stage and navigation graph.
+    /// References to stages outside the scene are kept as they are. Returns the number of ids replaced
+    pub fn reassign_ids(&mut self) -> usize {
+        let ids: HashMap<NanoID, NanoID> = self
+            .stages
+            .iter()
+            .map(|stage| (stage.id.clone(), NanoID::new_nanoid()))
+            .collect();
+        let map_id = |id: &NanoID| ids.get(id).cloned().unwrap_or_else(|| id.clone());
+        self.id = NanoID::new_nanoid();
+        for stage in &mut self.stages {
+            stage.id = map_id(&stage.id);
+        }
+        self.root = map_id(&self.root);
+        self.graph = self
+            .graph
+            .iter()
+            .map(|(id, node)| {
+                let mut node = node.clone();
+                node.dest = node.dest.iter().map(|dest| map_id(dest)).collect();
+                node.conditions = node
+                    .conditions
+                    .iter()
+                    .map(|(dest, condition)| (map_id(dest), condition.clone()))
+                    .collect();
+                (map_id(id), node)
+            })
+            .collect();
+        ids.len() + 1
+    }
+
+    /// Copy of this scene with all ids replaced by readable placeholders, keeping references intact
+    pub fn anonymized(&self) -> Scene {
+        let mut ids: HashMap<NanoID, NanoID> = HashMap::new();
+        for (i, stage) in self.stages.iter().enumerate() {
+            ids.insert(stage.id.clone(), NanoID(format!("stage{}", i + 1)));
+        }
+        let mut missing = 0;
+        let mut map_id = |id: &NanoID| -> NanoID {
+            if id.0.is_empty() {
+                return id.clone();
+            }
+            ids.entry(id.clone())
+                .or_insert_with(|| {
+                    missing += 1;
+                    NanoID(format!("missing{}", missing))
+                })
+                .clone()
+        };
+
+        let mut ret = self.clone();
+        ret.id = NanoID("scene".into());
+        for stage in &mut ret.stages {
+            stage.id = map_id(&stage.id);
+        }
+        ret.root = map_id(&self.root);
+        ret.graph = self
+            .graph
+            .iter()
+            .map(|(id, node)| {
+                let mut node = node.clone();
+                node.dest = node.dest.iter().map(|dest| map_id(dest)).collect();
+                node.conditions = node
+                    .conditions
+                    .iter()
+                    .map(|(dest, condition)| (map_id(dest), condition.clone()))
+                    .collect();
+                (map_id(id), node)
+            })
+            .collect();
+        ret
+    }
+
+    /// Drop position infos past the largest number of positions any stage uses, returns the number removed.
+    /// A scene without stages keeps its infos, as they describe the actors of stages yet to be created
+    pub fn compact_positions(&mut self) -> usize {
+        let used = match self.stages.iter().map(|stage| stage.positions.len()).max() {
+            Some(used) => used,
+            None => return 0,
+        };
+        let removed = self.positions.len().saturating_sub(used);
+        if removed > 0 {
+            warn!(
+                "Scene {}: Removing {} position infos not used by any stage",
+                self.id.0, removed
+            );
+            self.positions.truncate(used);
+        }
+        removed
+    }
+
+    /// TOML representation of this scene. TOML has no null and requires homogeneous arrays, so the
+    /// scene goes through a json value with empty optionals dropped before being written
+    pub fn to_toml(&self) -> Result<String, String> {
+        let value = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        toml::to_string_pretty(&without_nulls(value)).map_err(|e| e.to_string())
+    }
+
+    pub fn from_toml(text: &str) -> Result<Scene, String> {
+        toml::from_str(text).map_err(|e| format!("Invalid scene TOML: {}", e))
+    }
+
+    /// The navigation graph as nodes and edges json, laid out in columns by link distance from the root.
+    /// Stages the root doesn't reach share the column after the last reachable one
+    pub fn to_nav_json(&self) -> Result<String, String> {
+        let mut depth: HashMap<&NanoID, usize> = HashMap::new();
+        let mut open: std::collections::VecDeque<&NanoID> = Default::default();
+        if self.get_stage(&self.root).is_some() {
+            depth.insert(&self.root, 0);
+            open.push_back(&self.root);
+        }
+        while let Some(id) = open.pop_front() {
+            let next = depth[id] + 1;
+            for dest in self.graph.get(id).map(|node| node.dest.iter()).into_iter().flatten() {
+                if self.get_stage(dest).is_some() && !depth.contains_key(dest) {
+                    depth.insert(dest, next);
+                    open.push_back(dest);
+                }
+            }
+        }
+        let orphan_column = depth.values().max().map_or(0, |max| max + 1);
+        let mut rows: HashMap<usize, usize> = HashMap::new();
+        let nodes = self
+            .stages
+            .iter()
+            .enumerate()
+            .map(|(i, stage)| {
+                let column = depth.get(&stage.id).copied().unwrap_or(orphan_column);
+                let row = rows.entry(column).or_default();
+                let node = NavJsonNode {
+                    id: &stage.id.0,
+                    name: if stage.name.is_empty() {
+                        format!("Stage {}", i + 1)
+                    } else {
+                        stage.name.clone()
+                    },
+                    root: stage.id == self.root,
+                    climax: stage.positions.iter().any(|position| position.climax),
+                    x: column as f32 * NAV_LAYOUT_COLUMN,
+                    y: *row as f32 * NAV_LAYOUT_ROW,
+                };
+                *row += 1;
+                node
+            })
+            .collect();
+        let mut edges = vec![];
+        for stage in &self.stages {
+            let node = match self.graph.get(&stage.id) {
+                Some(node) => node,
+                None => continue,
+            };
+            for dest in &node.dest {
+                let target = match self.get_stage(dest) {
+                    Some(target) => target,
+                    None => continue,
+                };
+                edges.push(NavJsonEdge {
+                    source: &stage.id.0,
+                    target: &target.id.0,
+                    weight: 1.0,
+                    label: &target.extra.nav_text,
+                    condition: node.condition(dest).filter(|condition| !condition.is_empty()),
+                });
+            }
+        }
+        let graph = NavJson {
+            name: &self.name,
+            nodes,
+            edges,
+        };
+        serde_json::to_string_pretty(&graph).map_err(|e| e.to_string())
+    }
+
+    /// Graphviz representation of the stage graph, labeling edges with the navigation text of their target
+    /// and the condition of the link, if any
+    pub fn to_dot(&self) -> String {
+        let escape = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut ret = format!("digraph \"{}\" {{\n", escape(&self.name));
+        for (i, stage) in self.stages.iter().enumerate() {
+            let label = if stage.name.is_empty() {
+                format!("Stage {}", i + 1)
+            } else {
+                stage.name.clone()
+            };
+            ret += &format!(
+                "    \"{}\" [label=\"{}\"{}];\n",
+                stage.id.0,
+                escape(&label),
+                if stage.id == self.root { ", peripheries=2" } else { "" }
+            );
+        }
+        for stage in &self.stages {
+            let node = match self.graph.get(&stage.id) {
+                Some(node) => node,
+                None => continue,
+            };
+            for dest in &node.dest {
+                let label = self
+                    .get_stage(dest)
+                    .map(|target| match node.condition(dest) {
+                        Some(condition) if !condition.is_empty() => {
+                            format!("{} [{}]", target.extra.nav_text, condition)
+                        }
+                        _ => target.extra.nav_text.clone(),
+                    })
+                    .unwrap_or_default();
+                ret += &format!(
+                    "    \"{}\" -> \"{}\"{};\n",
+                    stage.id.0,
+                    dest.0,
+                    if label.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" [label=\"{}\"]", escape(&label))
+                    }
+                );
+            }
+        }
+        ret += "}\n";
+        ret
+    }
+
+    /// Import offsets of the stages listed in the mapping, optionally rounding the imported values to
+    /// `round_to` decimals. Returns the number of positions updated
+    pub fn import_offset(
+        &mut self,
+        yaml_obj: &serde_yaml::Mapping,
+        round_to: Option<u32>,
+    ) -> Result<usize, String> {
         let self_id = self.id.0.clone();
+        let mut applied = 0;
         for (scene_id_v, scene_obj) in yaml_obj {
             let scene_id = scene_id_v
                 .as_str()
@@ -62,14 +1103,58 @@ impl Scene {
                 warn!("Scene {} has no stage with id {}", self.id.0, scene_id);
                 continue;
             }
-            stage
-                .unwrap()
-                .import_offset(scene_obj.as_sequence().ok_or(format!(
-                    "Expecting sequence in scene {} for stage {}",
-                    self_id, scene_id
-                ))?)?;
+            let stage = stage.unwrap();
+            stage.import_offset(scene_obj.as_sequence().ok_or(format!(
+                "Expecting sequence in scene {} for stage {}",
+                self_id, scene_id
+            ))?)?;
+            if let Some(decimals) = round_to {
+                stage.round_offsets(decimals);
+            }
+            applied += stage.positions.len();
         }
-        Ok(())
+        Ok(applied)
+    }
+
+    /// Import offsets keyed by stage name instead of id, names are matched ignoring case. Every stage
+    /// sharing a name receives the same offsets
+    pub fn import_offset_by_stage_name(
+        &mut self,
+        yaml_obj: &serde_yaml::Mapping,
+    ) -> Result<StageOffsetImportSummary, String> {
+        let mut summary = StageOffsetImportSummary::default();
+        let mut imported: HashSet<NanoID> = HashSet::new();
+        for (name_v, stage_obj) in yaml_obj {
+            let name = name_v
+                .as_str()
+                .ok_or(format!("Expected Stage name for Scene {}", self.id.0))?;
+            let list = stage_obj.as_sequence().ok_or(format!(
+                "Expecting sequence in scene {} for stage {}",
+                self.id.0, name
+            ))?;
+            let key = name.trim().to_lowercase();
+            let mut matched = false;
+            for stage in &mut self.stages {
+                if stage.name.trim().to_lowercase() != key {
+                    continue;
+                }
+                stage.import_offset(list)?;
+                summary.applied += stage.positions.len();
+                imported.insert(stage.id.clone());
+                matched = true;
+            }
+            if !matched {
+                warn!("Scene {} has no stage named {}", self.id.0, name);
+                summary.unmatched.push(name.to_string());
+            }
+        }
+        summary.missing = self
+            .stages
+            .iter()
+            .filter(|stage| !imported.contains(&stage.id))
+            .map(|stage| stage.name.clone())
+            .collect();
+        Ok(summary)
     }
 
     pub fn update_to_latest_version(&mut self, old_version: u8) -> Result<&mut Self, String> {
@@ -91,6 +1176,23 @@ impl Scene {
     }
 }
 
+/// Strip null values, which TOML cannot represent. Absent fields deserialize back to None
+fn without_nulls(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => map
+            .into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| (key, without_nulls(value)))
+            .collect(),
+        serde_json::Value::Array(list) => list
+            .into_iter()
+            .filter(|value| !value.is_null())
+            .map(without_nulls)
+            .collect(),
+        value => value,
+    }
+}
+
 impl Default for Scene {
     fn default() -> Self {
         Self {
@@ -104,6 +1206,11 @@ impl Default for Scene {
             tags: Default::default(),
             positions: vec![PositionInfo::default(); 1], // Default to one position
             has_warnings: false,
+            allow_same_race: false,
+            required_tags: Default::default(),
+            image: None,
+            notes: Default::default(),
+            modified: SystemTime::now(),
         }
     }
 }
@@ -117,6 +1224,7 @@ impl EncodeBinary for Scene {
             + self.root.get_byte_size()
             + self.furniture.get_byte_size()
             + self.private.get_byte_size()
+            + self.required_tags.get_byte_size()
             + self.graph.get_byte_size()
     }
 
@@ -128,5 +1236,6 @@ impl EncodeBinary for Scene {
         self.graph.write_byte(buf);
         self.furniture.write_byte(buf);
         self.private.write_byte(buf);
+        self.required_tags.write_byte(buf);
     }
 }
@@ -1,6 +1,8 @@
 use serde::{Deserializer, de::{self}};
 use std::{collections::HashMap, fmt, vec};
 
+use crate::racekeys::get_race_keys_string;
+
 pub fn map_race_to_folder(race: &str) -> Result<String, ()> {
     match race {
         "Human" => Ok("character".into()),
@@ -52,6 +54,17 @@ pub fn map_race_to_folder(race: &str) -> Result<String, ()> {
     }
 }
 
+/// Behavior folders of all known races, the skeletons a position may require
+pub fn get_known_skeletons() -> Vec<String> {
+    let mut ret: Vec<String> = get_race_keys_string()
+        .iter()
+        .filter_map(|race| map_race_to_folder(race).ok())
+        .collect();
+    ret.sort();
+    ret.dedup();
+    ret
+}
+
 pub struct DeserializeVecOrString;
 impl<'de> de::Visitor<'de> for DeserializeVecOrString {
     type Value = Vec<String>;
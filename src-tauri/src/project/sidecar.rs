@@ -0,0 +1,103 @@
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+use tauri_plugin_dialog::DialogExt;
+
+use super::{package::Package, NanoID};
+
+/// Builder only data of a project, omitted from the registry file
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BuilderMetadata {
+    pub pack_name: String,
+    pub scenes: HashMap<NanoID, SceneMetadata>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneMetadata {
+    pub name: String,
+    pub tags: Vec<String>,
+    pub notes: String,
+    pub image: Option<PathBuf>,
+}
+
+impl Package {
+    pub fn builder_metadata(&self) -> BuilderMetadata {
+        BuilderMetadata {
+            pack_name: self.pack_name.clone(),
+            scenes: self
+                .scenes
+                .iter()
+                .map(|(id, scene)| {
+                    let metadata = SceneMetadata {
+                        name: scene.name.clone(),
+                        tags: scene.tags.clone(),
+                        notes: scene.notes.clone(),
+                        image: scene.image.clone(),
+                    };
+                    (id.clone(), metadata)
+                })
+                .collect(),
+        }
+    }
+
+    /// Write `<pack>.builder.json` into the export directory, next to the game files
+    pub fn write_builder_sidecar(&self, root_dir: &Path, minify: bool) -> Result<PathBuf, String> {
+        let path = root_dir.join(format!("{}.builder.json", self.export_name()));
+        let file = fs::File::create(&path).map_err(|e| e.to_string())?;
+        let writer = BufWriter::new(file);
+        if minify {
+            serde_json::to_writer(writer, &self.builder_metadata())
+        } else {
+            serde_json::to_writer_pretty(writer, &self.builder_metadata())
+        }
+        .map_err(|e| e.to_string())?;
+        info!("Wrote builder metadata to {}", path.display());
+        Ok(path)
+    }
+
+    pub fn import_builder_sidecar(&mut self, app: &tauri::AppHandle) -> Result<usize, String> {
+        let path = app
+            .dialog()
+            .file()
+            .set_title("Import Builder Metadata")
+            .add_filter("Builder Metadata", &["builder.json"])
+            .blocking_pick_file()
+            .ok_or("No path to load builder metadata from".to_string())?
+            .into_path()
+            .map_err(|e| e.to_string())?;
+        let metadata = read_builder_sidecar(&path)?;
+        Ok(self.apply_builder_metadata(metadata))
+    }
+
+    /// Restore builder data onto scenes with matching ids, returns the number of scenes updated
+    pub fn apply_builder_metadata(&mut self, metadata: BuilderMetadata) -> usize {
+        let mut applied = 0;
+        for (id, data) in metadata.scenes {
+            let scene = match self.get_scene_mut(&id) {
+                Some(scene) => scene,
+                None => {
+                    warn!("Builder metadata for unknown Scene {} ({})", data.name, id.0);
+                    continue;
+                }
+            };
+            scene.tags = data.tags;
+            scene.notes = data.notes;
+            scene.image = data.image;
+            applied += 1;
+        }
+        applied
+    }
+}
+
+pub fn read_builder_sidecar(path: &Path) -> Result<BuilderMetadata, String> {
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| format!("Invalid builder metadata {}: {}", path.display(), e))
+}
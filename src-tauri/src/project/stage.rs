@@ -1,9 +1,9 @@
 use serde::{Deserialize, Serialize};
-use std::vec;
+use std::{path::PathBuf, vec};
 
 use crate::project::scene::Scene;
 
-use super::{position::Position, serialize::EncodeBinary, NanoID};
+use super::{define::{CameraHint, Offset}, position::Position, serialize::EncodeBinary, NanoID};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Stage {
@@ -13,12 +13,64 @@ pub struct Stage {
     pub positions: Vec<Position>,
     pub tags: Vec<String>,
     pub extra: Extra,
+
+    // Disabled stages are kept in the project but left out of the export
+    #[serde(default = "enabled_default")] // addition 2.1
+    pub enabled: bool,
+    // Recommended camera placement, SexLab picks its own if none is given
+    #[serde(default)] // addition 2.1
+    pub camera: Option<CameraHint>,
+
+    // Builder only metadata, not part of the SexLab export
+    #[serde(default)]
+    pub image: Option<PathBuf>,
+    #[serde(default)] // addition 2.1
+    pub notes: String,
+}
+
+fn enabled_default() -> bool {
+    true
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Extra {
     pub fixed_len: f32,
     pub nav_text: String,
+    /// Loop the stage regardless of its fixed length, which is kept for when looping is turned off again
+    #[serde(default)] // addition 2.1
+    pub looping: bool,
+}
+
+impl Extra {
+    /// Fixed length in milliseconds SexLab plays the stage for, 0 if it loops
+    pub fn export_len(&self) -> f32 {
+        if self.looping {
+            0.0
+        } else {
+            self.fixed_len
+        }
+    }
+}
+
+/// Differences between two stages of the same scene, for the editor to highlight
+#[derive(Debug, Serialize, Clone)]
+pub struct StageDiff {
+    pub a: NanoID,
+    pub b: NanoID,
+    pub position_count: (usize, usize),
+    pub tags_only_a: Vec<String>,
+    pub tags_only_b: Vec<String>,
+    pub positions: Vec<PositionDiff>,
+}
+
+/// Differences of a single position slot, fields are None if they match
+#[derive(Debug, Serialize, Clone)]
+pub struct PositionDiff {
+    pub index: usize,
+    pub offset: Option<(Offset, Offset)>,
+    pub event: Option<(Vec<String>, Vec<String>)>,
+    pub tags_only_a: Vec<String>,
+    pub tags_only_b: Vec<String>,
 }
 
 impl Stage {
@@ -33,6 +85,10 @@ impl Stage {
             ),
             tags: parent_scene.tags.clone(),
             extra: Default::default(),
+            enabled: true,
+            camera: None,
+            image: None,
+            notes: Default::default(),
         }
     }
 
@@ -61,38 +117,110 @@ impl Stage {
         Ok(())
     }
 
+    /// Round the offsets of all positions to the given number of decimals, returns the number of values adjusted
+    pub fn round_offsets(&mut self, decimals: u32) -> usize {
+        self.positions
+            .iter_mut()
+            .map(|position| position.offset.round_to(decimals))
+            .sum()
+    }
+
     pub fn update_to_latest_version(&mut self, old_version: u8) -> Result<(), String> {
         for pos in &mut self.positions {
             pos.update_to_latest_version(old_version)?;
         }
         Ok(())
     }
+
+    /// Tags as written to the registry, lowercase and without whitespace
+    pub fn export_tags(&self) -> Vec<String> {
+        self.tags
+            .iter()
+            .map(|tag| {
+                tag.chars()
+                    .filter(|c| !c.is_whitespace())
+                    .collect::<String>()
+                    .to_lowercase()
+            })
+            .collect()
+    }
+
+    pub fn diff(&self, other: &Stage) -> StageDiff {
+        let positions = self
+            .positions
+            .iter()
+            .zip(other.positions.iter())
+            .enumerate()
+            .filter_map(|(i, (a, b))| {
+                let diff = PositionDiff {
+                    index: i,
+                    offset: (a.offset != b.offset).then(|| (a.offset.clone(), b.offset.clone())),
+                    event: (a.event != b.event).then(|| (a.event.clone(), b.event.clone())),
+                    tags_only_a: tag_difference(&a.tags, &b.tags),
+                    tags_only_b: tag_difference(&b.tags, &a.tags),
+                };
+                let unchanged = diff.offset.is_none()
+                    && diff.event.is_none()
+                    && diff.tags_only_a.is_empty()
+                    && diff.tags_only_b.is_empty();
+                (!unchanged).then_some(diff)
+            })
+            .collect();
+        StageDiff {
+            a: self.id.clone(),
+            b: other.id.clone(),
+            position_count: (self.positions.len(), other.positions.len()),
+            tags_only_a: tag_difference(&self.tags, &other.tags),
+            tags_only_b: tag_difference(&other.tags, &self.tags),
+            positions,
+        }
+    }
+
+    /// If both stages animate their actors the same way, ignoring names and ids
+    pub fn has_same_positions(&self, other: &Stage, tolerance: f32) -> bool {
+        self.positions.len() == other.positions.len()
+            && self
+                .positions
+                .iter()
+                .zip(other.positions.iter())
+                .all(|(a, b)| {
+                    a.event == b.event
+                        && a.anim_obj == b.anim_obj
+                        && a.climax == b.climax
+                        && a.offset.approx_eq(&b.offset, tolerance)
+                })
+    }
+}
+
+fn tag_difference(tags: &Vec<String>, other: &Vec<String>) -> Vec<String> {
+    tags.iter()
+        .filter(|tag| !other.contains(tag))
+        .cloned()
+        .collect()
 }
 
 impl EncodeBinary for Stage {
     fn get_byte_size(&self) -> usize {
         self.id.get_byte_size()
             + self.positions.get_byte_size()
-            + self.extra.fixed_len.get_byte_size()
+            + self.extra.export_len().get_byte_size()
             + self.extra.nav_text.get_byte_size()
             + self.tags.get_byte_size()
+            + self.camera.is_some().get_byte_size()
+            + self.camera.as_ref().map_or(0, |camera| camera.get_byte_size())
     }
 
     fn write_byte(&self, buf: &mut Vec<u8>) -> () {
         self.id.write_byte(buf);
         self.positions.write_byte(buf);
-        self.extra.fixed_len.write_byte(buf);
+        self.extra.export_len().write_byte(buf);
         self.extra.nav_text.write_byte(buf);
-        self.tags
-            .iter()
-            .map(|tag| {
-                tag.chars()
-                    .filter(|c| !c.is_whitespace())
-                    .collect::<String>()
-                    .to_lowercase()
-            })
-            .collect::<Vec<_>>()
-            .write_byte(buf);
+        self.export_tags().write_byte(buf);
+        // v5: whether a camera hint follows
+        self.camera.is_some().write_byte(buf);
+        if let Some(camera) = &self.camera {
+            camera.write_byte(buf);
+        }
     }
 }
 
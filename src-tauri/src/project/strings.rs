@@ -0,0 +1,97 @@
+use log::{info, warn};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{BufReader, BufWriter},
+    path::Path,
+};
+
+use super::{package::Package, NanoID};
+
+/// Outcome of applying translated strings to a project
+#[derive(Debug, Default, Serialize)]
+pub struct StringsImportSummary {
+    /// Number of strings replaced
+    pub applied: usize,
+    /// Keys of the file without a matching scene or stage
+    pub unknown: Vec<String>,
+}
+
+impl Package {
+    /// User facing texts of the project keyed by `<scene>.name`, `<scene>.<stage>.name` and
+    /// `<scene>.<stage>.nav_text`, the parts being ids. Sorted so templates diff well between versions
+    pub fn strings(&self) -> BTreeMap<String, String> {
+        let mut ret = BTreeMap::new();
+        for scene in self.scenes.values() {
+            ret.insert(format!("{}.name", scene.id.0), scene.name.clone());
+            for stage in &scene.stages {
+                ret.insert(format!("{}.{}.name", scene.id.0, stage.id.0), stage.name.clone());
+                ret.insert(
+                    format!("{}.{}.nav_text", scene.id.0, stage.id.0),
+                    stage.extra.nav_text.clone(),
+                );
+            }
+        }
+        ret
+    }
+
+    /// Write the strings of the project to a JSON file for translators, see `strings`
+    pub fn export_strings_template(&self, path: &Path) -> Result<usize, String> {
+        let strings = self.strings();
+        let file = fs::File::create(path).map_err(|e| e.to_string())?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &strings).map_err(|e| e.to_string())?;
+        info!("Wrote {} strings to {}", strings.len(), path.display());
+        Ok(strings.len())
+    }
+
+    /// Apply a translated strings file written by `export_strings_template`. Empty values keep the current text
+    pub fn import_strings(&mut self, path: &Path) -> Result<StringsImportSummary, String> {
+        let file = fs::File::open(path).map_err(|e| e.to_string())?;
+        let strings: BTreeMap<String, String> = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("Invalid strings file {}: {}", path.display(), e))?;
+        let mut summary = StringsImportSummary::default();
+        for (key, value) in strings {
+            let text = match self.string_mut(&key) {
+                Some(text) => text,
+                None => {
+                    warn!("No scene or stage matches the string {}", key);
+                    summary.unknown.push(key);
+                    continue;
+                }
+            };
+            if value.trim().is_empty() || *text == value {
+                continue;
+            }
+            *text = value;
+            summary.applied += 1;
+        }
+        info!(
+            "Applied {} strings from {}, {} unknown",
+            summary.applied,
+            path.display(),
+            summary.unknown.len()
+        );
+        Ok(summary)
+    }
+
+    fn string_mut(&mut self, key: &str) -> Option<&mut String> {
+        let parts: Vec<&str> = key.split('.').collect();
+        match parts.as_slice() {
+            [scene, "name"] => self
+                .get_scene_mut(&NanoID(scene.to_string()))
+                .map(|scene| &mut scene.name),
+            [scene, stage, field] => {
+                let stage = self
+                    .get_scene_mut(&NanoID(scene.to_string()))?
+                    .get_stage_mut(&NanoID(stage.to_string()))?;
+                match *field {
+                    "name" => Some(&mut stage.name),
+                    "nav_text" => Some(&mut stage.extra.nav_text),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,745 @@
+use log::{error, info, warn};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{
+    furniture::{is_builtin_furniture, is_known_furniture},
+    racekeys::{get_expected_gender, get_race_key_bytes, Gender},
+    settings::ValidationSettings,
+    voices::is_known_voice,
+};
+
+use super::{
+    package::Package, position::Position, position_info::PositionInfo, sanitize_filename, scene::Scene,
+    serialize::get_known_skeletons, stage::Stage, NanoID,
+};
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A single problem found in a project, locating the offending scene, stage and position
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub scene: NanoID,
+    pub stage: Option<NanoID>,
+    pub position: Option<usize>,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn warning(scene: &Scene, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            scene: scene.id.clone(),
+            stage: None,
+            position: None,
+            message,
+        }
+    }
+
+    fn error(scene: &Scene, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            ..Self::warning(scene, message)
+        }
+    }
+
+    fn at(mut self, stage: &NanoID, position: Option<usize>) -> Self {
+        self.stage = Some(stage.clone());
+        self.position = position;
+        self
+    }
+}
+
+impl Package {
+    pub fn validate(&self, settings: &ValidationSettings) -> Vec<ValidationIssue> {
+        let mut issues = vec![];
+        let known_skeletons = get_known_skeletons();
+        validate_scene_ids(self, &mut issues);
+        for scene in self.scenes_by_name() {
+            if scene.has_warnings {
+                issues.push(ValidationIssue::warning(
+                    scene,
+                    format!("Scene {} has warnings and will not be exported", scene.name),
+                ));
+                continue;
+            }
+            let enabled = scene.stages.iter().filter(|stage| stage.enabled).count();
+            if enabled < settings.min_stages {
+                issues.push(ValidationIssue::error(
+                    scene,
+                    format!(
+                        "Scene {} has {} enabled stages, at least {} are required",
+                        scene.name, enabled, settings.min_stages
+                    ),
+                ));
+            }
+            match scene.get_stage(&scene.root) {
+                Some(root) if !root.enabled => issues.push(ValidationIssue::error(
+                    scene,
+                    format!("Scene {}: Start stage {} is disabled", scene.name, root.name),
+                )),
+                None if enabled > 1 => issues.push(ValidationIssue::error(
+                    scene,
+                    format!("Scene {} has no start stage", scene.name),
+                )),
+                _ => {}
+            }
+            validate_disabled_stages(scene, &mut issues);
+            validate_stage_names(scene, &mut issues);
+            validate_file_name(scene, &mut issues);
+            validate_position_count(scene, &mut issues);
+            validate_events(scene, &mut issues);
+            validate_max_positions(scene, settings, &mut issues);
+            validate_nav_cycles(scene, &mut issues);
+            validate_dead_ends(scene, &mut issues);
+            if scene.has_unreachable_climax() {
+                issues.push(ValidationIssue::error(
+                    scene,
+                    format!("Scene {}: No climax stage is reachable from the start stage", scene.name),
+                ));
+            }
+            validate_finite_offsets(scene, &mut issues);
+            validate_camera_hints(scene, &mut issues);
+            validate_offsets(scene, settings, &mut issues);
+            validate_conditions(scene, &mut issues);
+            validate_furniture(scene, &mut issues);
+            validate_furniture_offsets(scene, &mut issues);
+            validate_race_keys(scene, &mut issues);
+            validate_same_race(scene, &mut issues);
+            validate_sex_race_consistency(scene, &mut issues);
+            validate_skeletons(scene, &known_skeletons, &mut issues);
+            validate_voices(scene, &mut issues);
+            validate_start_offsets(scene, &mut issues);
+            validate_required_tags(scene, &mut issues);
+        }
+        issues
+    }
+}
+
+/// Positions of a stage which are exported with their slot index, disabled positions are dropped on export
+fn active_positions<'a>(scene: &'a Scene, stage: &'a Stage) -> impl Iterator<Item = (usize, &'a Position)> {
+    stage
+        .positions
+        .iter()
+        .enumerate()
+        .filter(move |(i, _)| scene.positions.get(*i).map_or(true, |info| info.enabled))
+}
+
+/// Position infos of the scene which are exported with their slot index
+fn active_infos(scene: &Scene) -> impl Iterator<Item = (usize, &PositionInfo)> {
+    scene.positions.iter().enumerate().filter(|(_, info)| info.enabled)
+}
+
+/// NaN or infinite offsets cannot be written into the registry
+fn validate_finite_offsets(scene: &Scene, issues: &mut Vec<ValidationIssue>) {
+    if !scene.furniture.offset.is_finite() {
+        issues.push(ValidationIssue::error(
+            scene,
+            format!("Scene {}: Furniture offset is not a finite number", scene.name),
+        ));
+    }
+    for (stage, i) in scene.non_finite_offsets() {
+        if scene.positions.get(i).is_some_and(|info| !info.enabled) {
+            continue;
+        }
+        issues.push(
+            ValidationIssue::error(
+                scene,
+                format!(
+                    "Scene {} Stage {} Position {}: Offset is not a finite number",
+                    scene.name,
+                    stage.name,
+                    i + 1
+                ),
+            )
+            .at(&stage.id, Some(i)),
+        );
+    }
+}
+
+/// Offsets far away from the origin are usually unit mismatches from an import
+fn validate_offsets(scene: &Scene, settings: &ValidationSettings, issues: &mut Vec<ValidationIssue>) {
+    for stage in &scene.stages {
+        for (i, position) in active_positions(scene, stage) {
+            let offset = &position.offset;
+            if [offset.x, offset.y, offset.z]
+                .iter()
+                .any(|v| v.abs() > settings.max_offset)
+            {
+                issues.push(
+                    ValidationIssue::warning(
+                        scene,
+                        format!(
+                            "Scene {} Stage {} Position {}: Offset ({}, {}, {}) exceeds the limit of {}",
+                            scene.name,
+                            stage.name,
+                            i + 1,
+                            offset.x,
+                            offset.y,
+                            offset.z,
+                            settings.max_offset
+                        ),
+                    )
+                    .at(&stage.id, Some(i)),
+                );
+            }
+        }
+    }
+}
+
+/// Camera angles are wrapped into range on export, but NaN or infinite values cannot be written
+fn validate_camera_hints(scene: &Scene, issues: &mut Vec<ValidationIssue>) {
+    for stage in &scene.stages {
+        if stage.camera.as_ref().is_some_and(|camera| !camera.is_finite()) {
+            issues.push(
+                ValidationIssue::error(
+                    scene,
+                    format!("Scene {} Stage {}: Camera hint is not a finite number", scene.name, stage.name),
+                )
+                .at(&stage.id, None),
+            );
+        }
+    }
+}
+
+/// Repeated stage names can't be told apart in the navigation editor and the docs
+fn validate_stage_names(scene: &Scene, issues: &mut Vec<ValidationIssue>) {
+    let duplicates = scene.duplicate_stage_names();
+    if !duplicates.is_empty() {
+        issues.push(ValidationIssue::warning(
+            scene,
+            format!(
+                "Scene {}: Stage names are used more than once: {}",
+                scene.name,
+                duplicates.join(", ")
+            ),
+        ));
+    }
+}
+
+/// Per scene registry files are named after the scene, with characters windows forbids in paths replaced
+fn validate_file_name(scene: &Scene, issues: &mut Vec<ValidationIssue>) {
+    let name = sanitize_filename(&scene.name);
+    if name != scene.name {
+        issues.push(ValidationIssue::warning(
+            scene,
+            format!("Scene {}: Exported under the file name {}", scene.name, name),
+        ));
+    }
+}
+
+/// Disabled stages are dropped on export, taking their navigation links with them
+fn validate_disabled_stages(scene: &Scene, issues: &mut Vec<ValidationIssue>) {
+    if scene.stages.iter().all(|stage| stage.enabled) {
+        return;
+    }
+    for stage in scene.stages.iter().filter(|stage| stage.enabled) {
+        let links = match scene.graph.get(&stage.id) {
+            Some(node) => &node.dest,
+            None => continue,
+        };
+        for target in links.iter().filter_map(|id| scene.get_stage(id)) {
+            if !target.enabled {
+                issues.push(
+                    ValidationIssue::warning(
+                        scene,
+                        format!(
+                            "Scene {} Stage {}: Links to disabled Stage {}",
+                            scene.name, stage.name, target.name
+                        ),
+                    )
+                    .at(&stage.id, None),
+                );
+            }
+        }
+    }
+    let reachable = scene.reachable_stages(true);
+    let reachable_with_disabled = scene.reachable_stages(false);
+    for stage in &scene.stages {
+        if stage.enabled
+            && !reachable.contains(&stage.id)
+            && reachable_with_disabled.contains(&stage.id)
+        {
+            issues.push(
+                ValidationIssue::warning(
+                    scene,
+                    format!(
+                        "Scene {} Stage {}: Only reachable through disabled stages",
+                        scene.name, stage.name
+                    ),
+                )
+                .at(&stage.id, None),
+            );
+        }
+    }
+}
+
+/// Disabled positions are dropped from every stage, which only works out if all stages agree on the actors
+fn validate_position_count(scene: &Scene, issues: &mut Vec<ValidationIssue>) {
+    if !scene.positions.is_empty() && scene.positions.iter().all(|info| !info.enabled) {
+        issues.push(ValidationIssue::error(
+            scene,
+            format!("Scene {}: All positions are disabled", scene.name),
+        ));
+    }
+    // Stages disagreeing among each other can't be exported, a stale position info is only suspicious
+    let mismatched = scene.stage_actor_counts().len() > 1;
+    let disabled = scene.positions.iter().filter(|info| !info.enabled).count();
+    for stage in &scene.stages {
+        if stage.positions.len() != scene.positions.len() {
+            let mut message = format!(
+                "Scene {} Stage {}: Has {} positions, but the scene has {}",
+                scene.name,
+                stage.name,
+                stage.positions.len(),
+                scene.positions.len()
+            );
+            if disabled > 0 {
+                message += ", disabled positions may remove the wrong actors";
+            }
+            let issue = if mismatched {
+                ValidationIssue::error(scene, message)
+            } else {
+                ValidationIssue::warning(scene, message)
+            };
+            issues.push(issue.at(&stage.id, None));
+        }
+    }
+}
+
+/// Every exported position starts its animation with the first event of its list
+fn validate_events(scene: &Scene, issues: &mut Vec<ValidationIssue>) {
+    for stage in scene.stages.iter().filter(|stage| stage.enabled) {
+        for (i, position) in active_positions(scene, stage) {
+            if position.event.is_empty() {
+                issues.push(
+                    ValidationIssue::error(
+                        scene,
+                        format!(
+                            "Scene {} Stage {} Position {}: Has no animation event",
+                            scene.name,
+                            stage.name,
+                            i + 1
+                        ),
+                    )
+                    .at(&stage.id, Some(i)),
+                );
+            }
+        }
+    }
+}
+
+/// SexLab refuses to load scenes with more actors than it supports
+fn validate_max_positions(scene: &Scene, settings: &ValidationSettings, issues: &mut Vec<ValidationIssue>) {
+    for stage in &scene.stages {
+        let active = active_positions(scene, stage).count();
+        if active > settings.max_positions {
+            issues.push(
+                ValidationIssue::error(
+                    scene,
+                    format!(
+                        "Scene {} Stage {}: Has {} active positions, at most {} are supported",
+                        scene.name, stage.name, active, settings.max_positions
+                    ),
+                )
+                .at(&stage.id, None),
+            );
+        }
+    }
+}
+
+/// Scene ids key the registry index, a scene stored under another id or sharing its id would corrupt it
+fn validate_scene_ids(package: &Package, issues: &mut Vec<ValidationIssue>) {
+    let mut seen: HashMap<&NanoID, &Scene> = HashMap::new();
+    for (key, scene) in &package.scenes {
+        if *key != scene.id {
+            issues.push(ValidationIssue::error(
+                scene,
+                format!(
+                    "Scene {} is stored under id {} but has id {}",
+                    scene.name, key.0, scene.id.0
+                ),
+            ));
+        }
+        if let Some(other) = seen.insert(&scene.id, scene) {
+            issues.push(ValidationIssue::error(
+                scene,
+                format!(
+                    "Scenes {} and {} share the id {}",
+                    other.name, scene.name, scene.id.0
+                ),
+            ));
+        }
+    }
+}
+
+/// SexLab stops advancing at a stage without links, which is only intended at a climax
+fn validate_dead_ends(scene: &Scene, issues: &mut Vec<ValidationIssue>) {
+    if scene.stages.len() < 2 {
+        return;
+    }
+    for stage in scene.stages.iter().filter(|stage| stage.enabled && scene.is_dead_end(stage)) {
+        issues.push(
+            ValidationIssue::warning(
+                scene,
+                format!(
+                    "Scene {} Stage {}: Has no outgoing navigation and is not a climax",
+                    scene.name, stage.name
+                ),
+            )
+            .at(&stage.id, None),
+        );
+    }
+}
+
+/// Loops are fine as long as there is a way out, either a link leaving the loop or a conditional transition
+fn validate_nav_cycles(scene: &Scene, issues: &mut Vec<ValidationIssue>) {
+    for cycle in scene.find_nav_cycles() {
+        let has_exit = cycle.iter().any(|id| {
+            scene.graph.get(id).is_some_and(|node| {
+                node.dest
+                    .iter()
+                    .any(|dest| !cycle.contains(dest) || node.condition(dest).is_some())
+            })
+        });
+        if has_exit {
+            continue;
+        }
+        let names: Vec<String> = cycle
+            .iter()
+            .filter_map(|id| scene.get_stage(id))
+            .map(|stage| stage.name.clone())
+            .collect();
+        issues.push(
+            ValidationIssue::warning(
+                scene,
+                format!(
+                    "Scene {}: Stages {} loop without an exit or condition",
+                    scene.name,
+                    names.join(" -> ")
+                ),
+            )
+            .at(&cycle[0], None),
+        );
+    }
+}
+
+fn validate_furniture(scene: &Scene, issues: &mut Vec<ValidationIssue>) {
+    for stage in &scene.stages {
+        for (i, position) in active_positions(scene, stage) {
+            for furniture in position.furniture_offsets.keys() {
+                if !is_known_furniture(furniture) {
+                    issues.push(
+                        ValidationIssue::warning(
+                            scene,
+                            format!(
+                                "Scene {} Stage {} Position {}: Offsets for unknown furniture {}",
+                                scene.name,
+                                stage.name,
+                                i + 1,
+                                furniture
+                            ),
+                        )
+                        .at(&stage.id, Some(i)),
+                    );
+                }
+            }
+        }
+    }
+    for furniture in &scene.furniture.furni_types {
+        if is_builtin_furniture(furniture) {
+            continue;
+        }
+        let message = if is_known_furniture(furniture) {
+            format!(
+                "Scene {}: Custom furniture {} is not part of the registry format and will be exported without furniture",
+                scene.name, furniture
+            )
+        } else {
+            format!("Scene {}: Unknown furniture {}", scene.name, furniture)
+        };
+        issues.push(ValidationIssue::warning(scene, message));
+    }
+}
+
+/// Furniture offsets only move the actors which have them, a partial set leaves the others misplaced
+fn validate_furniture_offsets(scene: &Scene, issues: &mut Vec<ValidationIssue>) {
+    let mut furniture: Vec<&String> = scene
+        .stages
+        .iter()
+        .flat_map(|stage| active_positions(scene, stage))
+        .flat_map(|(_, position)| position.furniture_offsets.keys())
+        .collect();
+    furniture.sort();
+    furniture.dedup();
+    for furniture in furniture {
+        for stage in &scene.stages {
+            let missing: Vec<String> = active_positions(scene, stage)
+                .filter(|(_, position)| !position.furniture_offsets.contains_key(furniture))
+                .map(|(i, _)| (i + 1).to_string())
+                .collect();
+            if missing.is_empty() {
+                continue;
+            }
+            issues.push(
+                ValidationIssue::warning(
+                    scene,
+                    format!(
+                        "Scene {} Stage {}: Positions {} have no offsets for furniture {}",
+                        scene.name,
+                        stage.name,
+                        missing.join(", "),
+                        furniture
+                    ),
+                )
+                .at(&stage.id, None),
+            );
+        }
+    }
+}
+
+/// Several creatures of the exact same race are usually a copy paste mistake in creature-on-creature scenes.
+/// Race keys are shared by all stages of a scene, so every stage is affected alike
+fn validate_same_race(scene: &Scene, issues: &mut Vec<ValidationIssue>) {
+    if scene.allow_same_race {
+        return;
+    }
+    let mut active = scene.positions.iter().filter(|info| info.enabled);
+    let race = match active.next() {
+        Some(first) if first.race != "Human" => &first.race,
+        _ => return,
+    };
+    let mut count = 1;
+    for info in active {
+        if &info.race != race {
+            return;
+        }
+        count += 1;
+    }
+    if count < 2 {
+        return;
+    }
+    for stage in scene.stages.iter().filter(|stage| stage.enabled) {
+        issues.push(
+            ValidationIssue::warning(
+                scene,
+                format!(
+                    "Scene {} Stage {}: All {} positions are {}",
+                    scene.name, stage.name, count, race
+                ),
+            )
+            .at(&stage.id, None),
+        );
+    }
+}
+
+/// Custom race keys help organizing a project but have no id in the registry file
+fn validate_race_keys(scene: &Scene, issues: &mut Vec<ValidationIssue>) {
+    for (i, info) in active_infos(scene) {
+        if get_race_key_bytes(&info.race).is_none() {
+            issues.push(ValidationIssue::error(
+                scene,
+                format!(
+                    "Scene {} Position {}: Race key {} is not known to SexLab and cannot be exported",
+                    scene.name,
+                    i + 1,
+                    info.race
+                ),
+            ));
+        }
+    }
+}
+
+fn validate_sex_race_consistency(scene: &Scene, issues: &mut Vec<ValidationIssue>) {
+    for (i, info) in active_infos(scene) {
+        if info.allow_sex_mismatch {
+            continue;
+        }
+        let contradicts = match get_expected_gender(&info.race) {
+            Some(Gender::Female) => !info.sex.female,
+            Some(Gender::Male) => !info.sex.male,
+            None => false,
+        };
+        if contradicts {
+            issues.push(ValidationIssue::warning(
+                scene,
+                format!(
+                    "Scene {} Position {}: {} is unusual for race {}",
+                    scene.name,
+                    i + 1,
+                    info.sex,
+                    info.race
+                ),
+            ));
+        }
+    }
+}
+
+fn validate_skeletons(scene: &Scene, known: &[String], issues: &mut Vec<ValidationIssue>) {
+    for (i, info) in active_infos(scene) {
+        let skeleton = match &info.skeleton {
+            Some(skeleton) => skeleton,
+            None => continue,
+        };
+        if !known.iter().any(|it| it.eq_ignore_ascii_case(skeleton)) {
+            issues.push(ValidationIssue::warning(
+                scene,
+                format!(
+                    "Scene {} Position {}: Unknown skeleton {}",
+                    scene.name,
+                    i + 1,
+                    skeleton
+                ),
+            ));
+        }
+    }
+}
+
+fn validate_voices(scene: &Scene, issues: &mut Vec<ValidationIssue>) {
+    for (i, info) in active_infos(scene) {
+        let voice = match &info.voice {
+            Some(voice) => voice,
+            None => continue,
+        };
+        if !is_known_voice(voice) {
+            issues.push(ValidationIssue::warning(
+                scene,
+                format!(
+                    "Scene {} Position {}: Unknown voice {}",
+                    scene.name,
+                    i + 1,
+                    voice
+                ),
+            ));
+        }
+    }
+}
+
+/// Required tags are matched against actor tags by SexLab, an empty one can never match
+fn validate_required_tags(scene: &Scene, issues: &mut Vec<ValidationIssue>) {
+    if scene.required_tags.iter().any(|tag| tag.trim().is_empty()) {
+        issues.push(ValidationIssue::error(
+            scene,
+            format!("Scene {}: Required actor tags must not be empty", scene.name),
+        ));
+    }
+}
+
+/// A start offset must fall within every timed stage, or the actor would never start
+fn validate_start_offsets(scene: &Scene, issues: &mut Vec<ValidationIssue>) {
+    let shortest = scene
+        .stages
+        .iter()
+        .filter(|stage| stage.extra.export_len() > 0.0)
+        .map(|stage| stage.extra.export_len() / 1000.0)
+        .reduce(f32::min);
+    for (i, info) in active_infos(scene) {
+        if info.start_offset == 0.0 {
+            continue;
+        }
+        if !info.start_offset.is_finite() || info.start_offset < 0.0 {
+            issues.push(ValidationIssue::error(
+                scene,
+                format!(
+                    "Scene {} Position {}: Start offset {} must not be negative",
+                    scene.name,
+                    i + 1,
+                    info.start_offset
+                ),
+            ));
+        } else if shortest.is_some_and(|shortest| info.start_offset >= shortest) {
+            issues.push(ValidationIssue::error(
+                scene,
+                format!(
+                    "Scene {} Position {}: Start offset of {} s is not shorter than the shortest stage ({} s)",
+                    scene.name,
+                    i + 1,
+                    info.start_offset,
+                    shortest.unwrap_or_default()
+                ),
+            ));
+        }
+    }
+}
+
+fn validate_conditions(scene: &Scene, issues: &mut Vec<ValidationIssue>) {
+    for stage in &scene.stages {
+        let node = match scene.graph.get(&stage.id) {
+            Some(node) => node,
+            None => continue,
+        };
+        for dest in &node.dest {
+            let condition = match node.condition(dest) {
+                Some(condition) => condition.trim(),
+                None => continue,
+            };
+            let target = scene
+                .get_stage(dest)
+                .map_or(dest.0.as_str(), |target| target.name.as_str());
+            if condition.is_empty() {
+                issues.push(
+                    ValidationIssue::error(
+                        scene,
+                        format!(
+                            "Scene {} Stage {}: Condition of the link to Stage {} is empty",
+                            scene.name, stage.name, target
+                        ),
+                    )
+                    .at(&stage.id, None),
+                );
+            } else if let Some(problem) = find_condition_problem(condition) {
+                issues.push(
+                    ValidationIssue::warning(
+                        scene,
+                        format!(
+                            "Scene {} Stage {}: Condition '{}' of the link to Stage {} looks malformed, {}",
+                            scene.name, stage.name, condition, target, problem
+                        ),
+                    )
+                    .at(&stage.id, None),
+                );
+            }
+        }
+    }
+}
+
+/// Cheap syntactic sanity check, the condition itself is evaluated by SexLab
+fn find_condition_problem(condition: &str) -> Option<&'static str> {
+    let mut depth = 0;
+    for c in condition.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some("unexpected closing parenthesis"),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Some("unbalanced parentheses");
+    }
+    if condition.matches('"').count() % 2 != 0 {
+        return Some("unterminated string");
+    }
+    const OPERATORS: [&str; 8] = ["&&", "||", "==", "!=", "<", ">", "=", "&"];
+    if OPERATORS.iter().any(|op| condition.ends_with(op))
+        || OPERATORS.iter().any(|op| condition.starts_with(op))
+    {
+        return Some("dangling operator");
+    }
+    None
+}
+
+pub fn log_issues(issues: &Vec<ValidationIssue>) -> () {
+    for issue in issues {
+        match issue.severity {
+            Severity::Warning => warn!("{}", issue.message),
+            Severity::Error => error!("{}", issue.message),
+        }
+    }
+    info!("Validation finished with {} issues", issues.len());
+}
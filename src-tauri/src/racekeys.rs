@@ -1,4 +1,8 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+use log::warn;
+
+use crate::settings::SETTINGS;
 
 #[derive(Debug, Clone, Copy)]
 pub enum RaceKey {
@@ -113,7 +117,8 @@ pub fn map_legacy_to_racekey(legacykey: &str) -> Result<String, String> {
         "wisps" => Ok("Wisp".into()),
         "wispmothers" => Ok("Wispmother".into()),
         "wolves" => Ok("Wolf".into()),
-        _ => Err(format!("Unrecognized legacy key: {}", legacykey).into()),
+        _ => find_alias(&SETTINGS.lock().unwrap().race_key_aliases, legacykey)
+            .ok_or_else(|| format!("Unrecognized legacy key: {}", legacykey)),
     }
 }
 
@@ -175,6 +180,22 @@ fn get_race_map() -> HashMap<String, RaceKey> {
     ])
 }
 
+/// Sex a race is conventionally played as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gender {
+    Male,
+    Female,
+}
+
+/// Races which only exist as one sex in the base game, None for any other race
+pub fn get_expected_gender(race: &str) -> Option<Gender> {
+    match get_race_map().get(race)? {
+        RaceKey::Hagraven | RaceKey::Spriggan | RaceKey::Wispmother => Some(Gender::Female),
+        RaceKey::Giant | RaceKey::DragonPriest | RaceKey::Riekling => Some(Gender::Male),
+        _ => None,
+    }
+}
+
 pub fn get_race_keys_string() -> Vec<String> {
     get_race_map()
         .iter()
@@ -187,3 +208,126 @@ pub fn get_race_key_bytes(race: &str) -> Option<u8> {
         .get(race)
         .map(|&key| key as u8)
 }
+
+/// Map a race key to its canonical spelling, matching case-insensitively
+pub fn get_canonical_race_key(race: &str) -> Option<String> {
+    get_race_map()
+        .into_keys()
+        .find(|key| key.eq_ignore_ascii_case(race))
+}
+
+/// Map an alias to the race key it stands for, any other key to its canonical spelling if it has one
+pub fn resolve_race_key(key: &str) -> String {
+    resolve_race_key_with(&SETTINGS.lock().unwrap().race_key_aliases, key)
+}
+
+/// `resolve_race_key` with the given alias table, to look up many keys without locking the settings for each
+pub fn resolve_race_key_with(aliases: &BTreeMap<String, String>, key: &str) -> String {
+    find_alias(aliases, key)
+        .or_else(|| get_canonical_race_key(key))
+        .unwrap_or_else(|| key.to_string())
+}
+
+fn find_alias(aliases: &BTreeMap<String, String>, key: &str) -> Option<String> {
+    aliases
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(key.trim()))
+        .map(|(_, target)| target.clone())
+}
+
+/// Add or replace an alias, the target has to be a built-in or custom race key. Returns the stored target
+pub fn set_race_key_alias(alias: &str, target: &str) -> Result<String, String> {
+    let alias = alias.trim();
+    if alias.is_empty() {
+        return Err("Race key alias must not be empty".into());
+    }
+    if get_all_race_keys().iter().any(|key| key.eq_ignore_ascii_case(alias)) {
+        return Err(format!("{} is a race key and can't be an alias", alias));
+    }
+    let target = get_all_race_keys()
+        .into_iter()
+        .find(|key| key.eq_ignore_ascii_case(target.trim()))
+        .ok_or_else(|| format!("Unknown race key {}", target))?;
+    let mut settings = SETTINGS.lock().unwrap();
+    settings
+        .race_key_aliases
+        .retain(|other, _| !other.eq_ignore_ascii_case(alias));
+    settings.race_key_aliases.insert(alias.to_string(), target.clone());
+    settings.save()?;
+    Ok(target)
+}
+
+/// Remove an alias, ignoring case. Returns whether it existed
+pub fn remove_race_key_alias(alias: &str) -> Result<bool, String> {
+    let mut settings = SETTINGS.lock().unwrap();
+    let before = settings.race_key_aliases.len();
+    settings
+        .race_key_aliases
+        .retain(|other, _| !other.eq_ignore_ascii_case(alias.trim()));
+    let removed = settings.race_key_aliases.len() != before;
+    if removed {
+        settings.save()?;
+    }
+    Ok(removed)
+}
+
+/// Merge an alias table, e.g. one shared with a pack, into the stored one. Entries with an unknown target are
+/// skipped. Returns the number of aliases added or changed
+pub fn import_race_key_aliases(aliases: BTreeMap<String, String>) -> Result<usize, String> {
+    let mut imported = 0;
+    for (alias, target) in aliases {
+        let previous = find_alias(&SETTINGS.lock().unwrap().race_key_aliases, &alias);
+        match set_race_key_alias(&alias, &target) {
+            Ok(target) if previous.as_ref() != Some(&target) => imported += 1,
+            Ok(_) => {}
+            Err(e) => warn!("Skipping race key alias {}: {}", alias, e),
+        }
+    }
+    Ok(imported)
+}
+
+/// Built-in race keys followed by the ones registered by the user
+pub fn get_all_race_keys() -> Vec<String> {
+    let mut ret = get_race_keys_string();
+    ret.extend(SETTINGS.lock().unwrap().custom_race_keys.iter().cloned());
+    ret
+}
+
+/// Map a built-in or custom race key to its registered spelling, matching case-insensitively
+pub fn find_race_key(race: &str) -> Option<String> {
+    get_all_race_keys()
+        .into_iter()
+        .find(|key| key.eq_ignore_ascii_case(race.trim()))
+}
+
+/// All race keys in a stable order, for tools which need the exact vocabulary of the builder
+pub fn get_sorted_race_keys() -> Vec<String> {
+    let mut ret = get_all_race_keys();
+    ret.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()).then_with(|| a.cmp(b)));
+    ret.dedup();
+    ret
+}
+
+/// Merge a list of race keys into the custom ones, returns the number of keys newly added
+pub fn import_race_keys(keys: Vec<String>) -> Result<usize, String> {
+    let mut settings = SETTINGS.lock().unwrap();
+    let mut added = 0;
+    for key in keys {
+        let key = key.trim();
+        if key.is_empty()
+            || get_canonical_race_key(key).is_some()
+            || settings
+                .custom_race_keys
+                .iter()
+                .any(|it| it.eq_ignore_ascii_case(key))
+        {
+            continue;
+        }
+        settings.custom_race_keys.push(key.to_string());
+        added += 1;
+    }
+    if added > 0 {
+        settings.save()?;
+    }
+    Ok(added)
+}
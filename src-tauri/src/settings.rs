@@ -0,0 +1,329 @@
+use log::{error, info, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use crate::project::position_info::PositionInfo;
+
+pub static SETTINGS: Lazy<Mutex<Settings>> = Lazy::new(|| Mutex::new(Settings::load()));
+
+/// User preferences, persisted in the user's config directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub keybindings: Keybindings,
+    pub validation: ValidationSettings,
+    pub export: ExportSettings,
+    /// Furniture types for modded furniture, in addition to the built-in ones
+    pub custom_furniture: Vec<String>,
+    /// Race keys of modded creatures, in addition to the built-in ones
+    pub custom_race_keys: Vec<String>,
+    /// Other spellings of race keys used by some packs, mapped to the race key to use instead
+    pub race_key_aliases: BTreeMap<String, String>,
+    pub new_scene: SceneDefaults,
+    pub display_units: DisplayUnits,
+    pub import: ImportSettings,
+    /// Named sets of position infos which can be stamped onto stages
+    pub position_templates: BTreeMap<String, Vec<PositionInfo>>,
+    /// Ask before Save As replaces an existing file
+    pub confirm_overwrite: bool,
+    /// Name new stages "Stage N" instead of leaving them untitled
+    pub auto_name_stages: bool,
+    /// Save projects in the sorted and indented format of `Package::save_canonical`
+    pub canonical_saves: bool,
+    pub darkmode: bool,
+    /// Geometry of the main window when it was last closed
+    pub main_window: Option<WindowGeometry>,
+    /// Geometry of the last closed stage editor, shared by all stage editors
+    pub stage_editor_window: Option<WindowGeometry>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            keybindings: Default::default(),
+            validation: Default::default(),
+            export: Default::default(),
+            custom_furniture: Default::default(),
+            custom_race_keys: Default::default(),
+            race_key_aliases: Default::default(),
+            new_scene: Default::default(),
+            display_units: Default::default(),
+            import: Default::default(),
+            position_templates: Default::default(),
+            confirm_overwrite: true,
+            auto_name_stages: true,
+            canonical_saves: false,
+            darkmode: false,
+            main_window: None,
+            stage_editor_window: None,
+        }
+    }
+}
+
+/// Outer position and inner size of a window, in physical pixels
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Units the editor shows offsets in. Offsets are always stored and exported in SexLab units
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplayUnits {
+    /// Label shown next to converted values
+    pub name: String,
+    /// Display units per SexLab unit
+    pub per_game_unit: f32,
+}
+
+impl Default for DisplayUnits {
+    fn default() -> Self {
+        Self {
+            name: "units".into(),
+            per_game_unit: 1.0,
+        }
+    }
+}
+
+/// Clean up applied to offsets imported from files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportSettings {
+    /// Round the offsets of imported stages to `offset_decimals` decimals
+    pub normalize_offsets: bool,
+    pub offset_decimals: u32,
+    /// Replace race key aliases of converted SLAL packs, see `racekeys::resolve_race_key`
+    pub resolve_race_aliases: bool,
+}
+
+impl Default for ImportSettings {
+    fn default() -> Self {
+        Self {
+            normalize_offsets: false,
+            offset_decimals: 3,
+            resolve_race_aliases: false,
+        }
+    }
+}
+
+/// Values every blank scene starts with, empty keeps the built-in defaults
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SceneDefaults {
+    pub furniture: Vec<String>,
+    pub tags: Vec<String>,
+    /// Race key of new positions
+    pub race: String,
+}
+
+/// Options for the game export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportSettings {
+    /// Write builder only metadata into a `<pack>.builder.json` next to the export
+    pub builder_sidecar: bool,
+    /// Write JSON files of the export without indentation
+    pub minify: bool,
+    /// Delete files of earlier exports which the project no longer produces
+    pub clean: bool,
+    pub layout: ExportLayout,
+    /// Extension of registry files, without the leading dot
+    pub extension: String,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            builder_sidecar: false,
+            minify: false,
+            clean: false,
+            layout: Default::default(),
+            extension: DEFAULT_REGISTRY_EXTENSION.into(),
+        }
+    }
+}
+
+pub const DEFAULT_REGISTRY_EXTENSION: &str = "slr";
+
+impl ExportSettings {
+    /// The registry file extension without a leading dot. Only short alphanumeric suffixes are accepted
+    /// so the extension can't change the directory a file is written to
+    pub fn registry_extension(&self) -> Result<&str, String> {
+        let ext = self.extension.trim().trim_start_matches('.');
+        if ext.is_empty() {
+            return Ok(DEFAULT_REGISTRY_EXTENSION);
+        }
+        if ext.len() > 16
+            || !ext
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "Invalid export extension {}, expected up to 16 letters, digits, '-' or '_'",
+                self.extension
+            ));
+        }
+        Ok(ext)
+    }
+}
+
+/// How scenes are distributed over registry files
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportLayout {
+    /// One registry file holding all scenes of the pack. This is the layout SexLab P+ loads
+    /// from SKSE/SexLab/Registry and the only one the builder produced before layouts existed
+    #[default]
+    Combined,
+    /// One registry file per scene in a folder named after the pack, each carrying the pack
+    /// header. SexLab P+ does not read this layout, it is meant for tooling that processes
+    /// scenes individually
+    PerScene,
+}
+
+impl std::str::FromStr for ExportLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "combined" => Ok(Self::Combined),
+            "per-scene" => Ok(Self::PerScene),
+            _ => Err(format!("Unknown export layout {}, expected per-scene or combined", s)),
+        }
+    }
+}
+
+/// Limits checked when validating a project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValidationSettings {
+    /// Largest absolute x/y/z offset before a position is reported as an outlier
+    pub max_offset: f32,
+    /// Fewest stages a scene may have to be exported
+    pub min_stages: usize,
+    /// Most active positions a stage may have, SexLab fails to load scenes with more actors
+    pub max_positions: usize,
+}
+
+impl Default for ValidationSettings {
+    fn default() -> Self {
+        Self {
+            max_offset: 200.0,
+            min_stages: 1,
+            max_positions: 5,
+        }
+    }
+}
+
+/// Menu accelerators, an empty string leaves the action unbound
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keybindings {
+    pub new_project: String,
+    pub open_project: String,
+    pub save: String,
+    pub save_as: String,
+    pub build: String,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self {
+            new_project: "cmdOrControl+N".into(),
+            open_project: "cmdOrControl+O".into(),
+            save: "cmdOrControl+S".into(),
+            save_as: "cmdOrControl+Shift+S".into(),
+            build: "cmdOrControl+B".into(),
+        }
+    }
+}
+
+impl Keybindings {
+    /// Ensure no two actions share the same accelerator
+    pub fn validate(&self) -> Result<(), String> {
+        let mut used: HashMap<String, &str> = HashMap::new();
+        for (action, key) in [
+            ("New Project", &self.new_project),
+            ("Open Project", &self.open_project),
+            ("Save", &self.save),
+            ("Save As", &self.save_as),
+            ("Export", &self.build),
+        ] {
+            if key.is_empty() {
+                continue;
+            }
+            let normalized = key.replace(' ', "").to_lowercase();
+            if let Some(other) = used.insert(normalized, action) {
+                return Err(format!(
+                    "Accelerator {} is assigned to both {} and {}",
+                    key, other, action
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn accelerator(key: &str) -> Option<&str> {
+    if key.is_empty() {
+        None
+    } else {
+        Some(key)
+    }
+}
+
+impl Settings {
+    fn get_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("SexLabSceneBuilder").join("settings.json"))
+    }
+
+    pub fn load() -> Self {
+        let path = match Self::get_path() {
+            Some(path) if path.exists() => path,
+            _ => return Self::default(),
+        };
+        let mut settings: Settings = match fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+        {
+            Ok(settings) => settings,
+            Err(e) => {
+                error!("Failed to read settings from {}: {}", path.display(), e);
+                return Self::default();
+            }
+        };
+        if let Err(e) = settings.keybindings.validate() {
+            warn!("Invalid keybindings, falling back to defaults: {}", e);
+            settings.keybindings = Keybindings::default();
+        }
+        if settings.validation.min_stages == 0 {
+            warn!("A scene needs at least one stage, raising the minimum stage count to 1");
+            settings.validation.min_stages = 1;
+        }
+        let factor = settings.display_units.per_game_unit;
+        if !factor.is_finite() || factor <= 0.0 {
+            warn!("Invalid display unit factor {}, falling back to game units", factor);
+            settings.display_units = DisplayUnits::default();
+        }
+        info!("Loaded settings from {}", path.display());
+        settings
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::get_path().ok_or("Unable to locate config directory".to_string())?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(&path, content).map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,25 @@
+/// Voice sets shipped with SexLab, positions may request one of these by name
+const DEFAULT_VOICES: [&str; 12] = [
+    "FemaleClassic",
+    "FemaleBreathy",
+    "FemaleYoung",
+    "FemaleMature",
+    "FemaleSultry",
+    "FemaleQuiet",
+    "MaleNeutral",
+    "MaleCalm",
+    "MaleRough",
+    "MaleAverage",
+    "MaleYoung",
+    "MaleOld",
+];
+
+pub fn get_voices() -> Vec<String> {
+    DEFAULT_VOICES.iter().map(|voice| voice.to_string()).collect()
+}
+
+pub fn is_known_voice(voice: &str) -> bool {
+    DEFAULT_VOICES
+        .iter()
+        .any(|known| known.eq_ignore_ascii_case(voice))
+}
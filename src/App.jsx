@@ -4,17 +4,18 @@ import { invoke } from "@tauri-apps/api/core";
 import { listen, emit } from "@tauri-apps/api/event";
 import { Graph, Shape } from '@antv/x6'
 import { History } from "@antv/x6-plugin-history";
-import { Menu, Layout, Card, Input, Space, Button, Empty, Modal, Tooltip, notification, Divider, Switch, Checkbox, Row, Col, InputNumber, Select, ConfigProvider, theme } from 'antd'
+import { Menu, Layout, Card, Input, Space, Button, Empty, Modal, Tooltip, notification, Divider, Switch, Checkbox, Row, Col, InputNumber, Select, ConfigProvider, theme, Progress } from 'antd'
 import {
   ExperimentOutlined, FolderOutlined, PlusOutlined, ExclamationCircleOutlined, QuestionCircleOutlined, DiffOutlined, ZoomInOutlined, ZoomOutOutlined,
-  DeleteOutlined, DoubleLeftOutlined, DoubleRightOutlined, PicCenterOutlined, CompressOutlined, PushpinOutlined, DragOutlined, WarningOutlined
+  DeleteOutlined, DoubleLeftOutlined, DoubleRightOutlined, PicCenterOutlined, CompressOutlined, PushpinOutlined, DragOutlined, WarningOutlined,
+  NodeIndexOutlined
 } from '@ant-design/icons';
 import { Panel, PanelGroup, PanelResizeHandle } from 'react-resizable-panels';
 import './ResizableSidebar.css';
 const { Header, Content, Footer, Sider } = Layout;
 const { confirm } = Modal;
 import { STAGE_EDGE, STAGE_EDGE_SHAPEID } from "./scene/SceneEdge"
-import { Furnitures } from "./common/Furniture";
+import { withCustomFurnitures } from "./common/Furniture";
 import "./scene/SceneNode"
 import "./App.css";
 // import "./Dark.css";
@@ -39,6 +40,53 @@ function App() {
   const [edited, setEdited] = useState(0);
   const inEdit = useRef(0);
   const [showAreas, setShowAreas] = useState(false);
+  const [furnitureTypes, setFurnitureTypes] = useState([]);
+  const [loadProgress, setLoadProgress] = useState(null);
+  const [changedScenes, setChangedScenes] = useState([]);
+  const [displayUnits, setDisplayUnits] = useState({ name: 'units', per_game_unit: 1.0 });
+  // Project updates are received by a listener registered once, which needs the current values
+  const activeSceneId = useRef(null);
+  const editedRef = useRef(0);
+
+  useEffect(() => {
+    activeSceneId.current = activeScene ? activeScene.id : null;
+  }, [activeScene]);
+
+  useEffect(() => {
+    editedRef.current = edited;
+  }, [edited]);
+
+  const refreshChangedScenes = () => {
+    invoke('scenes_changed_since_export').then(setChangedScenes).catch(() => setChangedScenes([]));
+  };
+
+  useEffect(() => {
+    invoke('get_furniture_types').then(result => setFurnitureTypes(result));
+  }, []);
+
+  // Offsets are stored in SexLab units, the furniture position is shown in the user's display units
+  useEffect(() => {
+    invoke('get_display_units').then(setDisplayUnits);
+  }, []);
+
+  // Block the editor while a project is being loaded
+  useEffect(() => {
+    const progress = listen('load_progress', (event) => {
+      setLoadProgress(event.payload < 100 ? event.payload : null);
+    });
+    const failed = listen('load_failed', (event) => {
+      setLoadProgress(null);
+      api['error']({
+        message: 'Failed to load Project',
+        description: event.payload,
+        placement: 'bottomLeft'
+      });
+    });
+    return () => {
+      progress.then(res => { res() });
+      failed.then(res => { res() });
+    }
+  }, []);
 
   // Hide Areas when sidebar is collapsed
   useEffect(() => {
@@ -61,6 +109,7 @@ function App() {
     const unlisten = listen('toggle_darkmode', (event) => {
       setIsDark(event.payload); // event.payload should be true or false
     });
+    invoke('get_in_darkmode').then(setIsDark);
     return () => {
       unlisten.then(f => f());
     };
@@ -157,6 +206,24 @@ function App() {
       .on("edge:connected", (e) => {
         setEdited(true);
       })
+      .on("edge:dblclick", ({ edge }) => {
+        let condition = edge.getData()?.condition || '';
+        confirm({
+          title: 'Transition Condition',
+          icon: <QuestionCircleOutlined />,
+          content: <Input
+            defaultValue={condition}
+            placeholder="Optional SexLab condition which must be met to take this link"
+            onChange={(e) => { condition = e.target.value; }}
+          />,
+          onOk() {
+            condition = condition.trim();
+            edge.setData({ condition: condition || null }, { overwrite: true });
+            edge.setLabels(condition ? [condition] : []);
+            setEdited(true);
+          }
+        });
+      })
       // Custom Events
       .on("node:doMarkRoot", ({ node }) => {
         updateActiveScene(prev => {
@@ -325,7 +392,7 @@ function App() {
 
   useEffect(() => {
     if (!graph) return;
-    const unlisten = listen('on_project_update', (event) => {
+    const unlisten = listen('on_project_update', async (event) => {
       const stage_map = event.payload;
       const scns = [];
       for (const key in stage_map) {
@@ -334,13 +401,24 @@ function App() {
           scns.push(element);
         }
       }
+      const order = await invoke('get_scene_order').catch(() => []);
+      scns.sort((a, b) => order.indexOf(a.id) - order.indexOf(b.id));
+      refreshChangedScenes();
       console.log("Opening new Project with Scenes: ", scns);
       updateScenes(scns);
-      setEdited(false);
-      if (scns.length) {
+      const current = scns.find(scene => scene.id === activeSceneId.current);
+      // Unsaved graph edits of the open scene survive the update, otherwise the open scene is reloaded
+      if (editedRef.current && activeSceneId.current) {
+        if (current) mergeIntoActiveScene(current);
+        return;
+      }
+      if (current) {
+        setActiveScene(current);
+      } else if (scns.length) {
         setActiveScene(scns[0]);
       } else {
         updateActiveScene(null);
+        setEdited(false);
       }
     });
     invoke('request_project_update');
@@ -364,6 +442,30 @@ function App() {
     })
   }
 
+  const checkGraph = () => {
+    invoke('analyze_nav_graph', { sceneId: activeScene.id }).then(analysis => {
+      const stageName = (id) => activeScene.stages.find(it => it.id === id)?.name || id;
+      const problems = [];
+      if (analysis.unreachable.length)
+        problems.push(`Unreachable: ${analysis.unreachable.map(stageName).join(', ')}`);
+      if (analysis.dead_ends.length)
+        problems.push(`Dead ends: ${analysis.dead_ends.map(stageName).join(', ')}`);
+      analysis.cycles.forEach(cycle => problems.push(`Cycle: ${cycle.map(stageName).join(' -> ')}`));
+      api[problems.length ? 'warning' : 'success']({
+        message: 'Navigation Graph',
+        description: problems.length ? problems.join('\n') : 'Every stage is reachable and ends the scene or leads on.',
+        style: { whiteSpace: 'pre-line' },
+        placement: 'bottomLeft'
+      });
+    }).catch(e => {
+      api['error']({
+        message: 'Navigation Graph',
+        description: String(e),
+        placement: 'bottomLeft'
+      });
+    });
+  }
+
   const setActiveScene = async (newscene) => {
     if (!inEdit.current && edited > 0) {
       confirm({
@@ -393,9 +495,11 @@ function App() {
       const sourceNode = nodes.find(node => node.id === sourceid);
       if (!sourceNode) continue;
       const sourcePort = sourceNode.ports.items[0];
+      const conditions = newscene.graph[sourceid].conditions || {};
       dest.forEach(targetid => {
         const target = nodes.find(node => node.id === targetid);
         if (!target) return;
+        const condition = conditions[targetid] || null;
         graph.addEdge({
           shape: STAGE_EDGE_SHAPEID,
           source: {
@@ -403,6 +507,8 @@ function App() {
             port: sourcePort.id
           },
           target,
+          data: { condition },
+          labels: condition ? [condition] : [],
         });
       });
     }
@@ -447,6 +553,33 @@ function App() {
     node.prop('isStart', belongingScene && belongingScene.root === stage.id);
   }
 
+  // Take over backend edits of the open scene's stages and positions while keeping its unsaved graph, so the
+  // next save doesn't write the stale stages back. Nodes of stages removed by the backend are dropped
+  const mergeIntoActiveScene = (incoming) => {
+    inEdit.current = true;
+    updateActiveScene(prev => {
+      prev.stages = incoming.stages;
+      prev.positions = incoming.positions;
+    });
+    graph.getNodes().forEach(node => {
+      const stage = incoming.stages.find(it => it.id === node.id);
+      if (!stage) {
+        graph.removeNode(node);
+        return;
+      }
+      node.prop('stage', stage);
+      node.prop('fixedLen', stage.extra.fixed_len);
+    });
+    incoming.stages.forEach(stage => {
+      if (graph.getCellById(stage.id)) return;
+      const node = addStageToGraph(stage);
+      const position = incoming.graph[stage.id];
+      if (position) node.setPosition(position.x, position.y);
+      updateNodeProps(stage, node, incoming);
+    });
+    inEdit.current = false;
+  }
+
   const saveScene = () => {
     let has_warnings = false;
     let doSave = true;
@@ -500,8 +633,14 @@ function App() {
           const position = node.getPosition();
           const edges = graph.getOutgoingEdges(node);
           const value = edges ? edges.map(e => e.getTargetCellId()) : [];
+          const conditions = {};
+          (edges || []).forEach(e => {
+            const condition = e.getData()?.condition;
+            if (condition) conditions[e.getTargetCellId()] = condition;
+          });
           ret[node.id] = {
             dest: value,
+            conditions,
             x: position.x,
             y: position.y,
           };
@@ -510,8 +649,16 @@ function App() {
       }(),
       has_warnings,
     };
-    invoke('save_scene', { scene }).then(() => {
-      console.log("Saved scene", scene);
+    invoke('save_scene', { scene }).then(({ changed, warning }) => {
+      console.log(changed ? "Saved scene" : "Scene unchanged", scene);
+      refreshChangedScenes();
+      if (warning) {
+        api['warning']({
+          message: 'Similar Scene Name',
+          description: warning,
+          placement: 'bottomLeft'
+        });
+      }
       updateActiveScene(scene);
       updateScenes(prev => {
         const w = prev.findIndex(it => it.id === scene.id);
@@ -523,6 +670,12 @@ function App() {
       });
       setEdited(false);
       console.log("Saved Scene", scene);
+    }).catch(e => {
+      api['error']({
+        message: 'Unable to save Scene',
+        description: e,
+        placement: 'bottomLeft'
+      });
     });
   }
 
@@ -536,7 +689,7 @@ function App() {
         console.log(scene);
         return makeMenuItem(
           <Tooltip title={scene.name} mouseEnterDelay={0.5}>
-            {scene.name}
+            {changedScenes.includes(scene.id) ? <i>{scene.name} *</i> : scene.name}
           </Tooltip>, scene.id, scene.has_warnings ? <WarningOutlined style={{ color: 'red' }} /> : <ExperimentOutlined style={{ color: 'green' }} />, [
           makeMenuItem("Edit", "editanim_" + scene.id),
           makeMenuItem("Delete", "delanim_" + scene.id, null, null, false, true),
@@ -607,6 +760,9 @@ function App() {
           {/* Left Panel */}
           <Panel minSize={10} defaultSize={15} maxSize={50} id="left-panel">
             {contextHolder}
+            <Modal open={loadProgress !== null} title="Loading Project" footer={null} closable={false} maskClosable={false} keyboard={false}>
+              <Progress percent={loadProgress ?? 0} />
+            </Modal>
             <Sider
               className="main-sider"
               collapsible
@@ -806,6 +962,17 @@ function App() {
                                   />
                                 </Tooltip>
                                 <Divider type="vertical" />
+                                <Tooltip
+                                  title="Check graph (saved state)"
+                                  mouseEnterDelay={0.5}
+                                >
+                                  <Button
+                                    type="text"
+                                    size="small"
+                                    icon={<NodeIndexOutlined />}
+                                    onClick={checkGraph}
+                                  />
+                                </Tooltip>
                                 <Tooltip
                                   title="Clear canvas"
                                   mouseEnterDelay={0.5}
@@ -903,7 +1070,7 @@ function App() {
                                 ? activeScene.furniture.furni_types
                                 : []
                             }
-                            options={Furnitures}
+                            options={withCustomFurnitures(furnitureTypes)}
                             mode="multiple"
                             onSelect={(value) => {
                               if (value === 'None') {
@@ -969,6 +1136,19 @@ function App() {
                           >
                             Private
                           </Checkbox>
+                          <Tooltip title={'Do not warn when all creatures of this scene share one race.'}>
+                            <Checkbox
+                              onChange={(e) => {
+                                updateActiveScene((prev) => {
+                                  prev.allow_same_race = e.target.checked;
+                                });
+                                setEdited(true);
+                              }}
+                              checked={activeScene && activeScene.allow_same_race}
+                            >
+                              Same Race
+                            </Checkbox>
+                          </Tooltip>
                           <Row gutter={[12, 12]} justify={'space-evenly'}>
                             <Col>
                               <InputNumber
@@ -980,16 +1160,17 @@ function App() {
                                 value={
                                   activeScene
                                     ? activeScene.furniture.offset.x
-                                      ? activeScene.furniture.offset.x
+                                      ? activeScene.furniture.offset.x * displayUnits.per_game_unit
                                       : undefined
                                     : undefined
                                 }
                                 onChange={(e) => {
                                   updateActiveScene((prev) => {
-                                    prev.furniture.offset.x = e;
+                                    prev.furniture.offset.x = e / displayUnits.per_game_unit;
                                   });
                                   setEdited(true);
                                 }}
+                                addonAfter={displayUnits.name}
                                 placeholder="0.0"
                               />
                             </Col>
@@ -1002,15 +1183,16 @@ function App() {
                                 step={0.1}
                                 value={
                                   activeScene && activeScene.furniture.offset.y
-                                    ? activeScene.furniture.offset.y
+                                    ? activeScene.furniture.offset.y * displayUnits.per_game_unit
                                     : undefined
                                 }
                                 onChange={(e) => {
                                   updateActiveScene((prev) => {
-                                    prev.furniture.offset.y = e;
+                                    prev.furniture.offset.y = e / displayUnits.per_game_unit;
                                   });
                                   setEdited(true);
                                 }}
+                                addonAfter={displayUnits.name}
                                 placeholder="0.0"
                               />
                             </Col>
@@ -1024,16 +1206,17 @@ function App() {
                                 value={
                                   activeScene
                                     ? activeScene.furniture.offset.z
-                                      ? activeScene.furniture.offset.z
+                                      ? activeScene.furniture.offset.z * displayUnits.per_game_unit
                                       : undefined
                                     : undefined
                                 }
                                 onChange={(e) => {
                                   updateActiveScene((prev) => {
-                                    prev.furniture.offset.z = e;
+                                    prev.furniture.offset.z = e / displayUnits.per_game_unit;
                                   });
                                   setEdited(true);
                                 }}
+                                addonAfter={displayUnits.name}
                                 placeholder="0.0"
                               />
                             </Col>
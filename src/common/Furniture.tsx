@@ -69,3 +69,12 @@ export const Furnitures = [
     ],
   },
 ];
+
+const builtinFurnitures = Furnitures.flatMap((it) => (it.options ? it.options : [it]).map((opt) => opt.value));
+
+// Furniture options including the custom types registered by the user
+export function withCustomFurnitures(types: string[]) {
+  const custom = types.filter((it) => !builtinFurnitures.includes(it));
+  if (custom.length === 0) return Furnitures;
+  return [...Furnitures, { label: 'Custom', options: custom.map((it) => ({ label: it, value: it })) }];
+}
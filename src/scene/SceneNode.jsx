@@ -41,6 +41,7 @@ function StageNode({ node, graph }) {
   const fixedLen = node.prop('fixedLen');
 
   const label = stage.name;
+  const disabled = stage.enabled === false;
   const navText = stage.extra.nav_text;
   const orgasm = stage.positions.find(pos => pos.climax) !== undefined;
   const color = fixedLen ?
@@ -102,6 +103,7 @@ function StageNode({ node, graph }) {
         style={{
           backgroundColor: color,
           borderColor: start ? START_COLOR : undefined,
+          opacity: disabled ? 0.5 : undefined,
         }}>
         <Row className='node-header'>
           <Space className='node-attribute-icons' size={10} wrap={false}>
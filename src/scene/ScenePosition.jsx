@@ -1,5 +1,6 @@
 import { useState, useEffect } from "react";
-import { Card, Space, InputNumber, Divider, Tooltip } from "antd";
+import { Card, Space, InputNumber, Input, Divider, Tooltip, AutoComplete } from "antd";
+import { invoke } from "@tauri-apps/api/core"
 import { useImmer } from "use-immer";
 import CheckboxEx from "../components/CheckboxEx";
 import RaceSelect from "../components/RaceSelect";
@@ -10,12 +11,22 @@ function ScenePosition({ position, onChange }) {
   const [sex, updateSex] = useImmer(position.sex);
   const [race, setRace] = useState(position.race);
   const [scale, setScale] = useState(position.scale);
+  const [skeleton, setSkeleton] = useState(position.skeleton ?? '');
+  const [voice, setVoice] = useState(position.voice ?? '');
+  const [startOffset, setStartOffset] = useState(position.start_offset ?? 0);
+  const [voices, setVoices] = useState([]);
   const [extra, updateExtra] = useImmer({ 
     submissive: position.submissive, 
     vampire: position.vampire, 
     dead: position.dead, 
+    enabled: position.enabled ?? true,
+    allow_sex_mismatch: position.allow_sex_mismatch ?? false,
   });
 
+  useEffect(() => {
+    invoke('get_voices').then(result => setVoices(result));
+  }, []);
+
   useEffect(() => {
     onChange({
       ...position,
@@ -25,8 +36,13 @@ function ScenePosition({ position, onChange }) {
       submissive: extra.submissive,
       vampire: extra.vampire,
       dead: extra.dead,
+      enabled: extra.enabled,
+      allow_sex_mismatch: extra.allow_sex_mismatch,
+      skeleton: skeleton.trim() ? skeleton.trim() : null,
+      voice: voice.trim() ? voice.trim() : null,
+      start_offset: startOffset ?? 0,
     });
-  }, [sex, race, scale, extra]);
+  }, [sex, race, scale, extra, skeleton, voice, startOffset]);
 
   return (
     <Card>
@@ -99,6 +115,26 @@ function ScenePosition({ position, onChange }) {
                 />
               </div>
             </Tooltip>
+            <Tooltip className="tool-tip" title={'Disabled positions are left out of the export.'}>
+              <div>
+                <CheckboxEx
+                  obj={extra}
+                  label={'Enabled'}
+                  attr={'enabled'}
+                  updateFunc={updateExtra}
+                />
+              </div>
+            </Tooltip>
+            <Tooltip className="tool-tip" title={'Do not warn about a sex which is unusual for the race.'}>
+              <div>
+                <CheckboxEx
+                  obj={extra}
+                  label={'Any Sex'}
+                  attr={'allow_sex_mismatch'}
+                  updateFunc={updateExtra}
+                />
+              </div>
+            </Tooltip>
           </Space.Compact>
         </Space>
         <Divider size="small" />
@@ -116,6 +152,45 @@ function ScenePosition({ position, onChange }) {
           }}
           placeholder="1.0"
         />
+        <Tooltip className="tool-tip" title={'Seconds this actor starts after the others.'}>
+          <InputNumber
+            addonBefore={'Start Delay'}
+            addonAfter={'s'}
+            controls
+            decimalSeparator=","
+            precision={2}
+            min={0}
+            step={0.1}
+            value={startOffset}
+            onChange={(e) => {
+              setStartOffset(e);
+            }}
+            placeholder="0.0"
+          />
+        </Tooltip>
+        <Tooltip className="tool-tip" title={'Skeleton or behavior the creature needs to play this position, e.g. canine.'}>
+          <Input
+            addonBefore={'Skeleton'}
+            value={skeleton}
+            onChange={(e) => setSkeleton(e.target.value)}
+            placeholder="Any"
+          />
+        </Tooltip>
+        <Tooltip className="tool-tip" title={'Voice set SexLab should use for this position.'}>
+          <Space.Compact>
+            <Input disabled value={'Voice'} style={{ width: 70 }} />
+            <AutoComplete
+              value={voice}
+              options={voices.map(it => ({ value: it }))}
+              onChange={(e) => setVoice(e)}
+              filterOption={(input, option) =>
+                option.value.toLowerCase().includes(input.toLowerCase())
+              }
+              placeholder="Default"
+              style={{ width: 180 }}
+            />
+          </Space.Compact>
+        </Tooltip>
       </Space>
     </Card>
   );
@@ -4,7 +4,7 @@ import { invoke } from "@tauri-apps/api/core"
 import ReactDOM from "react-dom/client";
 import { useImmer } from "use-immer";
 import { AlipaySquareFilled, FileDoneOutlined, TagsOutlined, SaveOutlined, TeamOutlined } from '@ant-design/icons';
-import { Input, Button, Tag, Space, Tooltip, InputNumber, Card, Layout, Divider, Menu, Row, Col, Tabs, TreeSelect, notification, Collapse, ConfigProvider, theme } from 'antd';
+import { Input, Button, Tag, Space, Tooltip, InputNumber, Card, Layout, Divider, Menu, Row, Col, Tabs, TreeSelect, notification, Collapse, ConfigProvider, theme, Checkbox } from 'antd';
 
 import { tagsSFW, tagsNSFW } from "./common/Tags"
 import PositionField from "./stage/PositionField";
@@ -65,6 +65,10 @@ function Editor({ _sceneId, _stage, _positions }) {
   const [tags, setTags] = useState(_stage.tags);
   const [fixedLen, setFixedLen] = useState(_stage.extra.fixed_len);
   const [navText, setNavText] = useState(_stage.extra.nav_text);
+  const [looping, setLooping] = useState(_stage.extra.looping ?? false);
+  const [enabled, setEnabled] = useState(_stage.enabled ?? true);
+  const [notes, setNotes] = useState(_stage.notes ?? '');
+  const [camera, setCamera] = useState(_stage.camera ?? null);
 
   useEffect(() => {
     // Listen for the toggle_darkmode event from Tauri
@@ -101,8 +105,32 @@ function Editor({ _sceneId, _stage, _positions }) {
     }
   }, []);
 
+  function makeStage() {
+    return {
+      ..._stage,
+      id: _stage.id,
+      name,
+      positions: positions.map(p => p.position),
+      tags,
+      enabled,
+      notes,
+      camera,
+      extra: {
+        ..._stage.extra,
+        fixed_len: fixedLen || 0.0,
+        nav_text: navText || '',
+        looping,
+      },
+    };
+  }
+
+  // Keep the backend informed about unsaved changes, so a closed editor can be reopened
+  useEffect(() => {
+    invoke('cache_stage_editor', { scene: _sceneId, positions: positions.map(p => p.info), stage: makeStage() })
+      .catch(e => console.log("Unable to cache stage editor", e));
+  }, [name, positions, tags, fixedLen, navText, looping, enabled, notes, camera]);
+
   function saveAndReturn() {
-    let positionArg = [];
     let positionsInfo = [];
     for (let i = 0; i < positions.length; i++) {
       const { position: stage_p, info: scene_p } = positions[i];
@@ -122,21 +150,18 @@ function Editor({ _sceneId, _stage, _positions }) {
         });
         return;
       }
-      positionArg.push(stage_p);
       positionsInfo.push(scene_p);
     }
-    const stage = {
-      id: _stage.id,
-      name,
-      positions: positionArg,
-      tags,
-      extra: {
-        fixed_len: fixedLen || 0.0,
-        nav_text: navText || '',
-      },
-    };
+    const stage = makeStage();
     console.log("Saving Stage... ", _sceneId, positionsInfo, stage);
-    invoke('stage_save_and_close', { scene: _sceneId, positions: positionsInfo, stage });
+    invoke('stage_save_and_close', { scene: _sceneId, positions: positionsInfo, stage })
+      .catch(e => {
+        api['error']({
+          message: 'Unable to save Stage',
+          description: e,
+          placement: 'bottomLeft'
+        });
+      });
   }
 
   const onPositionTabEdit = (targetKey, action) => {
@@ -245,6 +270,15 @@ function Editor({ _sceneId, _stage, _positions }) {
                   value={navText}
                   onChange={(e) => setNavText(e.target.value)}
                 ></TextArea>
+                <Tooltip title={'Disabled stages stay in the project but are left out of the export.'}>
+                  <Checkbox
+                    style={{ marginTop: 8 }}
+                    checked={enabled}
+                    onChange={(e) => setEnabled(e.target.checked)}
+                  >
+                    Enabled
+                  </Checkbox>
+                </Tooltip>
               </Card>
             </Col>
             <Col span={12}>
@@ -254,7 +288,7 @@ function Editor({ _sceneId, _stage, _positions }) {
                 extra={
                   <Tooltip
                     title={
-                      'Duration of an animation that should only play once (does not loop).'
+                      'Duration of an animation that should only play once (does not loop). Looping stages ignore it, but keep it for when looping is turned off.'
                     }
                   >
                     <Button type="link">Info</Button>
@@ -270,13 +304,69 @@ function Editor({ _sceneId, _stage, _positions }) {
                   min={0}
                   value={fixedLen ? fixedLen : undefined}
                   onChange={(e) => setFixedLen(e)}
+                  disabled={looping}
                   placeholder="0"
                   addonAfter={'ms'}
                   style={{ width: '100%' }}
                 />
+                <Checkbox
+                  style={{ marginTop: 8 }}
+                  checked={looping}
+                  onChange={(e) => setLooping(e.target.checked)}
+                >
+                  Loop
+                </Checkbox>
               </Card>
             </Col>
           </Row>
+          <Card
+            style={{ marginTop: 2 }}
+            title={'Camera'}
+            extra={
+              <Tooltip title={'Recommended camera placement relative to the scene origin. Angles are wrapped into range when saving.'}>
+                <Button type="link">Info</Button>
+              </Tooltip>
+            }
+          >
+            <Checkbox
+              checked={camera !== null}
+              onChange={(e) => setCamera(e.target.checked ? { x: 0, y: 0, z: 0, pitch: 0, yaw: 0 } : null)}
+            >
+              Use camera hint
+            </Checkbox>
+            {camera && (
+              <Space wrap style={{ marginTop: 8 }}>
+                {['x', 'y', 'z', 'pitch', 'yaw'].map(key => (
+                  <InputNumber
+                    key={key}
+                    addonBefore={key}
+                    controls
+                    precision={2}
+                    value={camera[key]}
+                    onChange={(value) => setCamera(prev => ({ ...prev, [key]: value ?? 0 }))}
+                    style={{ width: 140 }}
+                  />
+                ))}
+              </Space>
+            )}
+          </Card>
+          <Card
+            style={{ marginTop: 2 }}
+            title={'Notes'}
+            extra={
+              <Tooltip title={'Reminders for yourself, these are not exported.'}>
+                <Button type="link">Info</Button>
+              </Tooltip>
+            }
+          >
+            <TextArea
+              className="extra-notes-textarea"
+              rows={4}
+              style={{ width: '100%' }}
+              value={notes}
+              onChange={(e) => setNotes(e.target.value)}
+            ></TextArea>
+          </Card>
         </>
     }
   ]
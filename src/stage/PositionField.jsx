@@ -1,4 +1,5 @@
-import React, { useState } from "react";
+import React, { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
 import { Button, Card, Checkbox, Col, Input, Row, Select, Space, Tooltip, InputNumber, Dropdown } from "antd";
 import RaceSelect from "../components/RaceSelect";
 import './PositionField.css'
@@ -38,6 +39,12 @@ const makeStrips = (list = []) => {
 function PositionField({ position, info, onChange }) {
   const [basicAnim, setBasicAnim] = useState(true);
   const [workingAnim, setWorkingAnim] = useState(undefined);
+  // Offsets are stored in SexLab units, the position is shown in the user's display units
+  const [units, setUnits] = useState({ name: 'units', per_game_unit: 1.0 });
+
+  useEffect(() => {
+    invoke('get_display_units').then(setUnits);
+  }, []);
   const [sequenceOpen, setSequenceOpen] = useState(false);
 
   const makeSequenceMenu = (events) => {
@@ -300,10 +307,12 @@ function PositionField({ position, info, onChange }) {
                     decimalSeparator=","
                     precision={1}
                     step={0.1}
-                    value={position.offset[axis] ? position.offset[axis] : undefined}
+                    value={position.offset[axis] ? (axis === 'r' ? position.offset[axis] : position.offset[axis] * units.per_game_unit) : undefined}
                     onChange={(e) => {
-                      onChange({ ...position, offset: { ...position.offset, [axis]: e ? e : 0.0 } }, info);
+                      const value = e ? (axis === 'r' ? e : e / units.per_game_unit) : 0.0;
+                      onChange({ ...position, offset: { ...position.offset, [axis]: value } }, info);
                     }}
+                    addonAfter={axis === 'r' ? undefined : units.name}
                     placeholder="0.0"
                     min={axis === 'r' ? 0.0 : undefined}
                     max={axis === 'r' ? 359.9 : undefined}